@@ -1,11 +1,12 @@
-use failure::Fallible;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
 use std::fs::File;
-use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom};
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use tiffy::baseline::tags;
-use tiffy::lowlevel::{IFDField, MetadataReader, MetadataWriter, NativeEndian};
+use tiffy::lowlevel::{IFDField, MetadataReader, MetadataWriter};
+use tiffy::Result;
 
 /// Rewrite (copy) an image's tags and data
-fn main() -> Fallible<()> {
+fn main() -> Result<()> {
     // Parse arguments
     let mut args = std::env::args();
     let (source_path, dest_path) = match (args.next(), args.next(), args.next()) {
@@ -21,14 +22,28 @@ fn main() -> Fallible<()> {
     let mut source_file = BufReader::new(File::open(source_path)?);
     let mut dest_file = BufWriter::new(File::create(dest_path)?);
 
-    // Create helpers
+    // Write in the source's byte order, as opaque field data is kept in that order
     let ifd_reader = MetadataReader::read_header(&mut source_file)?;
-    let mut ifd_writer = MetadataWriter::<NativeEndian>::write_header(&mut dest_file)?;
+    if ifd_reader.is_little_endian() {
+        copy::<LittleEndian>(&ifd_reader, &mut source_file, &mut dest_file)
+    } else {
+        copy::<BigEndian>(&ifd_reader, &mut source_file, &mut dest_file)
+    }
+}
+
+/// Copy the IFDs read by `ifd_reader` and their strips from `source_file` into `dest_file`, in
+/// byte order `E`.
+fn copy<E: ByteOrder>(
+    ifd_reader: &MetadataReader,
+    source_file: &mut (impl Read + Seek),
+    dest_file: &mut (impl Write + Seek),
+) -> Result<()> {
+    let mut ifd_writer = MetadataWriter::<E>::write_header_format(dest_file, ifd_reader.format())?;
 
     for ifd in ifd_reader.ifds() {
         // Gather strip offsets and byte counts from the source image
-        let strip_offsets: &[u32] = ifd.get(tags::STRIP_OFFSETS)?;
-        let strip_lengths: &[u32] = ifd.get(tags::STRIP_BYTE_COUNTS)?;
+        let strip_offsets: Vec<u64> = ifd.get(tags::STRIP_OFFSETS)?;
+        let strip_lengths: Vec<u64> = ifd.get(tags::STRIP_BYTE_COUNTS)?;
 
         // Create buffers for strips and lengths produced by the ifd_writer
         let mut strip_offsets_out = Vec::new();
@@ -36,37 +51,32 @@ fn main() -> Fallible<()> {
 
         // For each original strip offset and length, copy the data to the output image
         for (offset, length) in strip_offsets.iter().zip(strip_lengths.iter()) {
-            source_file.seek(SeekFrom::Start(*offset as u64))?;
-            let strip_offset = dest_file.seek(SeekFrom::Current(0))? as u32;
-            std::io::copy(
-                &mut source_file.by_ref().take(*length as u64),
-                &mut dest_file,
-            )?;
+            source_file.seek(SeekFrom::Start(*offset))?;
+            let strip_offset = dest_file.stream_position()?;
+            std::io::copy(&mut source_file.by_ref().take(*length), dest_file)?;
 
             // Save the locations and lengths of the strips in the output image
             strip_offsets_out.push(strip_offset);
             strip_lengths_out.push(*length);
         }
 
+        // Fields of unrecognized types cannot be written, as there is no knowing where their data
+        // lives. Register the sizes of any vendor-specific types with
+        // `MetadataReader::read_header_with_sizes` to keep them.
         let mut new_ifd = ifd.clone();
-
-        // Careful about blindly copying tags between files, Unrecognized tags will write out the
-        // _literal_ information from the tag instead of reordering it and setting a new pointer in
-        // the file. This library _does_ give you enough rope to hang yourself if you so choose.
-        new_ifd.entries.retain(|_, field| match field {
-            IFDField::Unrecognized { .. } => false,
-            _ => true,
-        });
+        new_ifd
+            .entries
+            .retain(|_, field| !matches!(field, IFDField::Unrecognized { .. }));
 
         // Set the strip offsets and lengths on the output image (they are different from the original)
         *new_ifd.entries.get_mut(&tags::STRIP_OFFSETS).unwrap() =
-            IFDField::Long(strip_offsets_out.into_boxed_slice());
+            IFDField::offsets(strip_offsets_out);
 
         *new_ifd.entries.get_mut(&tags::STRIP_BYTE_COUNTS).unwrap() =
-            IFDField::Long(strip_lengths_out.into_boxed_slice());
+            IFDField::offsets(strip_lengths_out);
 
         // Write the modified IFD to output image
-        ifd_writer.write_ifd(&new_ifd, &mut dest_file)?;
+        ifd_writer.write_ifd(&new_ifd, dest_file)?;
     }
 
     Ok(())
@@ -81,3 +81,173 @@ pub mod photometic_interpretation {
         }
     }
 }
+
+/// Predictor magic
+pub mod predictor {
+    pub const NONE: u16 = 0x0001;
+    pub const HORIZONTAL_DIFFERENCING: u16 = 0x0002;
+    pub const FLOATING_POINT: u16 = 0x0003;
+
+    /// Return known predictor types as strings
+    pub fn to_string(ptype: u16) -> &'static str {
+        match ptype {
+            NONE => "None",
+            HORIZONTAL_DIFFERENCING => "Horizontal Differencing",
+            FLOATING_POINT => "Floating Point",
+            _ => "<Unrecognized>",
+        }
+    }
+}
+
+/// Sample format magic
+pub mod sample_format {
+    pub const UNSIGNED_INTEGER: u16 = 0x0001;
+    pub const SIGNED_INTEGER: u16 = 0x0002;
+    pub const IEEE_FLOATING_POINT: u16 = 0x0003;
+    pub const UNDEFINED: u16 = 0x0004;
+
+    /// Return known sample formats as strings
+    pub fn to_string(ftype: u16) -> &'static str {
+        match ftype {
+            UNSIGNED_INTEGER => "Unsigned Integer",
+            SIGNED_INTEGER => "Signed Integer",
+            IEEE_FLOATING_POINT => "IEEE Floating Point",
+            UNDEFINED => "Undefined",
+            _ => "<Unrecognized>",
+        }
+    }
+}
+
+/// Planar configuration magic
+pub mod planar_configuration {
+    pub const CHUNKY: u16 = 0x0001;
+    pub const PLANAR: u16 = 0x0002;
+
+    /// Return known planar configurations as strings
+    pub fn to_string(ctype: u16) -> &'static str {
+        match ctype {
+            CHUNKY => "Chunky",
+            PLANAR => "Planar",
+            _ => "<Unrecognized>",
+        }
+    }
+}
+
+/// Fill order magic
+pub mod fill_order {
+    pub const MSB_TO_LSB: u16 = 0x0001;
+    pub const LSB_TO_MSB: u16 = 0x0002;
+
+    /// Return known fill orders as strings
+    pub fn to_string(otype: u16) -> &'static str {
+        match otype {
+            MSB_TO_LSB => "Most Significant Bit First",
+            LSB_TO_MSB => "Least Significant Bit First",
+            _ => "<Unrecognized>",
+        }
+    }
+}
+
+/// Orientation magic, naming the corner of the image at the start of the first row and the
+/// direction of the rows
+pub mod orientation {
+    pub const TOP_LEFT: u16 = 0x0001;
+    pub const TOP_RIGHT: u16 = 0x0002;
+    pub const BOTTOM_RIGHT: u16 = 0x0003;
+    pub const BOTTOM_LEFT: u16 = 0x0004;
+    pub const LEFT_TOP: u16 = 0x0005;
+    pub const RIGHT_TOP: u16 = 0x0006;
+    pub const RIGHT_BOTTOM: u16 = 0x0007;
+    pub const LEFT_BOTTOM: u16 = 0x0008;
+
+    /// Return known orientations as strings
+    pub fn to_string(otype: u16) -> &'static str {
+        match otype {
+            TOP_LEFT => "Top Left",
+            TOP_RIGHT => "Top Right",
+            BOTTOM_RIGHT => "Bottom Right",
+            BOTTOM_LEFT => "Bottom Left",
+            LEFT_TOP => "Left Top",
+            RIGHT_TOP => "Right Top",
+            RIGHT_BOTTOM => "Right Bottom",
+            LEFT_BOTTOM => "Left Bottom",
+            _ => "<Unrecognized>",
+        }
+    }
+}
+
+/// Resolution unit magic
+pub mod resolution_unit {
+    pub const NONE: u16 = 0x0001;
+    pub const INCH: u16 = 0x0002;
+    pub const CENTIMETER: u16 = 0x0003;
+
+    /// Return known resolution units as strings
+    pub fn to_string(utype: u16) -> &'static str {
+        match utype {
+            NONE => "None",
+            INCH => "Inch",
+            CENTIMETER => "Centimeter",
+            _ => "<Unrecognized>",
+        }
+    }
+}
+
+/// Extra sample magic
+pub mod extra_samples {
+    pub const UNSPECIFIED: u16 = 0x0000;
+    pub const ASSOCIATED_ALPHA: u16 = 0x0001;
+    pub const UNASSOCIATED_ALPHA: u16 = 0x0002;
+
+    /// Return known extra sample types as strings
+    pub fn to_string(etype: u16) -> &'static str {
+        match etype {
+            UNSPECIFIED => "Unspecified",
+            ASSOCIATED_ALPHA => "Associated Alpha",
+            UNASSOCIATED_ALPHA => "Unassociated Alpha",
+            _ => "<Unrecognized>",
+        }
+    }
+}
+
+/// Ink set magic
+pub mod ink_set {
+    pub const CMYK: u16 = 0x0001;
+    pub const NOT_CMYK: u16 = 0x0002;
+
+    /// Return known ink sets as strings
+    pub fn to_string(itype: u16) -> &'static str {
+        match itype {
+            CMYK => "CMYK",
+            NOT_CMYK => "Not CMYK",
+            _ => "<Unrecognized>",
+        }
+    }
+}
+
+/// YCbCr positioning magic
+pub mod ycbcr_positioning {
+    pub const CENTERED: u16 = 0x0001;
+    pub const COSITED: u16 = 0x0002;
+
+    /// Return known YCbCr positionings as strings
+    pub fn to_string(ptype: u16) -> &'static str {
+        match ptype {
+            CENTERED => "Centered",
+            COSITED => "Cosited",
+            _ => "<Unrecognized>",
+        }
+    }
+}
+
+/// Bit flags of the `T4_OPTIONS` tag
+pub mod t4_options {
+    pub const TWO_DIMENSIONAL: u32 = 0x0001;
+    pub const UNCOMPRESSED: u32 = 0x0002;
+    pub const FILL_BITS: u32 = 0x0004;
+}
+
+/// Bit flags of the `T6_OPTIONS` tag
+pub mod t6_options {
+    pub const UNCOMPRESSED: u32 = 0x0002;
+}
@@ -0,0 +1,274 @@
+use crate::baseline::constants::{
+    compression, fill_order, orientation, photometic_interpretation, planar_configuration,
+    predictor, resolution_unit, sample_format,
+};
+use crate::baseline::tags;
+use crate::errors::FieldExtractionError;
+use crate::lowlevel::IFD;
+use std::convert::TryFrom;
+
+/// Define an enum of the values of a baseline tag, named after the constants of `constants`,
+/// with a fallback for values without a variant.
+macro_rules! baseline_enum {
+    { $(#[$meta:meta])* $name:ident { $($(#[$variant_meta:meta])* $variant:ident = $value:path,)* } } => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)*
+            /// A value without a variant of its own.
+            Other(u16),
+        }
+
+        impl From<u16> for $name {
+            fn from(value: u16) -> Self {
+                match value {
+                    $($value => $name::$variant,)*
+                    value => $name::Other(value),
+                }
+            }
+        }
+
+        impl From<$name> for u16 {
+            fn from(value: $name) -> Self {
+                match value {
+                    $($name::$variant => $value,)*
+                    $name::Other(value) => value,
+                }
+            }
+        }
+    };
+}
+
+baseline_enum! {
+    /// Value of the `COMPRESSION` tag.
+    Compression {
+        Uncompressed = compression::UNCOMPRESSED,
+        Ccitt1D = compression::CCITT_1D,
+        Group3Fax = compression::GROUP_3_FAX,
+        Group4Fax = compression::GROUP_4_FAX,
+        Lzw = compression::LZW,
+        /// Old-style JPEG, as described by TIFF 6.0.
+        Jpeg = compression::JPEG,
+        /// JPEG as described by TIFF Technical Note 2.
+        NewJpeg = compression::NEW_JPEG,
+        AdobeDeflate = compression::ADOBE_DEFLATE,
+        PackBits = compression::PACKBITS,
+        DeflatePkzip = compression::DEFLATE_PKZIP,
+    }
+}
+
+baseline_enum! {
+    /// Value of the `PHOTOMETRIC_INTERPRETATION` tag.
+    Photometric {
+        WhiteIsZero = photometic_interpretation::WHITEISZERO,
+        BlackIsZero = photometic_interpretation::BLACKISZERO,
+        Rgb = photometic_interpretation::RGB,
+        RgbPalette = photometic_interpretation::RGB_PALETTE,
+        TransparencyMask = photometic_interpretation::TRANSPARENCY_MASK,
+        Cmyk = photometic_interpretation::CMYK,
+        YCbCr = photometic_interpretation::YCBCR,
+        CieLab = photometic_interpretation::CIELAB,
+    }
+}
+
+baseline_enum! {
+    /// Value of the `ORIENTATION` tag, naming the corner of the image at the start of the first
+    /// row and the direction of the rows.
+    Orientation {
+        TopLeft = orientation::TOP_LEFT,
+        TopRight = orientation::TOP_RIGHT,
+        BottomRight = orientation::BOTTOM_RIGHT,
+        BottomLeft = orientation::BOTTOM_LEFT,
+        LeftTop = orientation::LEFT_TOP,
+        RightTop = orientation::RIGHT_TOP,
+        RightBottom = orientation::RIGHT_BOTTOM,
+        LeftBottom = orientation::LEFT_BOTTOM,
+    }
+}
+
+baseline_enum! {
+    /// Value of the `RESOLUTION_UNIT` tag.
+    ResolutionUnit {
+        None = resolution_unit::NONE,
+        Inch = resolution_unit::INCH,
+        Centimeter = resolution_unit::CENTIMETER,
+    }
+}
+
+baseline_enum! {
+    /// Value of the `PLANAR_CONFIGURATION` tag.
+    PlanarConfiguration {
+        Chunky = planar_configuration::CHUNKY,
+        Planar = planar_configuration::PLANAR,
+    }
+}
+
+baseline_enum! {
+    /// Value of the `SAMPLE_FORMAT` tag.
+    SampleFormat {
+        UnsignedInteger = sample_format::UNSIGNED_INTEGER,
+        SignedInteger = sample_format::SIGNED_INTEGER,
+        IeeeFloatingPoint = sample_format::IEEE_FLOATING_POINT,
+        Undefined = sample_format::UNDEFINED,
+    }
+}
+
+baseline_enum! {
+    /// Value of the `FILL_ORDER` tag.
+    FillOrder {
+        MsbToLsb = fill_order::MSB_TO_LSB,
+        LsbToMsb = fill_order::LSB_TO_MSB,
+    }
+}
+
+baseline_enum! {
+    /// Value of the `PREDICTOR` tag.
+    PredictorType {
+        None = predictor::NONE,
+        HorizontalDifferencing = predictor::HORIZONTAL_DIFFERENCING,
+        FloatingPoint = predictor::FLOATING_POINT,
+    }
+}
+
+/// Number of pixels per unit along each axis, from the `X_RESOLUTION`, `Y_RESOLUTION` and
+/// `RESOLUTION_UNIT` tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resolution {
+    pub x: (u32, u32),
+    pub y: (u32, u32),
+    pub unit: ResolutionUnit,
+}
+
+/// Typed accessors for the baseline tags. Unsigned integer tags may be stored as BYTE, SHORT,
+/// LONG or LONG8, and missing tags with a default in the TIFF specification read as that default.
+impl IFD {
+    /// Number of columns of the image.
+    pub fn width(&self) -> Result<u32, FieldExtractionError> {
+        self.unsigned(tags::IMAGE_WIDTH)
+    }
+
+    /// Number of rows of the image.
+    pub fn height(&self) -> Result<u32, FieldExtractionError> {
+        self.unsigned(tags::IMAGE_LENGTH)
+    }
+
+    /// Number of samples per pixel, 1 by default.
+    pub fn samples_per_pixel(&self) -> Result<u16, FieldExtractionError> {
+        self.unsigned_or(tags::SAMPLES_PER_PIXEL, 1)
+    }
+
+    /// Number of bits of each sample of a pixel, 1 for every sample by default.
+    pub fn bits_per_sample(&self) -> Result<Vec<u16>, FieldExtractionError> {
+        match self.unsigned_values(tags::BITS_PER_SAMPLE) {
+            Err(FieldExtractionError::MissingTag { .. }) => {
+                Ok(vec![1; usize::from(self.samples_per_pixel()?)])
+            }
+            result => result,
+        }
+    }
+
+    /// Format of each sample of a pixel, unsigned integers for every sample by default.
+    pub fn sample_format(&self) -> Result<Vec<SampleFormat>, FieldExtractionError> {
+        let values = match self.unsigned_values(tags::SAMPLE_FORMAT) {
+            Err(FieldExtractionError::MissingTag { .. }) => {
+                vec![sample_format::UNSIGNED_INTEGER; usize::from(self.samples_per_pixel()?)]
+            }
+            result => result?,
+        };
+        Ok(values.into_iter().map(SampleFormat::from).collect())
+    }
+
+    /// Compression scheme of the image data, uncompressed by default.
+    pub fn compression(&self) -> Result<Compression, FieldExtractionError> {
+        self.unsigned_or(tags::COMPRESSION, compression::UNCOMPRESSED)
+            .map(Compression::from)
+    }
+
+    /// Colour space of the image data, which has no default.
+    pub fn photometric(&self) -> Result<Photometric, FieldExtractionError> {
+        self.unsigned::<u16>(tags::PHOTOMETRIC_INTERPRETATION)
+            .map(Photometric::from)
+    }
+
+    /// Orientation of the rows and columns of the image, top left by default.
+    pub fn orientation(&self) -> Result<Orientation, FieldExtractionError> {
+        self.unsigned_or(tags::ORIENTATION, orientation::TOP_LEFT)
+            .map(Orientation::from)
+    }
+
+    /// Whether samples are interleaved or stored in separate planes, interleaved by default.
+    pub fn planar_configuration(&self) -> Result<PlanarConfiguration, FieldExtractionError> {
+        self.unsigned_or(tags::PLANAR_CONFIGURATION, planar_configuration::CHUNKY)
+            .map(PlanarConfiguration::from)
+    }
+
+    /// Order of the bits within each byte, most significant first by default.
+    pub fn fill_order(&self) -> Result<FillOrder, FieldExtractionError> {
+        self.unsigned_or(tags::FILL_ORDER, fill_order::MSB_TO_LSB)
+            .map(FillOrder::from)
+    }
+
+    /// Predictor applied before compression, none by default.
+    pub fn predictor(&self) -> Result<PredictorType, FieldExtractionError> {
+        self.unsigned_or(tags::PREDICTOR, predictor::NONE)
+            .map(PredictorType::from)
+    }
+
+    /// Number of rows in each strip, the whole image by default. Counts beyond 32 bits also
+    /// mean the whole image.
+    pub fn rows_per_strip(&self) -> Result<u32, FieldExtractionError> {
+        self.unsigned_or(tags::ROWS_PER_STRIP, u64::from(u32::MAX))
+            .map(|rows| u32::try_from(rows).unwrap_or(u32::MAX))
+    }
+
+    /// Resolution of the image, or `None` if either axis is missing. The unit is inches by
+    /// default.
+    pub fn resolution(&self) -> Result<Option<Resolution>, FieldExtractionError> {
+        let x = match self.get(tags::X_RESOLUTION) {
+            Err(FieldExtractionError::MissingTag { .. }) => return Ok(None),
+            result => result?,
+        };
+        let y = match self.get(tags::Y_RESOLUTION) {
+            Err(FieldExtractionError::MissingTag { .. }) => return Ok(None),
+            result => result?,
+        };
+        let unit = self
+            .unsigned_or(tags::RESOLUTION_UNIT, resolution_unit::INCH)
+            .map(ResolutionUnit::from)?;
+        Ok(Some(Resolution { x, y, unit }))
+    }
+
+    /// Read all values of an unsigned integer field, which must fit in `T`.
+    pub(crate) fn unsigned_values<T: TryFrom<u64>>(
+        &self,
+        tag: u16,
+    ) -> Result<Vec<T>, FieldExtractionError> {
+        let values: Vec<u64> = self.get(tag)?;
+        values
+            .into_iter()
+            .map(|value| {
+                T::try_from(value).map_err(|_| FieldExtractionError::WrongDataType { tag })
+            })
+            .collect()
+    }
+
+    /// Read the first value of an unsigned integer field, which must fit in `T`.
+    pub(crate) fn unsigned<T: TryFrom<u64>>(&self, tag: u16) -> Result<T, FieldExtractionError> {
+        self.unsigned_values(tag)?
+            .into_iter()
+            .next()
+            .ok_or(FieldExtractionError::InsufficientData { tag })
+    }
+
+    /// Read the first value of an unsigned integer field, or `default` if it is absent.
+    pub(crate) fn unsigned_or<T: TryFrom<u64>>(
+        &self,
+        tag: u16,
+        default: T,
+    ) -> Result<T, FieldExtractionError> {
+        match self.unsigned(tag) {
+            Err(FieldExtractionError::MissingTag { .. }) => Ok(default),
+            result => result,
+        }
+    }
+}
@@ -3,3 +3,7 @@ pub mod constants;
 
 /// Integer values of tags
 pub mod tags;
+
+/// Typed values of baseline tags, and accessors for them on IFDs
+pub(crate) mod fields;
+pub use fields::*;
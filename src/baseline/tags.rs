@@ -48,6 +48,7 @@ pub const TILE_WIDTH: u16 = 322;
 pub const TILE_LENGTH: u16 = 323;
 pub const TILE_OFFSETS: u16 = 324;
 pub const TILE_BYTE_COUNTS: u16 = 325;
+pub const SUB_IFDS: u16 = 330;
 pub const INK_SET: u16 = 332;
 pub const INK_NAMES: u16 = 333;
 pub const NUMBER_OF_INKS: u16 = 334;
@@ -58,6 +59,7 @@ pub const SAMPLE_FORMAT: u16 = 339;
 pub const S_MIN_SAMPLE_VALUE: u16 = 340;
 pub const S_MAX_SAMPLE_VALUE: u16 = 341;
 pub const TRANSFER_RANGE: u16 = 342;
+pub const JPEG_TABLES: u16 = 347;
 pub const JPEG_PROC: u16 = 512;
 pub const JPEG_INTERCHANGE_FORMAT: u16 = 513;
 pub const JPEG_INTERCHANGE_FORMAT_LNGTH: u16 = 514;
@@ -72,3 +74,129 @@ pub const Y_CBCR_SUB_SAMPLING: u16 = 530;
 pub const Y_CBCR_POSITIONING: u16 = 531;
 pub const REFERENCE_BLACK_WHITE: u16 = 532;
 pub const COPYRIGHT: u16 = 33432;
+pub const MODEL_PIXEL_SCALE: u16 = 33550;
+pub const MODEL_TIEPOINT: u16 = 33922;
+pub const MODEL_TRANSFORMATION: u16 = 34264;
+pub const EXIF_IFD: u16 = 34665;
+pub const GEO_KEY_DIRECTORY: u16 = 34735;
+pub const GEO_DOUBLE_PARAMS: u16 = 34736;
+pub const GEO_ASCII_PARAMS: u16 = 34737;
+pub const GPS_IFD: u16 = 34853;
+
+/// Tags of the EXIF private IFD, pointed to by `EXIF_IFD`, and of its Interoperability IFD
+pub mod exif {
+    pub const EXPOSURE_TIME: u16 = 33434;
+    pub const F_NUMBER: u16 = 33437;
+    pub const EXPOSURE_PROGRAM: u16 = 34850;
+    pub const SPECTRAL_SENSITIVITY: u16 = 34852;
+    pub const PHOTOGRAPHIC_SENSITIVITY: u16 = 34855;
+    pub const OECF: u16 = 34856;
+    pub const SENSITIVITY_TYPE: u16 = 34864;
+    pub const STANDARD_OUTPUT_SENSITIVITY: u16 = 34865;
+    pub const RECOMMENDED_EXPOSURE_INDEX: u16 = 34866;
+    pub const ISO_SPEED: u16 = 34867;
+    pub const EXIF_VERSION: u16 = 36864;
+    pub const DATE_TIME_ORIGINAL: u16 = 36867;
+    pub const DATE_TIME_DIGITIZED: u16 = 36868;
+    pub const OFFSET_TIME: u16 = 36880;
+    pub const OFFSET_TIME_ORIGINAL: u16 = 36881;
+    pub const OFFSET_TIME_DIGITIZED: u16 = 36882;
+    pub const COMPONENTS_CONFIGURATION: u16 = 37121;
+    pub const COMPRESSED_BITS_PER_PIXEL: u16 = 37122;
+    pub const SHUTTER_SPEED_VALUE: u16 = 37377;
+    pub const APERTURE_VALUE: u16 = 37378;
+    pub const BRIGHTNESS_VALUE: u16 = 37379;
+    pub const EXPOSURE_BIAS_VALUE: u16 = 37380;
+    pub const MAX_APERTURE_VALUE: u16 = 37381;
+    pub const SUBJECT_DISTANCE: u16 = 37382;
+    pub const METERING_MODE: u16 = 37383;
+    pub const LIGHT_SOURCE: u16 = 37384;
+    pub const FLASH: u16 = 37385;
+    pub const FOCAL_LENGTH: u16 = 37386;
+    pub const SUBJECT_AREA: u16 = 37396;
+    pub const MAKER_NOTE: u16 = 37500;
+    pub const USER_COMMENT: u16 = 37510;
+    pub const SUB_SEC_TIME: u16 = 37520;
+    pub const SUB_SEC_TIME_ORIGINAL: u16 = 37521;
+    pub const SUB_SEC_TIME_DIGITIZED: u16 = 37522;
+    pub const FLASHPIX_VERSION: u16 = 40960;
+    pub const COLOR_SPACE: u16 = 40961;
+    pub const PIXEL_X_DIMENSION: u16 = 40962;
+    pub const PIXEL_Y_DIMENSION: u16 = 40963;
+    pub const RELATED_SOUND_FILE: u16 = 40964;
+    pub const INTEROPERABILITY_IFD: u16 = 40965;
+    pub const FLASH_ENERGY: u16 = 41483;
+    pub const SPATIAL_FREQUENCY_RESPONSE: u16 = 41484;
+    pub const FOCAL_PLANE_X_RESOLUTION: u16 = 41486;
+    pub const FOCAL_PLANE_Y_RESOLUTION: u16 = 41487;
+    pub const FOCAL_PLANE_RESOLUTION_UNIT: u16 = 41488;
+    pub const SUBJECT_LOCATION: u16 = 41492;
+    pub const EXPOSURE_INDEX: u16 = 41493;
+    pub const SENSING_METHOD: u16 = 41495;
+    pub const FILE_SOURCE: u16 = 41728;
+    pub const SCENE_TYPE: u16 = 41729;
+    pub const CFA_PATTERN: u16 = 41730;
+    pub const CUSTOM_RENDERED: u16 = 41985;
+    pub const EXPOSURE_MODE: u16 = 41986;
+    pub const WHITE_BALANCE: u16 = 41987;
+    pub const DIGITAL_ZOOM_RATIO: u16 = 41988;
+    pub const FOCAL_LENGTH_IN_35MM_FILM: u16 = 41989;
+    pub const SCENE_CAPTURE_TYPE: u16 = 41990;
+    pub const GAIN_CONTROL: u16 = 41991;
+    pub const CONTRAST: u16 = 41992;
+    pub const SATURATION: u16 = 41993;
+    pub const SHARPNESS: u16 = 41994;
+    pub const DEVICE_SETTING_DESCRIPTION: u16 = 41995;
+    pub const SUBJECT_DISTANCE_RANGE: u16 = 41996;
+    pub const IMAGE_UNIQUE_ID: u16 = 42016;
+    pub const CAMERA_OWNER_NAME: u16 = 42032;
+    pub const BODY_SERIAL_NUMBER: u16 = 42033;
+    pub const LENS_SPECIFICATION: u16 = 42034;
+    pub const LENS_MAKE: u16 = 42035;
+    pub const LENS_MODEL: u16 = 42036;
+    pub const LENS_SERIAL_NUMBER: u16 = 42037;
+    pub const GAMMA: u16 = 42240;
+
+    // Interoperability IFD
+    pub const INTEROPERABILITY_INDEX: u16 = 1;
+    pub const INTEROPERABILITY_VERSION: u16 = 2;
+    pub const RELATED_IMAGE_FILE_FORMAT: u16 = 4096;
+    pub const RELATED_IMAGE_WIDTH: u16 = 4097;
+    pub const RELATED_IMAGE_LENGTH: u16 = 4098;
+}
+
+/// Tags of the GPS info IFD, pointed to by `GPS_IFD`
+pub mod gps {
+    pub const GPS_VERSION_ID: u16 = 0;
+    pub const GPS_LATITUDE_REF: u16 = 1;
+    pub const GPS_LATITUDE: u16 = 2;
+    pub const GPS_LONGITUDE_REF: u16 = 3;
+    pub const GPS_LONGITUDE: u16 = 4;
+    pub const GPS_ALTITUDE_REF: u16 = 5;
+    pub const GPS_ALTITUDE: u16 = 6;
+    pub const GPS_TIME_STAMP: u16 = 7;
+    pub const GPS_SATELLITES: u16 = 8;
+    pub const GPS_STATUS: u16 = 9;
+    pub const GPS_MEASURE_MODE: u16 = 10;
+    pub const GPS_DOP: u16 = 11;
+    pub const GPS_SPEED_REF: u16 = 12;
+    pub const GPS_SPEED: u16 = 13;
+    pub const GPS_TRACK_REF: u16 = 14;
+    pub const GPS_TRACK: u16 = 15;
+    pub const GPS_IMG_DIRECTION_REF: u16 = 16;
+    pub const GPS_IMG_DIRECTION: u16 = 17;
+    pub const GPS_MAP_DATUM: u16 = 18;
+    pub const GPS_DEST_LATITUDE_REF: u16 = 19;
+    pub const GPS_DEST_LATITUDE: u16 = 20;
+    pub const GPS_DEST_LONGITUDE_REF: u16 = 21;
+    pub const GPS_DEST_LONGITUDE: u16 = 22;
+    pub const GPS_DEST_BEARING_REF: u16 = 23;
+    pub const GPS_DEST_BEARING: u16 = 24;
+    pub const GPS_DEST_DISTANCE_REF: u16 = 25;
+    pub const GPS_DEST_DISTANCE: u16 = 26;
+    pub const GPS_PROCESSING_METHOD: u16 = 27;
+    pub const GPS_AREA_INFORMATION: u16 = 28;
+    pub const GPS_DATE_STAMP: u16 = 29;
+    pub const GPS_DIFFERENTIAL: u16 = 30;
+    pub const GPS_H_POSITIONING_ERROR: u16 = 31;
+}
@@ -0,0 +1,719 @@
+use crate::baseline::constants::{compression, t4_options, t6_options};
+use crate::compression::{BlockDimensions, Codec};
+use crate::errors::{CompressionError, Result};
+use std::sync::OnceLock;
+
+/// Codes of white runs of 0 through 63 pixels, as (code, length in bits).
+const WHITE_TERMINATING: [(u16, u8); 64] = [
+    (0x35, 8),
+    (0x07, 6),
+    (0x07, 4),
+    (0x08, 4),
+    (0x0B, 4),
+    (0x0C, 4),
+    (0x0E, 4),
+    (0x0F, 4),
+    (0x13, 5),
+    (0x14, 5),
+    (0x07, 5),
+    (0x08, 5),
+    (0x08, 6),
+    (0x03, 6),
+    (0x34, 6),
+    (0x35, 6),
+    (0x2A, 6),
+    (0x2B, 6),
+    (0x27, 7),
+    (0x0C, 7),
+    (0x08, 7),
+    (0x17, 7),
+    (0x03, 7),
+    (0x04, 7),
+    (0x28, 7),
+    (0x2B, 7),
+    (0x13, 7),
+    (0x24, 7),
+    (0x18, 7),
+    (0x02, 8),
+    (0x03, 8),
+    (0x1A, 8),
+    (0x1B, 8),
+    (0x12, 8),
+    (0x13, 8),
+    (0x14, 8),
+    (0x15, 8),
+    (0x16, 8),
+    (0x17, 8),
+    (0x28, 8),
+    (0x29, 8),
+    (0x2A, 8),
+    (0x2B, 8),
+    (0x2C, 8),
+    (0x2D, 8),
+    (0x04, 8),
+    (0x05, 8),
+    (0x0A, 8),
+    (0x0B, 8),
+    (0x52, 8),
+    (0x53, 8),
+    (0x54, 8),
+    (0x55, 8),
+    (0x24, 8),
+    (0x25, 8),
+    (0x58, 8),
+    (0x59, 8),
+    (0x5A, 8),
+    (0x5B, 8),
+    (0x4A, 8),
+    (0x4B, 8),
+    (0x32, 8),
+    (0x33, 8),
+    (0x34, 8),
+];
+
+/// Codes of white runs of 64 through 1728 pixels in steps of 64.
+const WHITE_MAKEUP: [(u16, u8); 27] = [
+    (0x1B, 5),
+    (0x12, 5),
+    (0x17, 6),
+    (0x37, 7),
+    (0x36, 8),
+    (0x37, 8),
+    (0x64, 8),
+    (0x65, 8),
+    (0x68, 8),
+    (0x67, 8),
+    (0xCC, 9),
+    (0xCD, 9),
+    (0xD2, 9),
+    (0xD3, 9),
+    (0xD4, 9),
+    (0xD5, 9),
+    (0xD6, 9),
+    (0xD7, 9),
+    (0xD8, 9),
+    (0xD9, 9),
+    (0xDA, 9),
+    (0xDB, 9),
+    (0x98, 9),
+    (0x99, 9),
+    (0x9A, 9),
+    (0x18, 6),
+    (0x9B, 9),
+];
+
+/// Codes of black runs of 0 through 63 pixels.
+const BLACK_TERMINATING: [(u16, u8); 64] = [
+    (0x37, 10),
+    (0x02, 3),
+    (0x03, 2),
+    (0x02, 2),
+    (0x03, 3),
+    (0x03, 4),
+    (0x02, 4),
+    (0x03, 5),
+    (0x05, 6),
+    (0x04, 6),
+    (0x04, 7),
+    (0x05, 7),
+    (0x07, 7),
+    (0x04, 8),
+    (0x07, 8),
+    (0x18, 9),
+    (0x17, 10),
+    (0x18, 10),
+    (0x08, 10),
+    (0x67, 11),
+    (0x68, 11),
+    (0x6C, 11),
+    (0x37, 11),
+    (0x28, 11),
+    (0x17, 11),
+    (0x18, 11),
+    (0xCA, 12),
+    (0xCB, 12),
+    (0xCC, 12),
+    (0xCD, 12),
+    (0x68, 12),
+    (0x69, 12),
+    (0x6A, 12),
+    (0x6B, 12),
+    (0xD2, 12),
+    (0xD3, 12),
+    (0xD4, 12),
+    (0xD5, 12),
+    (0xD6, 12),
+    (0xD7, 12),
+    (0x6C, 12),
+    (0x6D, 12),
+    (0xDA, 12),
+    (0xDB, 12),
+    (0x54, 12),
+    (0x55, 12),
+    (0x56, 12),
+    (0x57, 12),
+    (0x64, 12),
+    (0x65, 12),
+    (0x52, 12),
+    (0x53, 12),
+    (0x24, 12),
+    (0x37, 12),
+    (0x38, 12),
+    (0x27, 12),
+    (0x28, 12),
+    (0x58, 12),
+    (0x59, 12),
+    (0x2B, 12),
+    (0x2C, 12),
+    (0x5A, 12),
+    (0x66, 12),
+    (0x67, 12),
+];
+
+/// Codes of black runs of 64 through 1728 pixels in steps of 64.
+const BLACK_MAKEUP: [(u16, u8); 27] = [
+    (0x0F, 10),
+    (0xC8, 12),
+    (0xC9, 12),
+    (0x5B, 12),
+    (0x33, 12),
+    (0x34, 12),
+    (0x35, 12),
+    (0x6C, 13),
+    (0x6D, 13),
+    (0x4A, 13),
+    (0x4B, 13),
+    (0x4C, 13),
+    (0x4D, 13),
+    (0x72, 13),
+    (0x73, 13),
+    (0x74, 13),
+    (0x75, 13),
+    (0x76, 13),
+    (0x77, 13),
+    (0x52, 13),
+    (0x53, 13),
+    (0x54, 13),
+    (0x55, 13),
+    (0x5A, 13),
+    (0x5B, 13),
+    (0x64, 13),
+    (0x65, 13),
+];
+
+/// Codes of runs of either colour of 1792 through 2560 pixels in steps of 64.
+const EXTENDED_MAKEUP: [(u16, u8); 13] = [
+    (0x08, 11),
+    (0x0C, 11),
+    (0x0D, 11),
+    (0x12, 12),
+    (0x13, 12),
+    (0x14, 12),
+    (0x15, 12),
+    (0x16, 12),
+    (0x17, 12),
+    (0x1C, 12),
+    (0x1D, 12),
+    (0x1E, 12),
+    (0x1F, 12),
+];
+
+/// The end-of-line code, eleven zeros followed by a one.
+const EOL: (u16, u8) = (0x001, 12);
+
+/// Longest run code, in bits.
+const MAX_RUN_CODE_BITS: u8 = 13;
+
+/// The longest run described by a single makeup code.
+const MAX_MAKEUP: u32 = 2560;
+
+/// Codes of the two-dimensional coding modes.
+const PASS: (u16, u8) = (0x1, 4);
+const HORIZONTAL: (u16, u8) = (0x1, 3);
+/// Vertical mode codes for a1 - b1 of -3 through 3.
+const VERTICAL: [(u16, u8); 7] = [
+    (0x02, 7),
+    (0x02, 6),
+    (0x02, 3),
+    (0x1, 1),
+    (0x03, 3),
+    (0x03, 6),
+    (0x03, 7),
+];
+/// Prefix of the extension codes, such as that entering uncompressed mode.
+const EXTENSION: (u16, u8) = (0x01, 7);
+
+/// Rows of Group 3 two-dimensional data coded one-dimensionally, limiting how far errors spread.
+const GROUP_3_K: u32 = 4;
+
+/// A coding mode of a two-dimensionally coded row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Pass,
+    Horizontal,
+    Vertical(i32),
+    Extension,
+}
+
+/// A decoded run code: the length of the run, and whether another code follows.
+#[derive(Debug, Clone, Copy)]
+struct RunCode {
+    bits: u8,
+    run: u16,
+    makeup: bool,
+}
+
+/// CCITT bilevel fax compression: Modified Huffman (Compression = 2), T.4 also known as Group 3
+/// (Compression = 3) and T.6 also known as Group 4 (Compression = 4).
+///
+/// Data is decoded into rows of one bit per pixel, with white runs as zeros and black runs as
+/// ones, as suits a `PHOTOMETRIC_INTERPRETATION` of WhiteIsZero. Uncompressed mode is not
+/// supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fax {
+    /// Value of the `COMPRESSION` tag.
+    compression: u16,
+    /// Value of the `T4_OPTIONS` or `T6_OPTIONS` tag.
+    options: u32,
+}
+
+impl Fax {
+    /// Create a codec for a value of the `COMPRESSION` tag, with `options` holding the value of
+    /// the `T4_OPTIONS` tag for Group 3 or of `T6_OPTIONS` for Group 4. When compressing with
+    /// options other than 0, the tag must be added to the IFD describing the image.
+    pub fn new(compression: u16, options: u32) -> Result<Self> {
+        let known = match compression {
+            compression::CCITT_1D => 0,
+            compression::GROUP_3_FAX => {
+                t4_options::TWO_DIMENSIONAL | t4_options::UNCOMPRESSED | t4_options::FILL_BITS
+            }
+            compression::GROUP_4_FAX => t6_options::UNCOMPRESSED,
+            _ => return Err(CompressionError::UnsupportedCompression { compression }.into()),
+        };
+        if options & !known != 0 {
+            return Err(CompressionError::UnsupportedFaxOptions { options }.into());
+        }
+        Ok(Self {
+            compression,
+            options,
+        })
+    }
+
+    /// The value of the `T4_OPTIONS` or `T6_OPTIONS` tag.
+    pub fn options(&self) -> u32 {
+        self.options
+    }
+
+    /// Whether Group 3 rows may be coded two-dimensionally.
+    fn two_dimensional(&self) -> bool {
+        self.compression == compression::GROUP_3_FAX
+            && self.options & t4_options::TWO_DIMENSIONAL != 0
+    }
+}
+
+impl Codec for Fax {
+    fn decompress(&self, input: &[u8], block: &BlockDimensions) -> Result<Vec<u8>> {
+        check_layout(block)?;
+        let width = block.width;
+        let mut reader = BitReader::new(input);
+        let mut output = vec![0; block.byte_count()?];
+        // The first row is coded relative to an imaginary white row
+        let mut reference = Vec::new();
+        for (row, pixels) in output.chunks_mut(block.row_bytes.max(1)).enumerate() {
+            let row = row as u32;
+            let changes = match self.compression {
+                compression::CCITT_1D => {
+                    reader.align();
+                    decode_1d(&mut reader, width, row)?
+                }
+                compression::GROUP_3_FAX => {
+                    reader.skip_eols();
+                    if self.two_dimensional() && reader.read(1) == 0 {
+                        decode_2d(&mut reader, width, &reference, row)?
+                    } else {
+                        decode_1d(&mut reader, width, row)?
+                    }
+                }
+                _ => decode_2d(&mut reader, width, &reference, row)?,
+            };
+            fill_row(&changes, width, pixels);
+            reference = changes;
+        }
+        Ok(output)
+    }
+
+    fn compress(&self, input: &[u8], block: &BlockDimensions) -> Result<Vec<u8>> {
+        check_layout(block)?;
+        let width = block.width;
+        let mut writer = BitWriter::default();
+        let mut reference = Vec::new();
+        for (row, pixels) in input.chunks(block.row_bytes.max(1)).enumerate() {
+            let changes = row_changes(pixels, width);
+            match self.compression {
+                compression::CCITT_1D => {
+                    encode_1d(&mut writer, &changes, width);
+                    writer.align();
+                }
+                compression::GROUP_3_FAX => {
+                    // With fill bits, each EOL ends on a byte boundary
+                    if self.options & t4_options::FILL_BITS != 0 {
+                        writer.pad_to(EOL.1);
+                    }
+                    writer.write(EOL);
+                    if self.two_dimensional() {
+                        let one_dimensional = (row as u32).is_multiple_of(GROUP_3_K);
+                        writer.write((one_dimensional as u16, 1));
+                        if !one_dimensional {
+                            encode_2d(&mut writer, &changes, &reference, width);
+                            reference = changes;
+                            continue;
+                        }
+                    }
+                    encode_1d(&mut writer, &changes, width);
+                }
+                _ => encode_2d(&mut writer, &changes, &reference, width),
+            }
+            reference = changes;
+        }
+        if self.compression == compression::GROUP_4_FAX {
+            // End of facsimile block
+            writer.write(EOL);
+            writer.write(EOL);
+        }
+        writer.align();
+        Ok(writer.output)
+    }
+}
+
+/// Fail unless `block` holds rows of one bit per pixel.
+fn check_layout(block: &BlockDimensions) -> Result<()> {
+    if block.row_bytes != (block.width as usize).div_ceil(8) {
+        return Err(CompressionError::InvalidFaxLayout.into());
+    }
+    Ok(())
+}
+
+/// The positions of the pixels in a row of one bit per pixel whose colour differs from the
+/// pixel before, starting from white.
+fn row_changes(pixels: &[u8], width: u32) -> Vec<u32> {
+    let mut changes = Vec::new();
+    let mut color = 0;
+    for x in 0..width {
+        let bit = pixels[x as usize / 8] >> (7 - x % 8) & 1;
+        if bit != color {
+            changes.push(x);
+            color = bit;
+        }
+    }
+    changes
+}
+
+/// Set the bits of the black runs of a row, which begin at the even `changes`.
+fn fill_row(changes: &[u32], width: u32, pixels: &mut [u8]) {
+    for run in changes.chunks(2) {
+        let end = run.get(1).copied().unwrap_or(width).min(width);
+        for x in run[0]..end {
+            pixels[x as usize / 8] |= 0x80 >> (x % 8);
+        }
+    }
+}
+
+/// The first change on the `reference` row to the right of `a0` becoming the opposite of
+/// `color`, and the change following it. Both are `width` if there are none.
+fn reference_changes(reference: &[u32], a0: i64, color: usize, width: u32) -> (u32, u32) {
+    let mut index = reference.partition_point(|&change| i64::from(change) <= a0);
+    // Changes to black are at even indices
+    if index % 2 != color {
+        index += 1;
+    }
+    let b1 = reference.get(index).copied().unwrap_or(width);
+    let b2 = reference.get(index + 1).copied().unwrap_or(width);
+    (b1, b2)
+}
+
+/// Decode a one-dimensionally coded row of `width` pixels, returning its changes.
+fn decode_1d(reader: &mut BitReader, width: u32, row: u32) -> Result<Vec<u32>> {
+    let mut changes = Vec::new();
+    let mut position = 0;
+    let mut color = 0;
+    while position < width {
+        position += reader.read_run(color, row)?;
+        if position > width {
+            return Err(CompressionError::InvalidFaxCode { row }.into());
+        }
+        if position < width {
+            changes.push(position);
+        }
+        color ^= 1;
+    }
+    Ok(changes)
+}
+
+/// Decode a two-dimensionally coded row of `width` pixels relative to the changes of the
+/// `reference` row, returning its changes.
+fn decode_2d(reader: &mut BitReader, width: u32, reference: &[u32], row: u32) -> Result<Vec<u32>> {
+    let invalid = CompressionError::InvalidFaxCode { row };
+    let mut changes = Vec::new();
+    // a0 starts on an imaginary pixel before the start of the row
+    let mut a0: i64 = -1;
+    let mut color = 0;
+    while a0 < i64::from(width) {
+        let (b1, b2) = reference_changes(reference, a0, color, width);
+        match reader.read_mode(row)? {
+            Mode::Pass => a0 = b2.into(),
+            Mode::Horizontal => {
+                let start = a0.max(0) as u32;
+                let a1 = start + reader.read_run(color, row)?;
+                let a2 = a1 + reader.read_run(color ^ 1, row)?;
+                if a2 > width {
+                    return Err(invalid.into());
+                }
+                changes.extend([a1, a2].iter().filter(|&&change| change < width));
+                a0 = a2.into();
+            }
+            Mode::Vertical(offset) => {
+                let a1 = i64::from(b1) + i64::from(offset);
+                if a1 <= a0 || a1 > i64::from(width) {
+                    return Err(invalid.into());
+                }
+                if a1 < i64::from(width) {
+                    changes.push(a1 as u32);
+                }
+                a0 = a1;
+                color ^= 1;
+            }
+            Mode::Extension => return Err(CompressionError::UnsupportedFaxExtension { row }.into()),
+        }
+    }
+    Ok(changes)
+}
+
+/// Code a row of `width` pixels with the given `changes` one-dimensionally.
+fn encode_1d(writer: &mut BitWriter, changes: &[u32], width: u32) {
+    let mut position = 0;
+    for (color, &change) in changes.iter().chain(Some(&width)).enumerate() {
+        writer.write_run(color % 2, change - position);
+        position = change;
+    }
+}
+
+/// Code a row of `width` pixels with the given `changes` relative to the changes of the
+/// `reference` row.
+fn encode_2d(writer: &mut BitWriter, changes: &[u32], reference: &[u32], width: u32) {
+    let mut a0: i64 = -1;
+    let mut color = 0;
+    while a0 < i64::from(width) {
+        let index = changes.partition_point(|&change| i64::from(change) <= a0);
+        let a1 = changes.get(index).copied().unwrap_or(width);
+        let a2 = changes.get(index + 1).copied().unwrap_or(width);
+        let (b1, b2) = reference_changes(reference, a0, color, width);
+        let offset = i64::from(a1) - i64::from(b1);
+        if b2 < a1 {
+            writer.write(PASS);
+            a0 = b2.into();
+        } else if offset.abs() <= 3 {
+            writer.write(VERTICAL[(offset + 3) as usize]);
+            a0 = a1.into();
+            color ^= 1;
+        } else {
+            writer.write(HORIZONTAL);
+            writer.write_run(color, a1 - a0.max(0) as u32);
+            writer.write_run(color ^ 1, a2 - a1);
+            a0 = a2.into();
+        }
+    }
+}
+
+/// Lookup tables decoding the run codes of white and black runs from their next
+/// `MAX_RUN_CODE_BITS` bits.
+fn run_tables() -> &'static [Vec<Option<RunCode>>; 2] {
+    static TABLES: OnceLock<[Vec<Option<RunCode>>; 2]> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let table = |terminating: &[(u16, u8)], makeup: &[(u16, u8)]| {
+            let mut table = vec![None; 1 << MAX_RUN_CODE_BITS];
+            let codes = terminating
+                .iter()
+                .enumerate()
+                .map(|(run, &code)| (code, run, false))
+                .chain(
+                    makeup
+                        .iter()
+                        .chain(&EXTENDED_MAKEUP)
+                        .enumerate()
+                        .map(|(index, &code)| (code, (index + 1) * 64, true)),
+                );
+            for ((code, bits), run, makeup) in codes {
+                let shift = MAX_RUN_CODE_BITS - bits;
+                let start = usize::from(code) << shift;
+                for entry in &mut table[start..start + (1 << shift)] {
+                    *entry = Some(RunCode {
+                        bits,
+                        run: run as u16,
+                        makeup,
+                    });
+                }
+            }
+            table
+        };
+        [
+            table(&WHITE_TERMINATING, &WHITE_MAKEUP),
+            table(&BLACK_TERMINATING, &BLACK_MAKEUP),
+        ]
+    })
+}
+
+/// Reads codes from a bitstream, most significant bit first.
+struct BitReader<'a> {
+    input: &'a [u8],
+    /// Position in bits.
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Self { input, position: 0 }
+    }
+
+    /// The next `bits` bits (no more than 16), padded with zeros past the end of the input.
+    fn peek(&self, bits: u8) -> u16 {
+        let byte = self.position / 8;
+        let window = (0..4).fold(0u32, |window, i| {
+            window << 8 | u32::from(self.input.get(byte + i).copied().unwrap_or(0))
+        });
+        (window << (self.position % 8) >> (32 - u32::from(bits))) as u16
+    }
+
+    /// Read the next `bits` bits.
+    fn read(&mut self, bits: u8) -> u16 {
+        let value = self.peek(bits);
+        self.position += usize::from(bits);
+        value
+    }
+
+    /// Whether the reader has passed the end of the input.
+    fn is_exhausted(&self) -> bool {
+        self.position >= self.input.len() * 8
+    }
+
+    /// Skip to the next byte boundary.
+    fn align(&mut self) {
+        self.position = self.position.next_multiple_of(8);
+    }
+
+    /// Skip any EOL codes and the fill bits before them.
+    fn skip_eols(&mut self) {
+        // No code begins with more than seven zeros
+        while !self.is_exhausted() && self.peek(8) == 0 {
+            let start = self.position;
+            while !self.is_exhausted() && self.peek(1) == 0 {
+                self.position += 1;
+            }
+            if self.position - start < usize::from(EOL.1 - 1) {
+                self.position = start;
+                return;
+            }
+            self.position += 1;
+        }
+    }
+
+    /// Read the run of the given colour (0 for white, 1 for black), made up of any number of
+    /// makeup codes followed by a terminating code.
+    fn read_run(&mut self, color: usize, row: u32) -> Result<u32> {
+        let table = &run_tables()[color];
+        let mut run = 0;
+        loop {
+            if self.is_exhausted() {
+                return Err(CompressionError::Truncated.into());
+            }
+            let code = table[usize::from(self.peek(MAX_RUN_CODE_BITS))]
+                .ok_or(CompressionError::InvalidFaxCode { row })?;
+            self.position += usize::from(code.bits);
+            run += u32::from(code.run);
+            if !code.makeup {
+                return Ok(run);
+            }
+        }
+    }
+
+    /// Read the code of a two-dimensional coding mode.
+    fn read_mode(&mut self, row: u32) -> Result<Mode> {
+        if self.is_exhausted() {
+            return Err(CompressionError::Truncated.into());
+        }
+        let codes = [
+            (PASS, Mode::Pass),
+            (HORIZONTAL, Mode::Horizontal),
+            (EXTENSION, Mode::Extension),
+        ]
+        .iter()
+        .copied()
+        .chain(
+            VERTICAL
+                .iter()
+                .enumerate()
+                .map(|(index, &code)| (code, Mode::Vertical(index as i32 - 3))),
+        );
+        for ((code, bits), mode) in codes {
+            if self.peek(bits) == code {
+                self.position += usize::from(bits);
+                return Ok(mode);
+            }
+        }
+        Err(CompressionError::InvalidFaxCode { row }.into())
+    }
+}
+
+/// Writes codes to a bitstream, most significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    output: Vec<u8>,
+    /// Bits written to the last byte of the output, or 0 if it is full.
+    used: u8,
+}
+
+impl BitWriter {
+    /// Write a code of `bits` bits.
+    fn write(&mut self, (code, bits): (u16, u8)) {
+        for bit in (0..bits).rev() {
+            if self.used == 0 {
+                self.output.push(0);
+            }
+            let last = self.output.len() - 1;
+            self.output[last] |= ((code >> bit) as u8 & 1) << (7 - self.used);
+            self.used = (self.used + 1) % 8;
+        }
+    }
+
+    /// Write a run of the given colour (0 for white, 1 for black).
+    fn write_run(&mut self, color: usize, mut run: u32) {
+        let (terminating, makeup) = if color == 0 {
+            (&WHITE_TERMINATING, &WHITE_MAKEUP)
+        } else {
+            (&BLACK_TERMINATING, &BLACK_MAKEUP)
+        };
+        while run >= 64 {
+            let step = run.min(MAX_MAKEUP) / 64;
+            let code = if step as usize <= makeup.len() {
+                makeup[step as usize - 1]
+            } else {
+                EXTENDED_MAKEUP[step as usize - makeup.len() - 1]
+            };
+            self.write(code);
+            run -= step * 64;
+        }
+        self.write(terminating[run as usize]);
+    }
+
+    /// Pad with zeros so that a following code of `bits` bits ends on a byte boundary.
+    fn pad_to(&mut self, bits: u8) {
+        let end = (self.used + bits) % 8;
+        if end != 0 {
+            self.write((0, 8 - end));
+        }
+    }
+
+    /// Pad with zeros to the next byte boundary.
+    fn align(&mut self) {
+        self.used = 0;
+    }
+}
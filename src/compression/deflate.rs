@@ -0,0 +1,51 @@
+use crate::compression::{BlockDimensions, Codec};
+use crate::errors::Result;
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use std::io::{Read, Write};
+
+/// Default zlib compression level, balancing speed and size.
+pub const DEFAULT_DEFLATE_LEVEL: u32 = 6;
+
+/// zlib-wrapped Deflate compression (Compression = 8 or the obsolete 32946).
+#[derive(Debug, Clone, Copy)]
+pub struct Deflate {
+    /// Compression level from 0 (store) to 9 (smallest).
+    level: u32,
+}
+
+impl Default for Deflate {
+    fn default() -> Self {
+        Self::with_level(DEFAULT_DEFLATE_LEVEL)
+    }
+}
+
+impl Deflate {
+    /// Create a Deflate codec compressing at `level`, clamped to the range 0 (store) to 9 (smallest).
+    pub fn with_level(level: u32) -> Self {
+        Self {
+            level: level.min(9),
+        }
+    }
+
+    /// The compression level used when compressing.
+    pub fn level(&self) -> u32 {
+        self.level
+    }
+}
+
+impl Codec for Deflate {
+    fn decompress(&self, input: &[u8], block: &BlockDimensions) -> Result<Vec<u8>> {
+        let expected_len = block.byte_count()?;
+        let mut output = Vec::with_capacity(expected_len);
+        ZlibDecoder::new(input)
+            .take(expected_len as u64)
+            .read_to_end(&mut output)?;
+        Ok(output)
+    }
+
+    fn compress(&self, input: &[u8], _block: &BlockDimensions) -> Result<Vec<u8>> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(self.level));
+        encoder.write_all(input)?;
+        Ok(encoder.finish()?)
+    }
+}
@@ -0,0 +1,80 @@
+use crate::baseline::constants::compression;
+use crate::compression::{BlockDimensions, Codec};
+use crate::errors::{CompressionError, Result};
+use jpeg_decoder::{ColorTransform, Decoder, PixelFormat};
+
+/// Start of image marker.
+const SOI: [u8; 2] = [0xFF, 0xD8];
+/// End of image marker.
+const EOI: [u8; 2] = [0xFF, 0xD9];
+
+/// New-style JPEG compression (Compression = 7), where each strip or tile is a JPEG stream whose
+/// quantization and Huffman tables may be stored once in the `JPEG_TABLES` tag instead.
+///
+/// Decoded samples are returned as stored, without converting their colours: YCbCr data is
+/// upsampled to full resolution YCbCr, leaving conversion to `ColorConverter`, while RGB data is
+/// left as RGB. Only decompression is supported.
+#[derive(Debug, Clone, Default)]
+pub struct Jpeg {
+    /// Contents of the `JPEG_TABLES` tag, an abbreviated stream holding only tables.
+    tables: Option<Vec<u8>>,
+}
+
+impl Jpeg {
+    /// Create a codec for streams which rely on the tables of `tables`, the contents of the
+    /// `JPEG_TABLES` tag.
+    pub fn with_tables(tables: Vec<u8>) -> Self {
+        Self {
+            tables: Some(tables),
+        }
+    }
+
+    /// Prefix `input` with the tables of `JPEG_TABLES`, if any, forming a single stream.
+    fn merge_tables(&self, input: &[u8]) -> Vec<u8> {
+        let tables = match &self.tables {
+            // Tables hold at least SOI and EOI
+            Some(tables) if tables.len() > SOI.len() + EOI.len() => tables,
+            _ => return input.to_vec(),
+        };
+        let tables = tables.strip_suffix(&EOI).unwrap_or(tables);
+        let input = input.strip_prefix(&SOI).unwrap_or(input);
+        let mut stream = Vec::with_capacity(tables.len() + input.len());
+        stream.extend_from_slice(tables);
+        stream.extend_from_slice(input);
+        stream
+    }
+}
+
+impl Codec for Jpeg {
+    fn decompress(&self, input: &[u8], block: &BlockDimensions) -> Result<Vec<u8>> {
+        let stream = self.merge_tables(input);
+        let mut decoder = Decoder::new(stream.as_slice());
+        decoder.read_info()?;
+        let info = decoder.info().ok_or(CompressionError::Truncated)?;
+        let row_bytes = usize::from(info.width) * info.pixel_format.pixel_bytes();
+        if u32::from(info.width) != block.width || row_bytes != block.row_bytes {
+            return Err(CompressionError::JpegMismatch.into());
+        }
+
+        // `ColorTransform::None` does not interleave the samples of each component, so use the
+        // transforms which interleave them unchanged (RGB) or merely inverted (CMYK) instead
+        let inverted = info.pixel_format == PixelFormat::CMYK32;
+        decoder.set_color_transform(if inverted {
+            ColorTransform::CMYK
+        } else {
+            ColorTransform::RGB
+        });
+        let mut pixels = decoder.decode()?;
+        if inverted {
+            pixels.iter_mut().for_each(|sample| *sample = !*sample);
+        }
+        Ok(pixels)
+    }
+
+    fn compress(&self, _input: &[u8], _block: &BlockDimensions) -> Result<Vec<u8>> {
+        Err(CompressionError::UnsupportedEncoding {
+            compression: compression::NEW_JPEG,
+        }
+        .into())
+    }
+}
@@ -0,0 +1,271 @@
+use crate::compression::{BlockDimensions, Codec};
+use crate::errors::{CompressionError, Result};
+use std::collections::HashMap;
+
+const CLEAR_CODE: u16 = 256;
+const END_OF_INFORMATION: u16 = 257;
+const FIRST_CODE: u16 = 258;
+const MIN_BITS: u8 = 9;
+const MAX_BITS: u8 = 12;
+
+/// The encoder resets its table before the code width would need to exceed `MAX_BITS`.
+const LAST_ENCODER_CODE: u16 = (1 << MAX_BITS) - 2;
+
+/// TIFF-flavoured Lempel-Ziv-Welch compression (Compression = 5).
+///
+/// Decompression accepts both the standard MSB-first "early change" variant and the old-style
+/// LSB-first variant emitted by some pre-TIFF 6.0 writers, telling them apart by the leading
+/// clear code. Compression always produces the standard variant.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Lzw;
+
+impl Codec for Lzw {
+    fn decompress(&self, input: &[u8], block: &BlockDimensions) -> Result<Vec<u8>> {
+        lzw_decode(input, block.byte_count()?)
+    }
+
+    fn compress(&self, input: &[u8], _block: &BlockDimensions) -> Result<Vec<u8>> {
+        Ok(lzw_encode(input))
+    }
+}
+
+/// Returns true if `input` begins with a clear code packed LSB-first, as old-style LZW does.
+fn is_old_style(input: &[u8]) -> bool {
+    input.len() >= 2 && input[0] == 0 && input[1] & 1 == 1
+}
+
+/// Reads variable width codes from a byte stream.
+struct BitReader<'a> {
+    input: &'a [u8],
+    position: usize,
+    buffer: u32,
+    buffered_bits: u8,
+    lsb_first: bool,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(input: &'a [u8], lsb_first: bool) -> Self {
+        Self {
+            input,
+            position: 0,
+            buffer: 0,
+            buffered_bits: 0,
+            lsb_first,
+        }
+    }
+
+    /// Read a code `bits` wide, or `None` once the input is exhausted.
+    fn read(&mut self, bits: u8) -> Option<u16> {
+        while self.buffered_bits < bits {
+            let byte = u32::from(*self.input.get(self.position)?);
+            self.position += 1;
+            if self.lsb_first {
+                self.buffer |= byte << self.buffered_bits;
+            } else {
+                self.buffer = (self.buffer << 8) | byte;
+            }
+            self.buffered_bits += 8;
+        }
+
+        let mask = (1u32 << bits) - 1;
+        let code = if self.lsb_first {
+            let code = self.buffer & mask;
+            self.buffer >>= bits;
+            code
+        } else {
+            (self.buffer >> (self.buffered_bits - bits)) & mask
+        };
+        self.buffered_bits -= bits;
+        if !self.lsb_first {
+            self.buffer &= (1u32 << self.buffered_bits) - 1;
+        }
+        Some(code as u16)
+    }
+}
+
+/// A string table entry, stored as a link to its prefix.
+#[derive(Clone, Copy)]
+struct Entry {
+    prefix: u16,
+    last: u8,
+    first: u8,
+    len: usize,
+}
+
+/// Decode LZW `input`, stopping at the end of information code, once `expected_len` bytes have
+/// been produced, or when the input runs out.
+pub fn lzw_decode(input: &[u8], expected_len: usize) -> Result<Vec<u8>> {
+    let old_style = is_old_style(input);
+    let mut reader = BitReader::new(input, old_style);
+    let mut output = Vec::with_capacity(expected_len);
+
+    let mut table: Vec<Entry> = (0..=255u8)
+        .map(|byte| Entry {
+            prefix: 0,
+            last: byte,
+            first: byte,
+            len: 1,
+        })
+        .collect();
+    // Placeholders for the clear and end of information codes
+    table.resize(
+        FIRST_CODE as usize,
+        Entry {
+            prefix: 0,
+            last: 0,
+            first: 0,
+            len: 0,
+        },
+    );
+
+    let mut bits = MIN_BITS;
+    let mut previous: Option<u16> = None;
+    while output.len() < expected_len {
+        let code = match reader.read(bits) {
+            Some(code) => code,
+            None => break,
+        };
+
+        match code {
+            CLEAR_CODE => {
+                table.truncate(FIRST_CODE as usize);
+                bits = MIN_BITS;
+                previous = None;
+                continue;
+            }
+            END_OF_INFORMATION => break,
+            _ => {}
+        }
+
+        let next_code = table.len() as u16;
+        match previous {
+            None => {
+                if code >= FIRST_CODE {
+                    return Err(CompressionError::InvalidCode { code }.into());
+                }
+                output.push(code as u8);
+            }
+            Some(previous) => {
+                let first = if code < next_code {
+                    table[code as usize].first
+                } else if code == next_code {
+                    // The code being defined right now: previous string plus its own first byte
+                    table[previous as usize].first
+                } else {
+                    return Err(CompressionError::InvalidCode { code }.into());
+                };
+
+                if table.len() < 1 << MAX_BITS {
+                    let prefix = table[previous as usize];
+                    table.push(Entry {
+                        prefix: previous,
+                        last: first,
+                        first: prefix.first,
+                        len: prefix.len + 1,
+                    });
+                }
+                write_string(&table, code, &mut output);
+            }
+        }
+        previous = Some(code);
+
+        // The code width grows one code "early" in standard TIFF LZW
+        let next_code = table.len() as u32 + if old_style { 0 } else { 1 };
+        if next_code >= 1 << bits && bits < MAX_BITS {
+            bits += 1;
+        }
+    }
+
+    output.truncate(expected_len);
+    Ok(output)
+}
+
+/// Append the string represented by `code` to `output`.
+fn write_string(table: &[Entry], code: u16, output: &mut Vec<u8>) {
+    let start = output.len();
+    let mut entry = table[code as usize];
+    output.resize(start + entry.len, 0);
+    for position in (start..output.len()).rev() {
+        output[position] = entry.last;
+        entry = table[entry.prefix as usize];
+    }
+}
+
+/// Writes variable width codes MSB-first.
+struct BitWriter {
+    output: Vec<u8>,
+    buffer: u32,
+    buffered_bits: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, bits: u8) {
+        self.buffer = (self.buffer << bits) | u32::from(code);
+        self.buffered_bits += bits;
+        while self.buffered_bits >= 8 {
+            self.buffered_bits -= 8;
+            self.output.push((self.buffer >> self.buffered_bits) as u8);
+        }
+        self.buffer &= (1u32 << self.buffered_bits) - 1;
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.buffered_bits > 0 {
+            self.output
+                .push((self.buffer << (8 - self.buffered_bits)) as u8);
+        }
+        self.output
+    }
+}
+
+/// Encode `input` with standard (MSB-first, early change) TIFF LZW.
+pub fn lzw_encode(input: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter {
+        output: Vec::with_capacity(input.len() / 2),
+        buffer: 0,
+        buffered_bits: 0,
+    };
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next_code = FIRST_CODE;
+    let mut bits = MIN_BITS;
+
+    writer.write(CLEAR_CODE, bits);
+
+    let mut bytes = input.iter();
+    let mut current = match bytes.next() {
+        Some(&byte) => u16::from(byte),
+        None => {
+            writer.write(END_OF_INFORMATION, bits);
+            return writer.finish();
+        }
+    };
+
+    for &byte in bytes {
+        if let Some(&code) = table.get(&(current, byte)) {
+            current = code;
+            continue;
+        }
+
+        writer.write(current, bits);
+        table.insert((current, byte), next_code);
+        next_code += 1;
+        if next_code == 1 << bits {
+            bits += 1;
+        }
+        if next_code == LAST_ENCODER_CODE {
+            writer.write(CLEAR_CODE, bits);
+            table.clear();
+            next_code = FIRST_CODE;
+            bits = MIN_BITS;
+        }
+        current = u16::from(byte);
+    }
+
+    writer.write(current, bits);
+    // The decoder adds a table entry for the final code, so the width may have to grow first
+    if next_code + 1 == 1 << bits && bits < MAX_BITS {
+        bits += 1;
+    }
+    writer.write(END_OF_INFORMATION, bits);
+    writer.finish()
+}
@@ -0,0 +1,89 @@
+use crate::baseline::constants::compression;
+use crate::errors::{CompressionError, DecodingError, Result};
+
+/// PackBits run-length encoding
+pub(crate) mod packbits;
+pub use packbits::*;
+
+/// Lempel-Ziv-Welch compression
+pub(crate) mod lzw;
+pub use lzw::*;
+
+/// zlib/Deflate compression
+pub(crate) mod deflate;
+pub use deflate::*;
+
+/// CCITT Group 3 and Group 4 fax compression
+pub(crate) mod ccitt;
+pub use ccitt::*;
+
+/// New-style JPEG compression
+pub(crate) mod jpeg;
+pub use jpeg::*;
+
+/// Old-style JPEG stream reconstruction
+pub(crate) mod ojpeg;
+pub use ojpeg::*;
+
+/// Horizontal differencing and floating point predictors
+pub(crate) mod predictor;
+pub use predictor::*;
+
+/// The shape of the strip or tile being (de)compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockDimensions {
+    /// Width of the block in pixels.
+    pub width: u32,
+    /// Height of the block in rows.
+    pub height: u32,
+    /// Size in bytes of a single uncompressed row.
+    pub row_bytes: usize,
+}
+
+impl BlockDimensions {
+    /// Size in bytes of the entire uncompressed block, failing if it does not fit in memory.
+    pub fn byte_count(&self) -> Result<usize> {
+        Ok(self
+            .row_bytes
+            .checked_mul(self.height as usize)
+            .ok_or(DecodingError::ImageTooLarge)?)
+    }
+}
+
+/// A compression scheme applied to the data of individual strips or tiles.
+pub trait Codec {
+    /// Decompress the data of a single strip or tile shaped like `block`.
+    fn decompress(&self, input: &[u8], block: &BlockDimensions) -> Result<Vec<u8>>;
+
+    /// Compress the data of a single strip or tile shaped like `block`.
+    fn compress(&self, input: &[u8], block: &BlockDimensions) -> Result<Vec<u8>>;
+}
+
+/// Strips or tiles stored as-is.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Uncompressed;
+
+impl Codec for Uncompressed {
+    fn decompress(&self, input: &[u8], _block: &BlockDimensions) -> Result<Vec<u8>> {
+        Ok(input.to_vec())
+    }
+
+    fn compress(&self, input: &[u8], _block: &BlockDimensions) -> Result<Vec<u8>> {
+        Ok(input.to_vec())
+    }
+}
+
+/// Find the codec for a value of the `COMPRESSION` tag.
+pub fn codec_for(compression: u16) -> Result<Box<dyn Codec>> {
+    match compression {
+        compression::UNCOMPRESSED => Ok(Box::new(Uncompressed)),
+        compression::LZW => Ok(Box::new(Lzw)),
+        compression::ADOBE_DEFLATE | compression::DEFLATE_PKZIP => Ok(Box::new(Deflate::default())),
+        compression::PACKBITS => Ok(Box::new(PackBits)),
+        compression::CCITT_1D | compression::GROUP_3_FAX | compression::GROUP_4_FAX => {
+            Ok(Box::new(Fax::new(compression, 0)?))
+        }
+        compression::JPEG | compression::NEW_JPEG => Ok(Box::new(Jpeg::default())),
+        _ => Err(CompressionError::UnsupportedCompression { compression }.into()),
+    }
+}
@@ -0,0 +1,249 @@
+use crate::baseline::{constants::photometic_interpretation, tags, PlanarConfiguration};
+use crate::compression::BlockDimensions;
+use crate::errors::{CompressionError, FieldExtractionError, Result};
+use crate::lowlevel::IFD;
+use std::convert::TryFrom;
+use std::io::{Read, Seek, SeekFrom};
+
+/// `JPEG_PROC` value of baseline sequential DCT coding.
+const BASELINE_PROCESS: u16 = 1;
+
+/// Start of image marker.
+const SOI: u8 = 0xD8;
+/// End of image marker.
+const EOI: u8 = 0xD9;
+/// Start of scan marker.
+const SOS: u8 = 0xDA;
+/// Baseline start of frame marker.
+const SOF0: u8 = 0xC0;
+/// Extended sequential start of frame marker, allowing more than two Huffman tables of a class.
+const SOF1: u8 = 0xC1;
+/// Quantization table marker.
+const DQT: u8 = 0xDB;
+/// Huffman table marker.
+const DHT: u8 = 0xC4;
+/// Restart interval marker.
+const DRI: u8 = 0xDD;
+
+/// Reconstructs decodable streams from old-style JPEG data (Compression = 6), as described by
+/// the obsolete `JPEG_*` tags of TIFF 6.0, for decoding with `Jpeg`.
+///
+/// This is a best effort, since writers disagreed on what the tags meant. When the
+/// `JPEG_INTERCHANGE_FORMAT` tag is present, the entire image is read as the stream it points to,
+/// followed by the data of any strips or tiles if that stream is only a header. Otherwise each
+/// strip or tile is entropy coded data, which is given a header built from the
+/// `JPEGQ_TABLES`, `JPEGDC_TABLES` and `JPEGAC_TABLES` tags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OldJpeg {
+    /// Offset and, if known, length of a complete JPEG stream.
+    interchange_format: Option<(u64, Option<u64>)>,
+    /// File offsets of the quantization tables of each component.
+    q_tables: Vec<u64>,
+    /// File offsets of the DC Huffman tables of each component.
+    dc_tables: Vec<u64>,
+    /// File offsets of the AC Huffman tables of each component.
+    ac_tables: Vec<u64>,
+    /// MCUs between restart markers, or 0 if there are none.
+    restart_interval: u16,
+    /// Horizontal and vertical sampling factors of each component of a strip or tile, as
+    /// packed into a frame header.
+    sampling: Vec<u8>,
+}
+
+impl OldJpeg {
+    /// Read the old-style JPEG tags of `ifd`.
+    pub fn from_ifd(ifd: &IFD) -> Result<Self> {
+        let interchange_format = match ifd.unsigned_or(tags::JPEG_INTERCHANGE_FORMAT, 0)? {
+            0 => None,
+            offset => Some((
+                offset,
+                Some(ifd.unsigned_or(tags::JPEG_INTERCHANGE_FORMAT_LNGTH, 0)?)
+                    .filter(|&length| length > 0),
+            )),
+        };
+        let tables = |tag| match ifd.get::<Vec<u64>>(tag) {
+            Err(FieldExtractionError::MissingTag { .. }) => Ok(Vec::new()),
+            result => result,
+        };
+        let q_tables = tables(tags::JPEGQ_TABLES)?;
+        if interchange_format.is_none() {
+            let process = ifd.unsigned_or(tags::JPEG_PROC, BASELINE_PROCESS)?;
+            if process != BASELINE_PROCESS {
+                return Err(CompressionError::UnsupportedJpegProcess { process }.into());
+            }
+            if q_tables.is_empty() {
+                return Err(CompressionError::MissingJpegTables.into());
+            }
+        }
+
+        let planar = ifd.planar_configuration()? == PlanarConfiguration::Planar;
+        let photometric = ifd.unsigned_or(
+            tags::PHOTOMETRIC_INTERPRETATION,
+            photometic_interpretation::BLACKISZERO,
+        )?;
+        let components = if planar {
+            1
+        } else {
+            usize::from(ifd.samples_per_pixel()?)
+        };
+        let mut sampling = vec![0x11; components];
+        if photometric == photometic_interpretation::YCBCR && components == 3 {
+            // Luma is sampled more often than the chroma, as `Y_CBCR_SUB_SAMPLING` describes
+            let subsampling = match ifd.get::<Vec<u64>>(tags::Y_CBCR_SUB_SAMPLING) {
+                Ok(values) if values.len() >= 2 => (values[0], values[1]),
+                _ => (2, 2),
+            };
+            sampling[0] = (subsampling.0 << 4 | subsampling.1 & 0xF) as u8;
+        }
+
+        Ok(Self {
+            interchange_format,
+            q_tables,
+            dc_tables: tables(tags::JPEGDC_TABLES)?,
+            ac_tables: tables(tags::JPEGAC_TABLES)?,
+            restart_interval: ifd.unsigned_or(tags::JPEG_RESTART_INTERVAL, 0)?,
+            sampling,
+        })
+    }
+
+    /// Whether the entire image is a single stream found through `JPEG_INTERCHANGE_FORMAT`,
+    /// rather than each strip or tile being decoded separately.
+    pub fn is_single_stream(&self) -> bool {
+        self.interchange_format.is_some()
+    }
+
+    /// Read the stream of an image for which `is_single_stream` holds from `reader`. The data of
+    /// the strips or tiles at `offsets` with the given `byte_counts` is appended if the stream is
+    /// only a header. Streams of unknown length are taken to end with the last strip or tile.
+    pub fn read_single_stream<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        offsets: &[u64],
+        byte_counts: &[u64],
+    ) -> Result<Vec<u8>> {
+        let (offset, length) = self
+            .interchange_format
+            .ok_or(CompressionError::MissingJpegTables)?;
+        let mut stream = Vec::new();
+        reader.seek(SeekFrom::Start(offset))?;
+        let length = length.unwrap_or_else(|| {
+            let end = offsets
+                .iter()
+                .zip(byte_counts)
+                .map(|(&offset, &byte_count)| offset.saturating_add(byte_count))
+                .max()
+                .unwrap_or_default();
+            end.saturating_sub(offset)
+        });
+        reader.by_ref().take(length).read_to_end(&mut stream)?;
+
+        if !has_scan(&stream) {
+            for (&offset, &byte_count) in offsets.iter().zip(byte_counts) {
+                reader.seek(SeekFrom::Start(offset))?;
+                reader.by_ref().take(byte_count).read_to_end(&mut stream)?;
+            }
+        }
+        // Streams ending with the last strip or tile may stop short of the end of image marker
+        if !stream.ends_with(&[0xFF, EOI]) {
+            stream.extend_from_slice(&[0xFF, EOI]);
+        }
+        Ok(stream)
+    }
+
+    /// Build the stream of a strip or tile shaped like `block` in plane `plane`, whose entropy
+    /// coded `data` follows a header holding the tables read from `reader`.
+    pub fn read_block_stream<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        data: &[u8],
+        block: &BlockDimensions,
+        plane: usize,
+    ) -> Result<Vec<u8>> {
+        let mut stream = vec![0xFF, SOI];
+        // Planar images have a set of tables for each plane
+        let first_table = if self.sampling.len() == 1 { plane } else { 0 };
+        let tables = first_table..first_table + self.sampling.len();
+
+        for (id, table) in tables.enumerate() {
+            let offset = *self
+                .q_tables
+                .get(table)
+                .ok_or(CompressionError::MissingJpegTables)?;
+            let mut payload = vec![id as u8];
+            payload.extend(read_table(reader, offset, 64)?);
+            write_segment(&mut stream, DQT, &payload);
+
+            for &(class, offsets) in &[(0u8, &self.dc_tables), (1, &self.ac_tables)] {
+                let offset = *offsets
+                    .get(table)
+                    .ok_or(CompressionError::MissingJpegTables)?;
+                let counts = read_table(reader, offset, 16)?;
+                let values = counts.iter().map(|&count| usize::from(count)).sum();
+                let mut payload = vec![class << 4 | id as u8];
+                payload.extend(counts);
+                payload.extend(read_table(reader, offset + 16, values)?);
+                write_segment(&mut stream, DHT, &payload);
+            }
+        }
+        if self.restart_interval > 0 {
+            write_segment(&mut stream, DRI, &self.restart_interval.to_be_bytes());
+        }
+
+        let dimension = |value| u16::try_from(value).map_err(|_| CompressionError::JpegMismatch);
+        let mut frame = vec![8];
+        frame.extend_from_slice(&dimension(block.height)?.to_be_bytes());
+        frame.extend_from_slice(&dimension(block.width)?.to_be_bytes());
+        frame.push(self.sampling.len() as u8);
+        let mut scan = vec![self.sampling.len() as u8];
+        for (id, &sampling) in self.sampling.iter().enumerate() {
+            frame.extend_from_slice(&[id as u8 + 1, sampling, id as u8]);
+            scan.extend_from_slice(&[id as u8 + 1, (id as u8) << 4 | id as u8]);
+        }
+        scan.extend_from_slice(&[0, 63, 0]);
+        let sof = if self.sampling.len() > 2 { SOF1 } else { SOF0 };
+        write_segment(&mut stream, sof, &frame);
+        write_segment(&mut stream, SOS, &scan);
+
+        stream.extend_from_slice(data);
+        if !stream.ends_with(&[0xFF, EOI]) {
+            stream.extend_from_slice(&[0xFF, EOI]);
+        }
+        Ok(stream)
+    }
+}
+
+/// Read `length` bytes of a table at `offset`.
+fn read_table<R: Read + Seek>(reader: &mut R, offset: u64, length: usize) -> Result<Vec<u8>> {
+    let mut table = vec![0; length];
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut table)?;
+    Ok(table)
+}
+
+/// Append a marker segment holding `payload` to `stream`.
+fn write_segment(stream: &mut Vec<u8>, marker: u8, payload: &[u8]) {
+    stream.extend_from_slice(&[0xFF, marker]);
+    stream.extend_from_slice(&(payload.len() as u16 + 2).to_be_bytes());
+    stream.extend_from_slice(payload);
+}
+
+/// Whether the marker segments of `stream` reach a start of scan, rather than it holding only
+/// the tables and frame header.
+fn has_scan(stream: &[u8]) -> bool {
+    let mut position = 2;
+    while let (Some(&0xFF), Some(&marker)) = (stream.get(position), stream.get(position + 1)) {
+        match marker {
+            SOS => return true,
+            // Fill bytes
+            0xFF => position += 1,
+            _ => {
+                let length = match stream.get(position + 2..position + 4) {
+                    Some(length) => usize::from(u16::from_be_bytes([length[0], length[1]])),
+                    None => return false,
+                };
+                position += 2 + length;
+            }
+        }
+    }
+    false
+}
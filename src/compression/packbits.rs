@@ -0,0 +1,97 @@
+use crate::compression::{BlockDimensions, Codec};
+use crate::errors::{CompressionError, Result};
+
+/// Longest run (literal or repeated) a single PackBits header can describe.
+const MAX_RUN: usize = 128;
+
+/// The PackBits byte-oriented run-length scheme (Compression = 32773).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PackBits;
+
+impl Codec for PackBits {
+    fn decompress(&self, input: &[u8], block: &BlockDimensions) -> Result<Vec<u8>> {
+        packbits_decode(input, block.byte_count()?)
+    }
+
+    fn compress(&self, input: &[u8], block: &BlockDimensions) -> Result<Vec<u8>> {
+        // Rows must be packed separately
+        let mut output = Vec::with_capacity(input.len() + input.len() / MAX_RUN + 1);
+        for row in input.chunks(block.row_bytes.max(1)) {
+            packbits_encode_into(row, &mut output);
+        }
+        Ok(output)
+    }
+}
+
+/// Decode PackBits `input`, stopping once `expected_len` bytes have been produced. Runs extending
+/// past `expected_len` are cut short, and input which runs out before then is an error.
+pub fn packbits_decode(input: &[u8], expected_len: usize) -> Result<Vec<u8>> {
+    let mut output = Vec::with_capacity(expected_len);
+    let mut input = input.iter();
+    while output.len() < expected_len {
+        let header = match input.next() {
+            Some(&header) => header as i8,
+            None => return Err(CompressionError::Truncated.into()),
+        };
+        match header {
+            // Literal run of header + 1 bytes
+            0..=127 => {
+                for _ in 0..=header {
+                    output.push(*input.next().ok_or(CompressionError::Truncated)?);
+                }
+            }
+            // No-op
+            -128 => {}
+            // Next byte repeated 1 - header times
+            _ => {
+                let byte = *input.next().ok_or(CompressionError::Truncated)?;
+                let run = (1 - header as isize) as usize;
+                output.resize(output.len() + run, byte);
+            }
+        }
+    }
+    output.truncate(expected_len);
+    Ok(output)
+}
+
+/// Encode `input` with PackBits.
+pub fn packbits_encode(input: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(input.len() + input.len() / MAX_RUN + 1);
+    packbits_encode_into(input, &mut output);
+    output
+}
+
+/// Encode `input` with PackBits, appending to `output`.
+fn packbits_encode_into(input: &[u8], output: &mut Vec<u8>) {
+    let mut literal_start = 0;
+    let mut position = 0;
+    while position < input.len() {
+        // Measure the run of identical bytes beginning here
+        let byte = input[position];
+        let run = input[position..]
+            .iter()
+            .take(MAX_RUN)
+            .take_while(|&&b| b == byte)
+            .count();
+
+        // Runs of three or more are always worth encoding as a repeat
+        if run >= 3 {
+            flush_literals(&input[literal_start..position], output);
+            output.push((1 - run as isize) as u8);
+            output.push(byte);
+            position += run;
+            literal_start = position;
+        } else {
+            position += run;
+        }
+    }
+    flush_literals(&input[literal_start..], output);
+}
+
+/// Write `literals` as one or more literal runs.
+fn flush_literals(literals: &[u8], output: &mut Vec<u8>) {
+    for chunk in literals.chunks(MAX_RUN) {
+        output.push((chunk.len() - 1) as u8);
+        output.extend_from_slice(chunk);
+    }
+}
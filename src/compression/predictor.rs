@@ -0,0 +1,192 @@
+use crate::baseline::constants::predictor;
+use crate::compression::{BlockDimensions, Codec};
+use crate::errors::{CompressionError, Result};
+
+/// A `PREDICTOR` applied to the samples of a strip or tile, along with the sample layout it needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Predictor {
+    /// Value of the `PREDICTOR` tag.
+    pub predictor: u16,
+    /// Samples per pixel within the block (1 for planar configuration 2).
+    pub samples_per_pixel: u16,
+    /// Bits in each sample.
+    pub bits_per_sample: u16,
+    /// Whether the samples are stored in little-endian byte order.
+    pub little_endian: bool,
+}
+
+impl Predictor {
+    /// Undo the predictor in place on decompressed `data`, leaving samples in their stored byte order.
+    pub fn decode(&self, data: &mut [u8], block: &BlockDimensions) -> Result<()> {
+        self.check()?;
+        for row in data.chunks_mut(block.row_bytes.max(1)) {
+            match self.predictor {
+                predictor::HORIZONTAL_DIFFERENCING => self.horizontal_accumulate(row),
+                predictor::FLOATING_POINT => self.floating_point_accumulate(row),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the predictor in place on `data` prior to compression.
+    pub fn encode(&self, data: &mut [u8], block: &BlockDimensions) -> Result<()> {
+        self.check()?;
+        for row in data.chunks_mut(block.row_bytes.max(1)) {
+            match self.predictor {
+                predictor::HORIZONTAL_DIFFERENCING => self.horizontal_difference(row),
+                predictor::FLOATING_POINT => self.floating_point_difference(row),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Make sure this predictor can be applied to the sample layout.
+    fn check(&self) -> Result<()> {
+        let supported = match self.predictor {
+            predictor::NONE => true,
+            predictor::HORIZONTAL_DIFFERENCING => {
+                matches!(self.bits_per_sample, 8 | 16 | 32 | 64)
+            }
+            predictor::FLOATING_POINT => matches!(self.bits_per_sample, 16 | 24 | 32 | 64),
+            _ => false,
+        };
+        if supported && self.samples_per_pixel > 0 {
+            Ok(())
+        } else {
+            Err(CompressionError::UnsupportedPredictor {
+                predictor: self.predictor,
+                bits_per_sample: self.bits_per_sample,
+            }
+            .into())
+        }
+    }
+
+    fn bytes_per_sample(&self) -> usize {
+        usize::from(self.bits_per_sample / 8)
+    }
+
+    /// Distance in bytes between a sample and the one it is predicted from.
+    fn stride(&self) -> usize {
+        usize::from(self.samples_per_pixel) * self.bytes_per_sample()
+    }
+
+    fn read_sample(&self, bytes: &[u8]) -> u64 {
+        let fold = |acc: u64, &byte: &u8| (acc << 8) | u64::from(byte);
+        if self.little_endian {
+            bytes.iter().rev().fold(0, fold)
+        } else {
+            bytes.iter().fold(0, fold)
+        }
+    }
+
+    fn write_sample(&self, bytes: &mut [u8], mut value: u64) {
+        let mut store = |byte: &mut u8| {
+            *byte = value as u8;
+            value >>= 8;
+        };
+        if self.little_endian {
+            bytes.iter_mut().for_each(&mut store);
+        } else {
+            bytes.iter_mut().rev().for_each(&mut store);
+        }
+    }
+
+    fn horizontal_accumulate(&self, row: &mut [u8]) {
+        let (size, stride) = (self.bytes_per_sample(), self.stride());
+        let samples = row.len() / size * size;
+        if size == 1 {
+            for i in stride..samples {
+                row[i] = row[i].wrapping_add(row[i - stride]);
+            }
+            return;
+        }
+        for i in (stride..samples).step_by(size) {
+            let previous = self.read_sample(&row[i - stride..i - stride + size]);
+            let current = self.read_sample(&row[i..i + size]);
+            self.write_sample(&mut row[i..i + size], current.wrapping_add(previous));
+        }
+    }
+
+    fn horizontal_difference(&self, row: &mut [u8]) {
+        let (size, stride) = (self.bytes_per_sample(), self.stride());
+        let samples = row.len() / size * size;
+        // Work backwards so each sample is differenced against its original predecessor
+        for i in (stride..samples).step_by(size).rev() {
+            let previous = self.read_sample(&row[i - stride..i - stride + size]);
+            let current = self.read_sample(&row[i..i + size]);
+            self.write_sample(&mut row[i..i + size], current.wrapping_sub(previous));
+        }
+    }
+
+    /// The floating point predictor differences bytes which have been shuffled into planes of
+    /// equal significance, most significant first, regardless of the file's byte order.
+    fn floating_point_accumulate(&self, row: &mut [u8]) {
+        let size = self.bytes_per_sample();
+        let stride = usize::from(self.samples_per_pixel);
+        let len = row.len() / size * size;
+        for i in stride..len {
+            row[i] = row[i].wrapping_add(row[i - stride]);
+        }
+
+        let samples = len / size;
+        let shuffled = row[..len].to_vec();
+        for sample in 0..samples {
+            for significance in 0..size {
+                let byte = shuffled[significance * samples + sample];
+                let position = if self.little_endian {
+                    size - significance - 1
+                } else {
+                    significance
+                };
+                row[sample * size + position] = byte;
+            }
+        }
+    }
+
+    fn floating_point_difference(&self, row: &mut [u8]) {
+        let size = self.bytes_per_sample();
+        let stride = usize::from(self.samples_per_pixel);
+        let len = row.len() / size * size;
+
+        let samples = len / size;
+        let unshuffled = row[..len].to_vec();
+        for sample in 0..samples {
+            for significance in 0..size {
+                let position = if self.little_endian {
+                    size - significance - 1
+                } else {
+                    significance
+                };
+                row[significance * samples + sample] = unshuffled[sample * size + position];
+            }
+        }
+
+        for i in (stride..len).rev() {
+            row[i] = row[i].wrapping_sub(row[i - stride]);
+        }
+    }
+}
+
+/// Wraps a codec, undoing a predictor after decompression and applying it before compression.
+pub struct PredictorCodec {
+    /// The codec (de)compressing the predicted data.
+    pub codec: Box<dyn Codec>,
+    /// The predictor applied to the uncompressed data.
+    pub predictor: Predictor,
+}
+
+impl Codec for PredictorCodec {
+    fn decompress(&self, input: &[u8], block: &BlockDimensions) -> Result<Vec<u8>> {
+        let mut output = self.codec.decompress(input, block)?;
+        self.predictor.decode(&mut output, block)?;
+        Ok(output)
+    }
+
+    fn compress(&self, input: &[u8], block: &BlockDimensions) -> Result<Vec<u8>> {
+        let mut predicted = input.to_vec();
+        self.predictor.encode(&mut predicted, block)?;
+        self.codec.compress(&predicted, block)
+    }
+}
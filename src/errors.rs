@@ -1,12 +1,200 @@
-use failure::Fail;
+use crate::lowlevel::HeaderError;
+use std::io;
+use thiserror::Error;
+
+/// Result of any fallible operation of this crate.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Any error encountered while reading or writing a TIFF file. The more specific errors below are
+/// wrapped as they are, while errors within an IFD or a strip or tile are given the location at
+/// which they occurred and keep the original error as their `source`.
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Header(#[from] HeaderError),
+    #[error(transparent)]
+    FieldExtraction(#[from] FieldExtractionError),
+    #[error(transparent)]
+    Compression(#[from] CompressionError),
+    #[error(transparent)]
+    Decoding(#[from] DecodingError),
+    #[error(transparent)]
+    Encoding(#[from] EncodingError),
+    #[error(transparent)]
+    Color(#[from] ColorError),
+    #[error(transparent)]
+    Gps(#[from] GpsError),
+    #[error(transparent)]
+    GeoTiff(#[from] GeoTiffError),
+    #[error("Invalid JPEG data")]
+    Jpeg(#[from] jpeg_decoder::Error),
+    #[error("Failed to read the IFD at offset {offset}")]
+    Ifd { offset: u64, source: Box<Error> },
+    #[error("Failed to decode strip or tile {index} at offset {offset}")]
+    Block {
+        index: usize,
+        offset: u64,
+        source: Box<Error>,
+    },
+}
 
 /// An error encountered during extraction of a field from from an IFD
-#[derive(Fail, Debug, Clone, Copy)]
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FieldExtractionError {
-    #[fail(display = "Tag has wrong data type")]
+    #[error("Tag {tag:#X} has wrong data type")]
+    WrongDataType { tag: u16 },
+    #[error("Tag {tag:#X} contains insufficient data")]
+    InsufficientData { tag: u16 },
+    #[error("Missing tag {tag:#X}")]
+    MissingTag { tag: u16 },
+}
+
+/// An error encountered while converting the data of a field, before it is known which tag the
+/// field belongs to
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldConversionError {
+    #[error("Field has wrong data type")]
     WrongDataType,
-    #[fail(display = "Tag contains insufficient data")]
+    #[error("Field contains insufficient data")]
     InsufficientData,
-    #[fail(display = "Missing tag {:X}", tag)]
-    MissingTag { tag: u16 },
+}
+
+impl FieldConversionError {
+    /// Attribute this error to the field of `tag`.
+    pub fn for_tag(self, tag: u16) -> FieldExtractionError {
+        match self {
+            FieldConversionError::WrongDataType => FieldExtractionError::WrongDataType { tag },
+            FieldConversionError::InsufficientData => {
+                FieldExtractionError::InsufficientData { tag }
+            }
+        }
+    }
+}
+
+/// An error encountered while compressing or decompressing strip or tile data
+#[derive(Error, Debug, Clone, Copy)]
+pub enum CompressionError {
+    #[error("Unsupported compression {compression}")]
+    UnsupportedCompression { compression: u16 },
+    #[error("Compressed data is truncated")]
+    Truncated,
+    #[error("Invalid LZW code {code}")]
+    InvalidCode { code: u16 },
+    #[error("Unsupported predictor {predictor} for {bits_per_sample}-bit samples")]
+    UnsupportedPredictor {
+        predictor: u16,
+        bits_per_sample: u16,
+    },
+    #[error("Unsupported CCITT fax options {options:#x}")]
+    UnsupportedFaxOptions { options: u32 },
+    #[error("CCITT fax data must have 1 bit per pixel")]
+    InvalidFaxLayout,
+    #[error("Invalid CCITT fax code in row {row}")]
+    InvalidFaxCode { row: u32 },
+    #[error("Unsupported CCITT fax extension code in row {row}")]
+    UnsupportedFaxExtension { row: u32 },
+    #[error("JPEG data does not match the dimensions of its strip or tile")]
+    JpegMismatch,
+    #[error("Compression {compression} is only supported for reading")]
+    UnsupportedEncoding { compression: u16 },
+    #[error("Unsupported old-style JPEG process {process}")]
+    UnsupportedJpegProcess { process: u16 },
+    #[error("Old-style JPEG data has neither an interchange format stream nor tables")]
+    MissingJpegTables,
+}
+
+/// An error encountered while decoding the pixel data of an image
+#[derive(Error, Debug, Clone, Copy)]
+pub enum DecodingError {
+    #[error("Unsupported sample format {sample_format} with {bits_per_sample} bits per sample")]
+    UnsupportedSampleFormat {
+        sample_format: u16,
+        bits_per_sample: u16,
+    },
+    #[error("Tag {tag:#X} differs between samples, which is unsupported")]
+    NonUniformSamples { tag: u16 },
+    #[error("Unsupported planar configuration {planar_configuration}")]
+    UnsupportedPlanarConfiguration { planar_configuration: u16 },
+    #[error("Image dimensions are too large")]
+    ImageTooLarge,
+    #[error("Pixels must have at least one sample")]
+    InvalidSamplesPerPixel,
+    #[error("Strip or tile {index} is missing")]
+    MissingBlock { index: usize },
+    #[error("Strip or tile {index} is truncated")]
+    TruncatedBlock { index: usize },
+    #[error("Strips or tiles have zero size")]
+    InvalidBlockSize,
+    #[error("Image is stored in tiles, not strips")]
+    NotStriped,
+    #[error("Image is stored in strips, not tiles")]
+    NotTiled,
+    #[error("Buffer does not match the type or size of the region")]
+    BufferMismatch,
+    #[error("Invalid YCbCr subsampling {horizontal}x{vertical}")]
+    InvalidSubsampling { horizontal: u16, vertical: u16 },
+    #[error("Subsampled YCbCr data must have 3 unsigned integer samples per pixel")]
+    UnsupportedSubsampledLayout,
+}
+
+/// An error encountered while encoding the pixel data of an image
+#[derive(Error, Debug, Clone, Copy)]
+pub enum EncodingError {
+    #[error("Pixel buffer does not hold width * height * samples_per_pixel samples")]
+    BufferMismatch,
+    #[error("Image dimensions are too large")]
+    ImageTooLarge,
+    #[error("Tile size {width}x{height} is not a multiple of 16 pixels")]
+    InvalidTileSize { width: u32, height: u32 },
+    #[error("Samples cannot be written with {bits_per_sample} bits per sample")]
+    UnsupportedBitDepth { bits_per_sample: u16 },
+    #[error("Subsampled YCbCr images must have 3 interleaved u8, u16 or u32 samples per pixel")]
+    UnsupportedSubsampledLayout,
+}
+
+/// An error encountered while converting the colours of an image
+#[derive(Error, Debug, Clone, Copy)]
+pub enum ColorError {
+    #[error("Unsupported photometric interpretation {photometric}")]
+    UnsupportedPhotometric { photometric: u16 },
+    #[error("Unsupported ink set {ink_set}")]
+    UnsupportedInkSet { ink_set: u16 },
+    #[error("Color map does not match the bits per sample")]
+    InvalidColorMap,
+    #[error(
+        "{samples_per_pixel} samples per pixel are too few for the photometric interpretation"
+    )]
+    MissingSamples { samples_per_pixel: u16 },
+    #[error("Image does not match the layout the converter was created for")]
+    ImageMismatch,
+}
+
+/// An error encountered while interpreting the tags of a GPS info IFD
+#[derive(Error, Debug, Clone, Copy, PartialEq)]
+pub enum GpsError {
+    #[error("Tag {tag:#X} holds a rational with a zero denominator")]
+    ZeroDenominator { tag: u16 },
+    #[error("Tag {tag:#X} holds an unrecognized reference")]
+    InvalidReference { tag: u16 },
+    #[error("Coordinate {degrees} is out of range")]
+    OutOfRange { degrees: f64 },
+}
+
+/// An error encountered while interpreting or writing GeoTIFF tags
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeoTiffError {
+    #[error("Unsupported GeoKey directory version {version}")]
+    UnsupportedVersion { version: u16 },
+    #[error("GeoKey directory is truncated")]
+    TruncatedDirectory,
+    #[error("GeoKey {key} refers to values beyond the end of tag {location:#X}")]
+    MissingValues { key: u16, location: u16 },
+    #[error("GeoKey {key} is stored in unsupported tag {location:#X}")]
+    UnsupportedLocation { key: u16, location: u16 },
+    #[error("GeoKey {key} has too many values to be written")]
+    TooManyValues { key: u16 },
+    #[error("Tag {tag:#X} must hold a multiple of {multiple} values")]
+    InvalidValueCount { tag: u16, multiple: usize },
 }
@@ -0,0 +1,619 @@
+use crate::baseline::tags;
+use crate::errors::{FieldExtractionError, GeoTiffError, Result};
+use crate::lowlevel::{IFDField, IFD};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+
+/// `KeyDirectoryVersion` of the GeoKey directory, the only version defined.
+const KEY_DIRECTORY_VERSION: u16 = 1;
+
+/// Terminator of each value stored in `GEO_ASCII_PARAMS`.
+const ASCII_TERMINATOR: char = '|';
+
+/// Numbers of the GeoKeys defined by GeoTIFF 1.1
+pub mod keys {
+    // Configuration
+    pub const GT_MODEL_TYPE: u16 = 1024;
+    pub const GT_RASTER_TYPE: u16 = 1025;
+    pub const GT_CITATION: u16 = 1026;
+
+    // Geodetic CRS
+    pub const GEODETIC_CRS: u16 = 2048;
+    pub const GEODETIC_CITATION: u16 = 2049;
+    pub const GEODETIC_DATUM: u16 = 2050;
+    pub const PRIME_MERIDIAN: u16 = 2051;
+    pub const GEOG_LINEAR_UNITS: u16 = 2052;
+    pub const GEOG_LINEAR_UNIT_SIZE: u16 = 2053;
+    pub const GEOG_ANGULAR_UNITS: u16 = 2054;
+    pub const GEOG_ANGULAR_UNIT_SIZE: u16 = 2055;
+    pub const ELLIPSOID: u16 = 2056;
+    pub const ELLIPSOID_SEMI_MAJOR_AXIS: u16 = 2057;
+    pub const ELLIPSOID_SEMI_MINOR_AXIS: u16 = 2058;
+    pub const ELLIPSOID_INV_FLATTENING: u16 = 2059;
+    pub const GEOG_AZIMUTH_UNITS: u16 = 2060;
+    pub const PRIME_MERIDIAN_LONGITUDE: u16 = 2061;
+
+    // Projected CRS
+    pub const PROJECTED_CRS: u16 = 3072;
+    pub const PROJECTED_CITATION: u16 = 3073;
+    pub const PROJECTION: u16 = 3074;
+    pub const PROJ_METHOD: u16 = 3075;
+    pub const PROJ_LINEAR_UNITS: u16 = 3076;
+    pub const PROJ_LINEAR_UNIT_SIZE: u16 = 3077;
+    pub const PROJ_STD_PARALLEL_1: u16 = 3078;
+    pub const PROJ_STD_PARALLEL_2: u16 = 3079;
+    pub const PROJ_NAT_ORIGIN_LONG: u16 = 3080;
+    pub const PROJ_NAT_ORIGIN_LAT: u16 = 3081;
+    pub const PROJ_FALSE_EASTING: u16 = 3082;
+    pub const PROJ_FALSE_NORTHING: u16 = 3083;
+    pub const PROJ_FALSE_ORIGIN_LONG: u16 = 3084;
+    pub const PROJ_FALSE_ORIGIN_LAT: u16 = 3085;
+    pub const PROJ_FALSE_ORIGIN_EASTING: u16 = 3086;
+    pub const PROJ_FALSE_ORIGIN_NORTHING: u16 = 3087;
+    pub const PROJ_CENTER_LONG: u16 = 3088;
+    pub const PROJ_CENTER_LAT: u16 = 3089;
+    pub const PROJ_CENTER_EASTING: u16 = 3090;
+    pub const PROJ_CENTER_NORTHING: u16 = 3091;
+    pub const PROJ_SCALE_AT_NAT_ORIGIN: u16 = 3092;
+    pub const PROJ_SCALE_AT_CENTER: u16 = 3093;
+    pub const PROJ_AZIMUTH_ANGLE: u16 = 3094;
+    pub const PROJ_STRAIGHT_VERT_POLE_LONG: u16 = 3095;
+
+    // Vertical CRS
+    pub const VERTICAL_CRS: u16 = 4096;
+    pub const VERTICAL_CITATION: u16 = 4097;
+    pub const VERTICAL_DATUM: u16 = 4098;
+    pub const VERTICAL_UNITS: u16 = 4099;
+
+    /// Value of CRS and unit keys which are described by further keys rather than an EPSG code.
+    pub const USER_DEFINED: u16 = 32767;
+}
+
+/// Define an enum of the codes of a GeoKey, with a fallback for codes without a variant.
+macro_rules! geokey_code_enum {
+    { $(#[$meta:meta])* $name:ident { $($(#[$variant_meta:meta])* $variant:ident = $code:literal,)* } } => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)*
+            /// A code without a variant of its own, such as `keys::USER_DEFINED`.
+            Other(u16),
+        }
+
+        impl $name {
+            /// Interpret the value of a GeoKey.
+            pub fn from_code(code: u16) -> Self {
+                match code {
+                    $($code => Self::$variant,)*
+                    code => Self::Other(code),
+                }
+            }
+
+            /// The value of a GeoKey holding this.
+            pub fn code(self) -> u16 {
+                match self {
+                    $(Self::$variant => $code,)*
+                    Self::Other(code) => code,
+                }
+            }
+        }
+    };
+}
+
+geokey_code_enum! {
+    /// The kind of coordinate system of model space, held by `keys::GT_MODEL_TYPE`.
+    ModelType {
+        Projected = 1,
+        Geographic = 2,
+        Geocentric = 3,
+    }
+}
+
+geokey_code_enum! {
+    /// How pixel values relate to raster space, held by `keys::GT_RASTER_TYPE`.
+    RasterType {
+        /// Each pixel covers a unit square of raster space.
+        PixelIsArea = 1,
+        /// Each pixel is a sample at a point of raster space.
+        PixelIsPoint = 2,
+    }
+}
+
+geokey_code_enum! {
+    /// EPSG codes of units of length, held by keys such as `keys::PROJ_LINEAR_UNITS`.
+    LinearUnit {
+        Metre = 9001,
+        Foot = 9002,
+        UsSurveyFoot = 9003,
+        NauticalMile = 9030,
+        Kilometre = 9036,
+    }
+}
+
+geokey_code_enum! {
+    /// EPSG codes of units of angle, held by keys such as `keys::GEOG_ANGULAR_UNITS`.
+    AngularUnit {
+        Radian = 9101,
+        Degree = 9102,
+        ArcMinute = 9103,
+        ArcSecond = 9104,
+        Grad = 9105,
+    }
+}
+
+/// The value of a GeoKey, which is stored in one of the three GeoTIFF parameter tags.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoKeyValue {
+    /// SHORT values, stored within `GEO_KEY_DIRECTORY` itself.
+    Short(Vec<u16>),
+    /// DOUBLE values, stored in `GEO_DOUBLE_PARAMS`.
+    Double(Vec<f64>),
+    /// A string, stored in `GEO_ASCII_PARAMS`.
+    Ascii(String),
+}
+
+/// The GeoKeys of an IFD, decoded from `GEO_KEY_DIRECTORY`, `GEO_DOUBLE_PARAMS` and
+/// `GEO_ASCII_PARAMS`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoKeyDirectory {
+    /// `KeyRevision` and `MinorRevision` of the directory: (1, 0) for GeoTIFF 1.0 and (1, 1) for
+    /// GeoTIFF 1.1.
+    pub revision: (u16, u16),
+    /// Values by key number, such as `keys::GT_MODEL_TYPE`.
+    pub keys: BTreeMap<u16, GeoKeyValue>,
+}
+
+impl Default for GeoKeyDirectory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GeoKeyDirectory {
+    /// Create an empty GeoTIFF 1.1 directory.
+    pub fn new() -> Self {
+        Self {
+            revision: (1, 1),
+            keys: BTreeMap::new(),
+        }
+    }
+
+    /// Read the GeoKeys of `ifd`, or `None` if it has no `GEO_KEY_DIRECTORY`.
+    pub fn from_ifd(ifd: &IFD) -> Result<Option<Self>> {
+        let directory: &[u16] = match ifd.get(tags::GEO_KEY_DIRECTORY) {
+            Err(FieldExtractionError::MissingTag { .. }) => return Ok(None),
+            result => result?,
+        };
+        let doubles: &[f64] = match ifd.get(tags::GEO_DOUBLE_PARAMS) {
+            Err(FieldExtractionError::MissingTag { .. }) => &[],
+            result => result?,
+        };
+        let ascii: String = match ifd.get(tags::GEO_ASCII_PARAMS) {
+            Err(FieldExtractionError::MissingTag { .. }) => String::new(),
+            result => result?,
+        };
+
+        let header = directory.get(..4).ok_or(GeoTiffError::TruncatedDirectory)?;
+        if header[0] != KEY_DIRECTORY_VERSION {
+            return Err(GeoTiffError::UnsupportedVersion { version: header[0] }.into());
+        }
+        let key_count = usize::from(header[3]);
+        let entries = directory
+            .get(4..4 + key_count * 4)
+            .ok_or(GeoTiffError::TruncatedDirectory)?;
+
+        let mut keys = BTreeMap::new();
+        for entry in entries.chunks_exact(4) {
+            let (key, location, count, value) = (entry[0], entry[1], entry[2], entry[3]);
+            let range = usize::from(value)..usize::from(value) + usize::from(count);
+            let missing = GeoTiffError::MissingValues { key, location };
+            let value = match location {
+                0 => GeoKeyValue::Short(vec![value]),
+                tags::GEO_KEY_DIRECTORY => {
+                    GeoKeyValue::Short(directory.get(range).ok_or(missing)?.to_vec())
+                }
+                tags::GEO_DOUBLE_PARAMS => {
+                    GeoKeyValue::Double(doubles.get(range).ok_or(missing)?.to_vec())
+                }
+                tags::GEO_ASCII_PARAMS => {
+                    let value = ascii.get(range).ok_or(missing)?;
+                    let value = value.strip_suffix(ASCII_TERMINATOR).unwrap_or(value);
+                    GeoKeyValue::Ascii(value.to_string())
+                }
+                location => return Err(GeoTiffError::UnsupportedLocation { key, location }.into()),
+            };
+            keys.insert(key, value);
+        }
+
+        Ok(Some(Self {
+            revision: (header[1], header[2]),
+            keys,
+        }))
+    }
+
+    /// Store these GeoKeys in `ifd`, replacing any GeoTIFF parameter tags it already holds. Keys
+    /// are written in ascending order, as GeoTIFF requires.
+    pub fn write_to_ifd(&self, ifd: &mut IFD) -> Result<()> {
+        let key_count = u16_count(self.keys.len(), keys::GT_MODEL_TYPE)?;
+        let mut directory = vec![
+            KEY_DIRECTORY_VERSION,
+            self.revision.0,
+            self.revision.1,
+            key_count,
+        ];
+        // SHORT values which do not fit within their entry follow the entries
+        let mut shorts = Vec::new();
+        let mut doubles = Vec::new();
+        let mut ascii = String::new();
+        for (&key, value) in &self.keys {
+            let entry = match value {
+                GeoKeyValue::Short(values) if values.len() == 1 => [key, 0, 1, values[0]],
+                GeoKeyValue::Short(values) => {
+                    let offset = 4 + self.keys.len() * 4 + shorts.len();
+                    shorts.extend_from_slice(values);
+                    [
+                        key,
+                        tags::GEO_KEY_DIRECTORY,
+                        u16_count(values.len(), key)?,
+                        u16_count(offset, key)?,
+                    ]
+                }
+                GeoKeyValue::Double(values) => {
+                    let offset = doubles.len();
+                    doubles.extend_from_slice(values);
+                    [
+                        key,
+                        tags::GEO_DOUBLE_PARAMS,
+                        u16_count(values.len(), key)?,
+                        u16_count(offset, key)?,
+                    ]
+                }
+                GeoKeyValue::Ascii(value) => {
+                    let offset = ascii.len();
+                    ascii.push_str(value);
+                    ascii.push(ASCII_TERMINATOR);
+                    [
+                        key,
+                        tags::GEO_ASCII_PARAMS,
+                        u16_count(value.len() + 1, key)?,
+                        u16_count(offset, key)?,
+                    ]
+                }
+            };
+            directory.extend_from_slice(&entry);
+        }
+        directory.extend(shorts);
+
+        ifd.entries.insert(
+            tags::GEO_KEY_DIRECTORY,
+            IFDField::Short(directory.into_boxed_slice()),
+        );
+        set_or_remove(
+            ifd,
+            tags::GEO_DOUBLE_PARAMS,
+            Some(doubles)
+                .filter(|doubles| !doubles.is_empty())
+                .map(|doubles| IFDField::Double(doubles.into_boxed_slice())),
+        );
+        set_or_remove(
+            ifd,
+            tags::GEO_ASCII_PARAMS,
+            Some(ascii)
+                .filter(|ascii| !ascii.is_empty())
+                .map(|ascii| IFDField::Ascii(Box::new([ascii]))),
+        );
+        Ok(())
+    }
+
+    /// The value of `key`, if present.
+    pub fn get(&self, key: u16) -> Option<&GeoKeyValue> {
+        self.keys.get(&key)
+    }
+
+    /// Set the value of `key`.
+    pub fn set(&mut self, key: u16, value: GeoKeyValue) {
+        self.keys.insert(key, value);
+    }
+
+    /// The first SHORT value of `key`, if it holds any.
+    pub fn short(&self, key: u16) -> Option<u16> {
+        match self.get(key)? {
+            GeoKeyValue::Short(values) => values.first().copied(),
+            _ => None,
+        }
+    }
+
+    /// The first DOUBLE value of `key`, if it holds any.
+    pub fn double(&self, key: u16) -> Option<f64> {
+        match self.get(key)? {
+            GeoKeyValue::Double(values) => values.first().copied(),
+            _ => None,
+        }
+    }
+
+    /// The string held by `key`, if any.
+    pub fn ascii(&self, key: u16) -> Option<&str> {
+        match self.get(key)? {
+            GeoKeyValue::Ascii(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Set `key` to the single SHORT `value`.
+    pub fn set_short(&mut self, key: u16, value: u16) {
+        self.set(key, GeoKeyValue::Short(vec![value]));
+    }
+
+    /// The kind of coordinate system of model space.
+    pub fn model_type(&self) -> Option<ModelType> {
+        self.short(keys::GT_MODEL_TYPE).map(ModelType::from_code)
+    }
+
+    /// Set the kind of coordinate system of model space.
+    pub fn set_model_type(&mut self, model_type: ModelType) {
+        self.set_short(keys::GT_MODEL_TYPE, model_type.code());
+    }
+
+    /// How pixel values relate to raster space. GeoTIFF defaults to `PixelIsArea` when the key
+    /// is absent.
+    pub fn raster_type(&self) -> RasterType {
+        self.short(keys::GT_RASTER_TYPE)
+            .map_or(RasterType::PixelIsArea, RasterType::from_code)
+    }
+
+    /// Set how pixel values relate to raster space.
+    pub fn set_raster_type(&mut self, raster_type: RasterType) {
+        self.set_short(keys::GT_RASTER_TYPE, raster_type.code());
+    }
+
+    /// EPSG code of the projected CRS, or `keys::USER_DEFINED`.
+    pub fn projected_crs(&self) -> Option<u16> {
+        self.short(keys::PROJECTED_CRS)
+    }
+
+    /// Set the EPSG code of the projected CRS.
+    pub fn set_projected_crs(&mut self, epsg: u16) {
+        self.set_short(keys::PROJECTED_CRS, epsg);
+    }
+
+    /// EPSG code of the geodetic CRS, or `keys::USER_DEFINED`.
+    pub fn geodetic_crs(&self) -> Option<u16> {
+        self.short(keys::GEODETIC_CRS)
+    }
+
+    /// Set the EPSG code of the geodetic CRS.
+    pub fn set_geodetic_crs(&mut self, epsg: u16) {
+        self.set_short(keys::GEODETIC_CRS, epsg);
+    }
+
+    /// EPSG code of the vertical CRS, or `keys::USER_DEFINED`.
+    pub fn vertical_crs(&self) -> Option<u16> {
+        self.short(keys::VERTICAL_CRS)
+    }
+
+    /// Set the EPSG code of the vertical CRS.
+    pub fn set_vertical_crs(&mut self, epsg: u16) {
+        self.set_short(keys::VERTICAL_CRS, epsg);
+    }
+
+    /// Units of length of a projected CRS.
+    pub fn linear_units(&self) -> Option<LinearUnit> {
+        self.short(keys::PROJ_LINEAR_UNITS)
+            .map(LinearUnit::from_code)
+    }
+
+    /// Set the units of length of a projected CRS.
+    pub fn set_linear_units(&mut self, units: LinearUnit) {
+        self.set_short(keys::PROJ_LINEAR_UNITS, units.code());
+    }
+
+    /// Units of angle of a geodetic CRS.
+    pub fn angular_units(&self) -> Option<AngularUnit> {
+        self.short(keys::GEOG_ANGULAR_UNITS)
+            .map(AngularUnit::from_code)
+    }
+
+    /// Set the units of angle of a geodetic CRS.
+    pub fn set_angular_units(&mut self, units: AngularUnit) {
+        self.set_short(keys::GEOG_ANGULAR_UNITS, units.code());
+    }
+
+    /// Description of the georeferencing as a whole.
+    pub fn citation(&self) -> Option<&str> {
+        self.ascii(keys::GT_CITATION)
+    }
+
+    /// Set the description of the georeferencing as a whole.
+    pub fn set_citation(&mut self, citation: &str) {
+        self.set(keys::GT_CITATION, GeoKeyValue::Ascii(citation.to_string()));
+    }
+}
+
+/// A point of raster space and the point of model space it corresponds to, as held by
+/// `MODEL_TIEPOINT`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Tiepoint {
+    /// Raster (I, J, K) coordinates, where I runs along rows and J down columns.
+    pub raster: [f64; 3],
+    /// Model (X, Y, Z) coordinates.
+    pub model: [f64; 3],
+}
+
+/// An affine transform of 2D points: `x' = a x + b y + c` and `y' = d x + e y + f`, with
+/// `coefficients` holding `[a, b, c, d, e, f]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AffineTransform {
+    pub coefficients: [f64; 6],
+}
+
+impl AffineTransform {
+    /// Transform the point (`x`, `y`).
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        let [a, b, c, d, e, f] = self.coefficients;
+        (a * x + b * y + c, d * x + e * y + f)
+    }
+
+    /// The transform undoing this one, or `None` if it is singular.
+    pub fn inverse(&self) -> Option<Self> {
+        let [a, b, c, d, e, f] = self.coefficients;
+        let determinant = a * e - b * d;
+        if determinant == 0.0 || !determinant.is_finite() {
+            return None;
+        }
+        let (a, b, d, e) = (
+            e / determinant,
+            -b / determinant,
+            -d / determinant,
+            a / determinant,
+        );
+        Some(Self {
+            coefficients: [a, b, -(a * c + b * f), d, e, -(d * c + e * f)],
+        })
+    }
+}
+
+/// The georeferencing of an image: its GeoKeys and the relation between raster and model space.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct GeoTiff {
+    /// The GeoKeys describing model space.
+    pub keys: GeoKeyDirectory,
+    /// Size of a pixel in model space along X, Y and Z, held by `MODEL_PIXEL_SCALE`.
+    pub pixel_scale: Option<[f64; 3]>,
+    /// Points tying raster space to model space, held by `MODEL_TIEPOINT`.
+    pub tiepoints: Vec<Tiepoint>,
+    /// Row-major 4x4 matrix transforming raster space to model space, held by
+    /// `MODEL_TRANSFORMATION` in place of a pixel scale and tiepoint.
+    pub transformation: Option<[f64; 16]>,
+}
+
+impl GeoTiff {
+    /// Read the GeoTIFF tags of `ifd`, or `None` if it has none.
+    pub fn from_ifd(ifd: &IFD) -> Result<Option<Self>> {
+        let directory = GeoKeyDirectory::from_ifd(ifd)?;
+        let pixel_scale = doubles(ifd, tags::MODEL_PIXEL_SCALE, 3)?.map(|values| {
+            let mut scale = [0.0; 3];
+            scale.copy_from_slice(&values[..3]);
+            scale
+        });
+        let tiepoints = match doubles(ifd, tags::MODEL_TIEPOINT, 6)? {
+            Some(values) if values.len() % 6 != 0 => {
+                return Err(GeoTiffError::InvalidValueCount {
+                    tag: tags::MODEL_TIEPOINT,
+                    multiple: 6,
+                }
+                .into())
+            }
+            Some(values) => values
+                .chunks_exact(6)
+                .map(|values| Tiepoint {
+                    raster: [values[0], values[1], values[2]],
+                    model: [values[3], values[4], values[5]],
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        let transformation = doubles(ifd, tags::MODEL_TRANSFORMATION, 16)?.map(|values| {
+            let mut matrix = [0.0; 16];
+            matrix.copy_from_slice(&values[..16]);
+            matrix
+        });
+
+        if directory.is_none()
+            && pixel_scale.is_none()
+            && tiepoints.is_empty()
+            && transformation.is_none()
+        {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            keys: directory.unwrap_or_default(),
+            pixel_scale,
+            tiepoints,
+            transformation,
+        }))
+    }
+
+    /// Store this georeferencing in `ifd`, removing any GeoTIFF tags it does not use.
+    pub fn write_to_ifd(&self, ifd: &mut IFD) -> Result<()> {
+        self.keys.write_to_ifd(ifd)?;
+        set_or_remove(
+            ifd,
+            tags::MODEL_PIXEL_SCALE,
+            self.pixel_scale
+                .map(|scale| IFDField::Double(Box::new(scale))),
+        );
+        set_or_remove(
+            ifd,
+            tags::MODEL_TIEPOINT,
+            Some(&self.tiepoints)
+                .filter(|tiepoints| !tiepoints.is_empty())
+                .map(|tiepoints| {
+                    IFDField::Double(
+                        tiepoints
+                            .iter()
+                            .flat_map(|tiepoint| tiepoint.raster.iter().chain(&tiepoint.model))
+                            .copied()
+                            .collect(),
+                    )
+                }),
+        );
+        set_or_remove(
+            ifd,
+            tags::MODEL_TRANSFORMATION,
+            self.transformation
+                .map(|matrix| IFDField::Double(Box::new(matrix))),
+        );
+        Ok(())
+    }
+
+    /// The transform from raster space (column, row) to model space (X, Y), taken from the
+    /// transformation matrix if present, or otherwise from the first tiepoint and the pixel
+    /// scale. With `RasterType::PixelIsArea`, pixel (i, j) covers raster space from (i, j) to
+    /// (i + 1, j + 1). Returns `None` if neither is present.
+    pub fn pixel_to_model(&self) -> Option<AffineTransform> {
+        if let Some(m) = self.transformation {
+            return Some(AffineTransform {
+                coefficients: [m[0], m[1], m[3], m[4], m[5], m[7]],
+            });
+        }
+        let tiepoint = self.tiepoints.first()?;
+        let [scale_x, scale_y, _] = self.pixel_scale?;
+        // Model Y increases upwards while raster rows increase downwards
+        Some(AffineTransform {
+            coefficients: [
+                scale_x,
+                0.0,
+                tiepoint.model[0] - tiepoint.raster[0] * scale_x,
+                0.0,
+                -scale_y,
+                tiepoint.model[1] + tiepoint.raster[1] * scale_y,
+            ],
+        })
+    }
+}
+
+/// Read the DOUBLE values of `tag`, of which there must be at least `count`, or `None` if the tag
+/// is absent.
+fn doubles(ifd: &IFD, tag: u16, count: usize) -> Result<Option<&[f64]>> {
+    let values: &[f64] = match ifd.get(tag) {
+        Err(FieldExtractionError::MissingTag { .. }) => return Ok(None),
+        result => result?,
+    };
+    if values.len() < count {
+        return Err(FieldExtractionError::InsufficientData { tag }.into());
+    }
+    Ok(Some(values))
+}
+
+/// Store `field` as `tag` in `ifd`, or remove `tag` if there is none.
+fn set_or_remove(ifd: &mut IFD, tag: u16, field: Option<IFDField>) {
+    match field {
+        Some(field) => ifd.entries.insert(tag, field),
+        None => ifd.entries.remove(&tag),
+    };
+}
+
+/// Narrow a count or offset of GeoKey `key` to the SHORT it is stored as.
+fn u16_count(value: usize, key: u16) -> Result<u16> {
+    Ok(u16::try_from(value).map_err(|_| GeoTiffError::TooManyValues { key })?)
+}
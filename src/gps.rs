@@ -0,0 +1,136 @@
+use crate::baseline::tags::gps;
+use crate::errors::{FieldExtractionError, GpsError, Result};
+use crate::lowlevel::{IFDField, IFD};
+
+/// `GPS_LATITUDE_REF` of latitudes north of the equator.
+pub const NORTH: &str = "N";
+/// `GPS_LATITUDE_REF` of latitudes south of the equator.
+pub const SOUTH: &str = "S";
+/// `GPS_LONGITUDE_REF` of longitudes east of the prime meridian.
+pub const EAST: &str = "E";
+/// `GPS_LONGITUDE_REF` of longitudes west of the prime meridian.
+pub const WEST: &str = "W";
+
+/// `GPS_VERSION_ID` written along with coordinates if the IFD has none.
+const VERSION_ID: [u8; 4] = [2, 3, 0, 0];
+
+/// Denominator of the seconds written by `degrees_to_dms`, which keeps them to within a few
+/// millimetres.
+const SECONDS_SCALE: u32 = 10_000;
+
+/// A position on the earth in signed decimal degrees, as held by the `GPS_LATITUDE` and
+/// `GPS_LONGITUDE` tags of a GPS info IFD and their references.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpsCoordinates {
+    /// Degrees north of the equator, negative to the south.
+    pub latitude: f64,
+    /// Degrees east of the prime meridian, negative to the west.
+    pub longitude: f64,
+}
+
+impl GpsCoordinates {
+    /// Read the coordinates of the GPS info IFD `ifd`, or `None` if it holds no position.
+    pub fn from_ifd(ifd: &IFD) -> Result<Option<Self>> {
+        if !ifd.entries.contains_key(&gps::GPS_LATITUDE)
+            || !ifd.entries.contains_key(&gps::GPS_LONGITUDE)
+        {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            latitude: read_coordinate(ifd, gps::GPS_LATITUDE, gps::GPS_LATITUDE_REF, NORTH, SOUTH)?,
+            longitude: read_coordinate(
+                ifd,
+                gps::GPS_LONGITUDE,
+                gps::GPS_LONGITUDE_REF,
+                EAST,
+                WEST,
+            )?,
+        }))
+    }
+
+    /// Store these coordinates in the GPS info IFD `ifd`. Fails if they are out of range.
+    pub fn write_to_ifd(&self, ifd: &mut IFD) -> Result<()> {
+        if !(-90.0..=90.0).contains(&self.latitude) {
+            return Err(GpsError::OutOfRange {
+                degrees: self.latitude,
+            }
+            .into());
+        }
+        if !(-180.0..=180.0).contains(&self.longitude) {
+            return Err(GpsError::OutOfRange {
+                degrees: self.longitude,
+            }
+            .into());
+        }
+
+        ifd.entries
+            .entry(gps::GPS_VERSION_ID)
+            .or_insert_with(|| IFDField::Byte(Box::new(VERSION_ID)));
+        let (latitude, latitude_ref) = degrees_to_dms(self.latitude, NORTH, SOUTH);
+        let (longitude, longitude_ref) = degrees_to_dms(self.longitude, EAST, WEST);
+        let reference = |reference: &str| IFDField::Ascii(Box::new([reference.to_string()]));
+        ifd.entries
+            .insert(gps::GPS_LATITUDE_REF, reference(latitude_ref));
+        ifd.entries
+            .insert(gps::GPS_LATITUDE, IFDField::Rational(Box::new(latitude)));
+        ifd.entries
+            .insert(gps::GPS_LONGITUDE_REF, reference(longitude_ref));
+        ifd.entries
+            .insert(gps::GPS_LONGITUDE, IFDField::Rational(Box::new(longitude)));
+        Ok(())
+    }
+}
+
+/// Convert degrees, minutes and seconds into decimal degrees, negated if `negative`. Returns
+/// `None` if any rational has a zero denominator.
+pub fn dms_to_degrees(dms: [(u32, u32); 3], negative: bool) -> Option<f64> {
+    let mut degrees = 0.0;
+    for (&(numerator, denominator), unit) in dms.iter().zip(&[1.0, 60.0, 3600.0]) {
+        if denominator == 0 {
+            return None;
+        }
+        degrees += f64::from(numerator) / f64::from(denominator) / unit;
+    }
+    Some(if negative { -degrees } else { degrees })
+}
+
+/// Convert signed decimal degrees into whole degrees, whole minutes and fractional seconds, along
+/// with the reference of their sign: `positive` (such as `NORTH`) or `negative` (such as `SOUTH`).
+pub fn degrees_to_dms<'a>(
+    degrees: f64,
+    positive: &'a str,
+    negative: &'a str,
+) -> ([(u32, u32); 3], &'a str) {
+    let reference = if degrees < 0.0 { negative } else { positive };
+    // Round once in the smallest unit, so seconds never round up to 60
+    let scale = u64::from(SECONDS_SCALE);
+    let total = (degrees.abs() * 3600.0 * scale as f64).round() as u64;
+    let dms = [
+        ((total / (3600 * scale)) as u32, 1),
+        ((total / (60 * scale) % 60) as u32, 1),
+        ((total % (60 * scale)) as u32, SECONDS_SCALE),
+    ];
+    (dms, reference)
+}
+
+/// Read the coordinate of rational triple `tag`, negated if its reference `ref_tag` is `negative`
+/// rather than `positive`.
+fn read_coordinate(
+    ifd: &IFD,
+    tag: u16,
+    ref_tag: u16,
+    positive: &str,
+    negative: &str,
+) -> Result<f64> {
+    let values: &[(u32, u32)] = ifd.get(tag)?;
+    if values.len() < 3 {
+        return Err(FieldExtractionError::InsufficientData { tag }.into());
+    }
+    let reference: String = ifd.get(ref_tag)?;
+    let reference = reference.trim().to_ascii_uppercase();
+    if reference != positive && reference != negative {
+        return Err(GpsError::InvalidReference { tag: ref_tag }.into());
+    }
+    let dms = [values[0], values[1], values[2]];
+    Ok(dms_to_degrees(dms, reference == negative).ok_or(GpsError::ZeroDenominator { tag })?)
+}
@@ -0,0 +1,104 @@
+use crate::baseline::constants::sample_format;
+use crate::errors::{DecodingError, EncodingError, Result};
+use crate::image::{PixelBuffer, SampleType};
+
+/// Size in bytes of a row of `samples` samples of `bits_per_sample` bits each, padded to a byte.
+pub fn packed_row_bytes(samples: usize, bits_per_sample: u16) -> usize {
+    (samples * usize::from(bits_per_sample)).div_ceil(8)
+}
+
+/// Reverse the order of the bits within each byte, converting between `FILL_ORDER` values.
+pub fn reverse_bits(data: &mut [u8]) {
+    data.iter_mut().for_each(|byte| *byte = byte.reverse_bits());
+}
+
+/// Unpack rows of `samples_per_row` samples of `bits_per_sample` bits each (1 through 32),
+/// packed from the most significant bit down with each row padded to a byte, into the smallest
+/// of u8, u16 or u32 samples which holds them. Any incomplete row at the end is ignored.
+pub fn unpack_samples(
+    data: &[u8],
+    bits_per_sample: u16,
+    samples_per_row: usize,
+) -> Result<PixelBuffer> {
+    let sample_type = SampleType::from_format(sample_format::UNSIGNED_INTEGER, bits_per_sample)?;
+    if bits_per_sample > 32 {
+        return Err(DecodingError::UnsupportedSampleFormat {
+            sample_format: sample_format::UNSIGNED_INTEGER,
+            bits_per_sample,
+        }
+        .into());
+    }
+
+    let bits = u32::from(bits_per_sample);
+    let row_bytes = packed_row_bytes(samples_per_row, bits_per_sample).max(1);
+    let mut samples = Vec::with_capacity(data.len() / row_bytes * samples_per_row);
+    for row in data.chunks_exact(row_bytes) {
+        let mut bytes = row.iter();
+        let mut buffer = 0u64;
+        let mut buffered = 0;
+        for _ in 0..samples_per_row {
+            while buffered < bits {
+                buffer = buffer << 8 | u64::from(*bytes.next().unwrap_or(&0));
+                buffered += 8;
+            }
+            buffered -= bits;
+            samples.push((buffer >> buffered) as u32 & mask(bits));
+            buffer &= (1 << buffered) - 1;
+        }
+    }
+
+    Ok(match sample_type {
+        SampleType::U8 => PixelBuffer::U8(samples.iter().map(|&sample| sample as u8).collect()),
+        SampleType::U16 => PixelBuffer::U16(samples.iter().map(|&sample| sample as u16).collect()),
+        _ => PixelBuffer::U32(samples),
+    })
+}
+
+/// Pack u8, u16 or u32 `samples` into rows of `samples_per_row` samples of `bits_per_sample` bits
+/// each, from the most significant bit down with each row padded to a byte. Fails if the samples
+/// are of a type which cannot hold `bits_per_sample` bits. Higher bits of samples are discarded.
+pub fn pack_samples(
+    samples: &PixelBuffer,
+    bits_per_sample: u16,
+    samples_per_row: usize,
+) -> Result<Vec<u8>> {
+    let samples: Vec<u32> = match samples {
+        PixelBuffer::U8(samples) if bits_per_sample <= 8 => {
+            samples.iter().copied().map(u32::from).collect()
+        }
+        PixelBuffer::U16(samples) if bits_per_sample <= 16 => {
+            samples.iter().copied().map(u32::from).collect()
+        }
+        PixelBuffer::U32(samples) if bits_per_sample <= 32 => samples.clone(),
+        _ => return Err(EncodingError::UnsupportedBitDepth { bits_per_sample }.into()),
+    };
+    if bits_per_sample == 0 {
+        return Err(EncodingError::UnsupportedBitDepth { bits_per_sample }.into());
+    }
+
+    let bits = u32::from(bits_per_sample);
+    let row_bytes = packed_row_bytes(samples_per_row, bits_per_sample);
+    let mut data = Vec::with_capacity(samples.len() / samples_per_row.max(1) * row_bytes);
+    for row in samples.chunks(samples_per_row.max(1)) {
+        let mut buffer = 0u64;
+        let mut buffered = 0;
+        for &sample in row {
+            buffer = buffer << bits | u64::from(sample & mask(bits));
+            buffered += bits;
+            while buffered >= 8 {
+                buffered -= 8;
+                data.push((buffer >> buffered) as u8);
+            }
+            buffer &= (1 << buffered) - 1;
+        }
+        if buffered > 0 {
+            data.push((buffer << (8 - buffered)) as u8);
+        }
+    }
+    Ok(data)
+}
+
+/// The lowest `bits` bits set.
+fn mask(bits: u32) -> u32 {
+    (u64::from(u32::MAX) >> (32 - bits)) as u32
+}
@@ -0,0 +1,298 @@
+use crate::baseline::{
+    constants::{extra_samples, ink_set, photometic_interpretation, planar_configuration},
+    tags,
+};
+use crate::errors::{ColorError, FieldExtractionError, Result};
+use crate::image::{color_samples, uniform_value, Image, PixelBuffer, YCbCr};
+use crate::lowlevel::IFD;
+
+/// Reference white of CIELab samples (D50), as used by ICC profiles.
+const LAB_WHITE: [f64; 3] = [0.9642, 1.0, 0.8249];
+
+/// Linear sRGB from CIE XYZ relative to D50, using the Bradford chromatic adaptation.
+const XYZ_D50_TO_SRGB: [[f64; 3]; 3] = [
+    [3.1338561, -1.6168667, -0.4906146],
+    [-0.9787684, 1.9161415, 0.0334540],
+    [0.0719453, -0.2289914, 1.4052427],
+];
+
+/// Converts decoded images of any supported `PHOTOMETRIC_INTERPRETATION` into RGB or RGBA.
+#[derive(Debug, Clone)]
+pub struct ColorConverter {
+    photometric: u16,
+    samples_per_pixel: u16,
+    bits_per_sample: u16,
+    /// Red, then green, then blue values of each palette entry.
+    color_map: Option<Vec<u16>>,
+    /// Coefficients and references of YCbCr samples.
+    ycbcr: Option<YCbCr>,
+    /// Index of the alpha sample within each pixel, and whether the colour samples have been
+    /// premultiplied by it.
+    alpha: Option<(usize, bool)>,
+    /// Whether output colour samples are premultiplied by alpha.
+    associated_output: bool,
+}
+
+impl ColorConverter {
+    /// Prepare to convert images described by `ifd`.
+    pub fn new(ifd: &IFD) -> Result<Self> {
+        let photometric = ifd.unsigned_or(
+            tags::PHOTOMETRIC_INTERPRETATION,
+            photometic_interpretation::BLACKISZERO,
+        )?;
+        let samples_per_pixel = ifd.samples_per_pixel()?;
+        let bits_per_sample = uniform_value(tags::BITS_PER_SAMPLE, &ifd.bits_per_sample()?)?;
+
+        let ycbcr = if photometric == photometic_interpretation::YCBCR {
+            Some(YCbCr::from_ifd(ifd, bits_per_sample)?)
+        } else {
+            None
+        };
+
+        let color_map = match photometric {
+            photometic_interpretation::WHITEISZERO
+            | photometic_interpretation::BLACKISZERO
+            | photometic_interpretation::RGB
+            | photometic_interpretation::YCBCR
+            | photometic_interpretation::CIELAB => None,
+            photometic_interpretation::RGB_PALETTE => {
+                let color_map: Vec<u64> = ifd.get(tags::COLOR_MAP)?;
+                if bits_per_sample > 16 || color_map.len() != 3 << bits_per_sample {
+                    return Err(ColorError::InvalidColorMap.into());
+                }
+                Some(color_map.iter().map(|&value| value as u16).collect())
+            }
+            photometic_interpretation::CMYK => {
+                let inks = ifd.unsigned_or(tags::INK_SET, ink_set::CMYK)?;
+                if inks != ink_set::CMYK {
+                    return Err(ColorError::UnsupportedInkSet { ink_set: inks }.into());
+                }
+                None
+            }
+            _ => return Err(ColorError::UnsupportedPhotometric { photometric }.into()),
+        };
+
+        let colors = color_samples(photometric);
+        if samples_per_pixel < colors {
+            return Err(ColorError::MissingSamples { samples_per_pixel }.into());
+        }
+
+        let extra: Vec<u64> = match ifd.get(tags::EXTRA_SAMPLES) {
+            Err(FieldExtractionError::MissingTag { .. }) => Vec::new(),
+            result => result?,
+        };
+        let alpha = extra
+            .iter()
+            .take(usize::from(samples_per_pixel - colors))
+            .position(|&extra| {
+                extra == extra_samples::ASSOCIATED_ALPHA.into()
+                    || extra == extra_samples::UNASSOCIATED_ALPHA.into()
+            })
+            .map(|index| {
+                (
+                    usize::from(colors) + index,
+                    extra[index] == extra_samples::ASSOCIATED_ALPHA.into(),
+                )
+            });
+
+        Ok(Self {
+            photometric,
+            samples_per_pixel,
+            bits_per_sample,
+            color_map,
+            ycbcr,
+            alpha,
+            associated_output: false,
+        })
+    }
+
+    /// Returns true if converted images have an alpha sample.
+    pub fn has_alpha(&self) -> bool {
+        self.alpha.is_some()
+    }
+
+    /// Premultiply the colour of converted images by their alpha (`ASSOCIATED_ALPHA`), or leave
+    /// it independent of alpha (`UNASSOCIATED_ALPHA`, the default).
+    pub fn with_output_alpha(mut self, extra_sample: u16) -> Self {
+        self.associated_output = extra_sample == extra_samples::ASSOCIATED_ALPHA;
+        self
+    }
+
+    /// Convert `image` to interleaved 8-bit RGB, or RGBA if it has alpha.
+    pub fn to_rgb8(&self, image: &Image) -> Result<Image> {
+        let samples = self.convert(image)?;
+        let data = samples
+            .iter()
+            .map(|&sample| (sample * f64::from(u8::MAX)).round() as u8)
+            .collect();
+        Ok(self.rgb_image(image, PixelBuffer::U8(data)))
+    }
+
+    /// Convert `image` to interleaved 16-bit RGB, or RGBA if it has alpha.
+    pub fn to_rgb16(&self, image: &Image) -> Result<Image> {
+        let samples = self.convert(image)?;
+        let data = samples
+            .iter()
+            .map(|&sample| (sample * f64::from(u16::MAX)).round() as u16)
+            .collect();
+        Ok(self.rgb_image(image, PixelBuffer::U16(data)))
+    }
+
+    /// Describe converted `data` of the same dimensions as `image`.
+    fn rgb_image(&self, image: &Image, data: PixelBuffer) -> Image {
+        Image {
+            width: image.width,
+            height: image.height,
+            samples_per_pixel: if self.has_alpha() { 4 } else { 3 },
+            photometric: photometic_interpretation::RGB,
+            planar_configuration: planar_configuration::CHUNKY,
+            data,
+        }
+    }
+
+    /// Convert the samples of `image` to RGB(A) samples between 0 and 1.
+    fn convert(&self, image: &Image) -> Result<Vec<f64>> {
+        if image.samples_per_pixel != self.samples_per_pixel
+            || image.photometric != self.photometric
+        {
+            return Err(ColorError::ImageMismatch.into());
+        }
+        let image = image.to_interleaved();
+        let raw = raw_samples(&image.data);
+        let max = self.max_value(&image.data);
+
+        let mut output = Vec::with_capacity(raw.len() / usize::from(self.samples_per_pixel) * 4);
+        for pixel in raw.chunks_exact(usize::from(self.samples_per_pixel).max(1)) {
+            let mut rgb = self.pixel_to_rgb(pixel, max);
+            if let Some((index, associated)) = self.alpha {
+                let alpha = (pixel[index] / max).clamp(0.0, 1.0);
+                if associated && alpha > 0.0 {
+                    rgb.iter_mut().for_each(|c| *c = (*c / alpha).min(1.0));
+                }
+                if self.associated_output {
+                    rgb.iter_mut().for_each(|c| *c *= alpha);
+                }
+                output.extend_from_slice(&rgb);
+                output.push(alpha);
+            } else {
+                output.extend_from_slice(&rgb);
+            }
+        }
+        Ok(output)
+    }
+
+    /// Convert the raw samples of a single pixel to RGB between 0 and 1.
+    fn pixel_to_rgb(&self, pixel: &[f64], max: f64) -> [f64; 3] {
+        let normalized = |sample: f64| (sample / max).clamp(0.0, 1.0);
+        match self.photometric {
+            photometic_interpretation::WHITEISZERO => [1.0 - normalized(pixel[0]); 3],
+            photometic_interpretation::RGB => [
+                normalized(pixel[0]),
+                normalized(pixel[1]),
+                normalized(pixel[2]),
+            ],
+            photometic_interpretation::RGB_PALETTE => {
+                let color_map = self.color_map.as_deref().unwrap_or_default();
+                let entries = color_map.len() / 3;
+                let index = (pixel[0].max(0.0) as usize).min(entries.saturating_sub(1));
+                let entry = |channel: usize| {
+                    color_map
+                        .get(channel * entries + index)
+                        .map_or(0.0, |&value| f64::from(value) / f64::from(u16::MAX))
+                };
+                [entry(0), entry(1), entry(2)]
+            }
+            photometic_interpretation::CMYK => {
+                let white = 1.0 - normalized(pixel[3]);
+                [
+                    (1.0 - normalized(pixel[0])) * white,
+                    (1.0 - normalized(pixel[1])) * white,
+                    (1.0 - normalized(pixel[2])) * white,
+                ]
+            }
+            photometic_interpretation::YCBCR => match &self.ycbcr {
+                Some(ycbcr) => ycbcr.to_rgb(pixel[0], pixel[1], pixel[2], max),
+                None => [normalized(pixel[0]); 3],
+            },
+            photometic_interpretation::CIELAB => self.lab_to_rgb(pixel, max),
+            _ => [normalized(pixel[0]); 3],
+        }
+    }
+
+    /// Convert a CIELab pixel to sRGB. L* is unsigned, scaled from 0 to 100 across the sample
+    /// range, while a* and b* are signed, in units of 1 for 8-bit samples.
+    fn lab_to_rgb(&self, pixel: &[f64], max: f64) -> [f64; 3] {
+        let bits = i32::from(self.bits_per_sample);
+        let signed = |sample: f64| {
+            let sample = if sample >= 2f64.powi(bits - 1) {
+                sample - 2f64.powi(bits)
+            } else {
+                sample
+            };
+            sample / 2f64.powi(bits - 8)
+        };
+        let l = pixel[0] / max * 100.0;
+        let (a, b) = (signed(pixel[1]), signed(pixel[2]));
+
+        let inverse = |t: f64| {
+            if t > 6.0 / 29.0 {
+                t.powi(3)
+            } else {
+                3.0 * (6.0f64 / 29.0).powi(2) * (t - 4.0 / 29.0)
+            }
+        };
+        let fy = (l + 16.0) / 116.0;
+        let xyz = [
+            LAB_WHITE[0] * inverse(fy + a / 500.0),
+            LAB_WHITE[1] * inverse(fy),
+            LAB_WHITE[2] * inverse(fy - b / 200.0),
+        ];
+
+        let mut rgb = [0.0; 3];
+        for (channel, row) in rgb.iter_mut().zip(XYZ_D50_TO_SRGB.iter()) {
+            let linear: f64 = row.iter().zip(xyz.iter()).map(|(m, v)| m * v).sum();
+            *channel = srgb_gamma(linear.clamp(0.0, 1.0));
+        }
+        rgb
+    }
+
+    /// The raw sample value standing for full intensity in `data`.
+    fn max_value(&self, data: &PixelBuffer) -> f64 {
+        match data {
+            PixelBuffer::U8(_) | PixelBuffer::U16(_) | PixelBuffer::U32(_) => {
+                2f64.powi(self.bits_per_sample.into()) - 1.0
+            }
+            PixelBuffer::U64(_) => u64::MAX as f64,
+            PixelBuffer::I8(_) => i8::MAX.into(),
+            PixelBuffer::I16(_) => i16::MAX.into(),
+            PixelBuffer::I32(_) => i32::MAX.into(),
+            PixelBuffer::I64(_) => i64::MAX as f64,
+            PixelBuffer::F32(_) | PixelBuffer::F64(_) => 1.0,
+        }
+    }
+}
+
+/// Apply the sRGB transfer curve to a linear intensity.
+fn srgb_gamma(linear: f64) -> f64 {
+    if linear <= 0.003_130_8 {
+        12.92 * linear
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// The samples of `data` as floating point values.
+fn raw_samples(data: &PixelBuffer) -> Vec<f64> {
+    match data {
+        PixelBuffer::U8(samples) => samples.iter().map(|&sample| sample.into()).collect(),
+        PixelBuffer::U16(samples) => samples.iter().map(|&sample| sample.into()).collect(),
+        PixelBuffer::U32(samples) => samples.iter().map(|&sample| sample.into()).collect(),
+        PixelBuffer::U64(samples) => samples.iter().map(|&sample| sample as f64).collect(),
+        PixelBuffer::I8(samples) => samples.iter().map(|&sample| sample.into()).collect(),
+        PixelBuffer::I16(samples) => samples.iter().map(|&sample| sample.into()).collect(),
+        PixelBuffer::I32(samples) => samples.iter().map(|&sample| sample.into()).collect(),
+        PixelBuffer::I64(samples) => samples.iter().map(|&sample| sample as f64).collect(),
+        PixelBuffer::F32(samples) => samples.iter().map(|&sample| sample.into()).collect(),
+        PixelBuffer::F64(samples) => samples.clone(),
+    }
+}
@@ -0,0 +1,689 @@
+use crate::baseline::{
+    constants::{compression, photometic_interpretation, planar_configuration, predictor},
+    tags, FillOrder, PlanarConfiguration,
+};
+use crate::compression::{
+    codec_for, BlockDimensions, Codec, Fax, Jpeg, OldJpeg, Predictor, PredictorCodec,
+};
+use crate::errors::{DecodingError, Error, FieldExtractionError, Result};
+use crate::image::{
+    deinterleave, interleave, reverse_bits, swap_byte_order, uniform_value, unpack_samples, Image,
+    PixelBuffer, SampleType, YCbCr,
+};
+use crate::lowlevel::{IFDField, IFD};
+use std::io::{Read, Seek, SeekFrom};
+
+/// A rectangle of pixels within an image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Rect {
+    /// The overlap between two rectangles, if any.
+    fn intersection(&self, other: &Rect) -> Option<Rect> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (u64::from(self.x) + u64::from(self.width))
+            .min(u64::from(other.x) + u64::from(other.width));
+        let bottom = (u64::from(self.y) + u64::from(self.height))
+            .min(u64::from(other.y) + u64::from(other.height));
+        if right <= x.into() || bottom <= y.into() {
+            None
+        } else {
+            Some(Rect {
+                x,
+                y,
+                width: (right - u64::from(x)) as u32,
+                height: (bottom - u64::from(y)) as u32,
+            })
+        }
+    }
+}
+
+/// Decodes the strips or tiles of an image described by an IFD into typed pixel buffers.
+pub struct ImageDecoder {
+    /// Whether the file is in little endian byte order.
+    little_endian: bool,
+    width: u32,
+    height: u32,
+    samples_per_pixel: u16,
+    sample_type: SampleType,
+    /// Bits in each stored sample, which are packed if they do not fill `sample_type` exactly.
+    bits_per_sample: u16,
+    /// Whether the bits within each byte of the stored data run from least significant up.
+    reversed_bits: bool,
+    photometric: u16,
+    /// Whether each sample is stored in planes of strips or tiles of its own.
+    planar: bool,
+    /// Whether decoded samples are laid out one plane after another rather than interleaved.
+    planar_output: bool,
+    /// Whether the image is cut into tiles rather than strips.
+    tiled: bool,
+    /// Width of each strip or tile. Strips span the entire image width.
+    block_width: u32,
+    /// Height of each strip or tile. The last strip may be cut short by the image height.
+    block_height: u32,
+    /// File offsets of each strip or tile, plane after plane for planar images.
+    offsets: Vec<u64>,
+    /// Compressed size of each strip or tile.
+    byte_counts: Vec<u64>,
+    /// Decompresses strips or tiles, undoing any predictor.
+    codec: Box<dyn Codec>,
+    /// Layout of subsampled YCbCr data, which is upsampled as each block is decoded.
+    ycbcr: Option<YCbCr>,
+    /// Tables and stream locations of old-style JPEG data, which is given the headers it lacks.
+    old_jpeg: Option<OldJpeg>,
+}
+
+impl ImageDecoder {
+    /// Prepare to decode the image described by `ifd`, from a file of the given byte order.
+    pub fn new(ifd: &IFD, little_endian: bool) -> Result<Self> {
+        let width = dimension(ifd, tags::IMAGE_WIDTH)?;
+        let height = dimension(ifd, tags::IMAGE_LENGTH)?;
+        let samples_per_pixel = ifd.samples_per_pixel()?;
+        if samples_per_pixel == 0 {
+            return Err(DecodingError::InvalidSamplesPerPixel.into());
+        }
+
+        let planar = match ifd.planar_configuration()? {
+            PlanarConfiguration::Chunky => false,
+            PlanarConfiguration::Planar => true,
+            other => {
+                return Err(DecodingError::UnsupportedPlanarConfiguration {
+                    planar_configuration: other.into(),
+                }
+                .into())
+            }
+        };
+
+        let bits_per_sample = uniform_value(tags::BITS_PER_SAMPLE, &ifd.bits_per_sample()?)?;
+        let format = uniform_value(tags::SAMPLE_FORMAT, &ifd.sample_format()?)?;
+        let sample_type = SampleType::from_format(format.into(), bits_per_sample)?;
+
+        let reversed_bits = ifd.fill_order()? == FillOrder::LsbToMsb;
+
+        let photometric = ifd.unsigned_or(
+            tags::PHOTOMETRIC_INTERPRETATION,
+            photometic_interpretation::BLACKISZERO,
+        )?;
+
+        let compression = ifd.compression()?.into();
+        let old_jpeg = if compression == compression::JPEG {
+            Some(OldJpeg::from_ifd(ifd)?)
+        } else {
+            None
+        };
+
+        let single_stream = old_jpeg.as_ref().is_some_and(OldJpeg::is_single_stream);
+        let tiled = ifd.entries.contains_key(&tags::TILE_WIDTH);
+        let (block_width, block_height, offsets, byte_counts) = if single_stream {
+            // The image is decoded as a whole, of which any strips or tiles are only pieces
+            let (offsets_tag, byte_counts_tag) = if tiled {
+                (tags::TILE_OFFSETS, tags::TILE_BYTE_COUNTS)
+            } else {
+                (tags::STRIP_OFFSETS, tags::STRIP_BYTE_COUNTS)
+            };
+            (
+                width,
+                height,
+                ifd.get(offsets_tag).unwrap_or_default(),
+                ifd.get(byte_counts_tag).unwrap_or_default(),
+            )
+        } else if tiled {
+            (
+                dimension(ifd, tags::TILE_WIDTH)?,
+                dimension(ifd, tags::TILE_LENGTH)?,
+                ifd.get(tags::TILE_OFFSETS)?,
+                ifd.get(tags::TILE_BYTE_COUNTS)?,
+            )
+        } else {
+            (
+                width,
+                ifd.rows_per_strip()?,
+                ifd.get(tags::STRIP_OFFSETS)?,
+                ifd.get(tags::STRIP_BYTE_COUNTS)?,
+            )
+        };
+        // A single stream holds every sample of each pixel together
+        let tiled = tiled && !single_stream;
+        let planar = planar && !single_stream;
+        if block_width == 0 || block_height == 0 {
+            return Err(DecodingError::InvalidBlockSize.into());
+        }
+
+        let codec: Box<dyn Codec> = match compression {
+            compression::GROUP_3_FAX => Box::new(Fax::new(
+                compression,
+                ifd.unsigned_or(tags::T4_OPTIONS, 0)?,
+            )?),
+            compression::GROUP_4_FAX => Box::new(Fax::new(
+                compression,
+                ifd.unsigned_or(tags::T6_OPTIONS, 0)?,
+            )?),
+            compression::NEW_JPEG => match ifd.entries.get(&tags::JPEG_TABLES) {
+                Some(IFDField::Undefined(tables)) | Some(IFDField::Byte(tables)) => {
+                    Box::new(Jpeg::with_tables(tables.to_vec()))
+                }
+                _ => Box::new(Jpeg::default()),
+            },
+            _ => codec_for(compression)?,
+        };
+        let predictor = ifd.predictor()?.into();
+        let codec: Box<dyn Codec> = if predictor == predictor::NONE {
+            codec
+        } else {
+            Box::new(PredictorCodec {
+                codec,
+                predictor: Predictor {
+                    predictor,
+                    samples_per_pixel: if planar { 1 } else { samples_per_pixel },
+                    bits_per_sample,
+                    little_endian,
+                },
+            })
+        };
+
+        // JPEG compressed data is upsampled by the JPEG decoder itself
+        let ycbcr = if photometric == photometic_interpretation::YCBCR
+            && !matches!(compression, compression::JPEG | compression::NEW_JPEG)
+        {
+            Some(YCbCr::from_ifd(ifd, bits_per_sample)?).filter(YCbCr::is_subsampled)
+        } else {
+            None
+        };
+        if ycbcr.is_some()
+            && (planar
+                || samples_per_pixel != 3
+                || sample_type.is_packed(bits_per_sample)
+                || !matches!(
+                    sample_type,
+                    SampleType::U8 | SampleType::U16 | SampleType::U32
+                ))
+        {
+            return Err(DecodingError::UnsupportedSubsampledLayout.into());
+        }
+
+        Ok(Self {
+            little_endian,
+            width,
+            height,
+            samples_per_pixel,
+            sample_type,
+            bits_per_sample,
+            reversed_bits,
+            photometric,
+            planar,
+            planar_output: false,
+            tiled,
+            block_width,
+            block_height,
+            offsets,
+            byte_counts,
+            codec,
+            ycbcr,
+            old_jpeg,
+        })
+    }
+
+    /// Width of the image in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Height of the image in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Number of samples (channels) in each pixel.
+    pub fn samples_per_pixel(&self) -> u16 {
+        self.samples_per_pixel
+    }
+
+    /// The type decoded samples will have.
+    pub fn sample_type(&self) -> SampleType {
+        self.sample_type
+    }
+
+    /// Bits in each sample as stored in the file.
+    pub fn bits_per_sample(&self) -> u16 {
+        self.bits_per_sample
+    }
+
+    /// `PHOTOMETRIC_INTERPRETATION` of the decoded samples.
+    pub fn photometric(&self) -> u16 {
+        self.photometric
+    }
+
+    /// Lay out decoded samples in `planar_configuration`, regardless of how they are stored in
+    /// the file. By default samples are interleaved.
+    pub fn with_planar_configuration(mut self, planar_configuration: u16) -> Self {
+        self.planar_output = planar_configuration == planar_configuration::PLANAR;
+        self
+    }
+
+    /// Returns true if the file stores each sample in planes of strips or tiles of its own.
+    pub fn is_planar(&self) -> bool {
+        self.planar
+    }
+
+    /// Returns true if the image is stored in tiles rather than strips.
+    pub fn is_tiled(&self) -> bool {
+        self.tiled
+    }
+
+    /// Width and height of each tile, or of each strip.
+    pub fn block_size(&self) -> (u32, u32) {
+        (self.block_width, self.block_height)
+    }
+
+    /// Number of columns of tiles (always 1 for strips).
+    pub fn blocks_across(&self) -> u32 {
+        self.width.div_ceil(self.block_width)
+    }
+
+    /// Number of rows of tiles, or the number of strips.
+    pub fn blocks_down(&self) -> u32 {
+        self.height.div_ceil(self.block_height)
+    }
+
+    /// Number of strips or tiles making up a single plane of the image.
+    pub fn blocks_per_plane(&self) -> usize {
+        self.blocks_across() as usize * self.blocks_down() as usize
+    }
+
+    /// Number of planes the strips or tiles are grouped into: one per sample for planar images,
+    /// otherwise a single plane holding all samples.
+    pub fn planes(&self) -> usize {
+        if self.planar {
+            self.samples_per_pixel.into()
+        } else {
+            1
+        }
+    }
+
+    /// Total number of strips or tiles making up the image.
+    pub fn block_count(&self) -> usize {
+        self.blocks_per_plane() * self.planes()
+    }
+
+    /// Size in bytes of a single decoded sample.
+    fn sample_size(&self) -> usize {
+        self.sample_type.byte_size()
+    }
+
+    /// Number of samples of a single pixel stored within one plane.
+    fn plane_samples(&self) -> usize {
+        usize::from(self.samples_per_pixel) / self.planes()
+    }
+
+    /// Size in bytes of the decoded samples of a single pixel within one plane.
+    fn pixel_size(&self) -> usize {
+        self.plane_samples() * self.sample_size()
+    }
+
+    /// The pixels of the image covered by block `index`, excluding any padding.
+    fn block_rect(&self, index: usize) -> Rect {
+        let index = index % self.blocks_per_plane();
+        let across = self.blocks_across() as usize;
+        let x = (index % across) as u32 * self.block_width;
+        let y = (index / across) as u32 * self.block_height;
+        Rect {
+            x,
+            y,
+            width: self.block_width.min(self.width - x),
+            height: self.block_height.min(self.height - y),
+        }
+    }
+
+    /// Number of rows of pixels in block `index`, including any padding.
+    fn block_rows(&self, index: usize) -> u32 {
+        if self.tiled {
+            self.block_height
+        } else {
+            self.block_rect(index).height
+        }
+    }
+
+    /// Dimensions of the stored data of block `index` after decompression. Tiles are always
+    /// full-sized, padding the right and bottom edges of the image, while the last strip may be
+    /// cut short.
+    fn block_dimensions(&self, index: usize) -> Result<BlockDimensions> {
+        let height = self.block_rows(index);
+        if let Some(ycbcr) = &self.ycbcr {
+            // Subsampled data is stored in rows of data units
+            let (across, down) = ycbcr.units(self.block_width, height);
+            return Ok(BlockDimensions {
+                width: self.block_width,
+                height: down as u32,
+                row_bytes: across
+                    .checked_mul(ycbcr.unit_samples() * self.sample_size())
+                    .ok_or(DecodingError::ImageTooLarge)?,
+            });
+        }
+        let row_bits = (self.block_width as usize)
+            .checked_mul(self.plane_samples())
+            .and_then(|samples| samples.checked_mul(self.bits_per_sample.into()))
+            .ok_or(DecodingError::ImageTooLarge)?;
+        Ok(BlockDimensions {
+            width: self.block_width,
+            height,
+            row_bytes: row_bits.div_ceil(8),
+        })
+    }
+
+    /// Read and decode block `index`, returning its samples (including any padding) as
+    /// native-endian bytes.
+    fn read_block<R: Read + Seek>(&self, reader: &mut R, index: usize) -> Result<Vec<u8>> {
+        if index >= self.block_count() {
+            return Err(DecodingError::MissingBlock { index }.into());
+        }
+        let block = self.block_dimensions(index)?;
+        let mut compressed = match &self.old_jpeg {
+            Some(old_jpeg) if old_jpeg.is_single_stream() => {
+                old_jpeg.read_single_stream(reader, &self.offsets, &self.byte_counts)?
+            }
+            Some(old_jpeg) => {
+                let data = self.read_block_data(reader, index)?;
+                let plane = index / self.blocks_per_plane();
+                old_jpeg.read_block_stream(reader, &data, &block, plane)?
+            }
+            None => self.read_block_data(reader, index)?,
+        };
+        if self.reversed_bits {
+            reverse_bits(&mut compressed);
+        }
+
+        let mut data = self
+            .codec
+            .decompress(&compressed, &block)
+            .map_err(|source| Error::Block {
+                index,
+                offset: self.offsets.get(index).copied().unwrap_or_default(),
+                source: Box::new(source),
+            })?;
+        let byte_count = block.byte_count()?;
+        if data.len() < byte_count {
+            return Err(DecodingError::TruncatedBlock { index }.into());
+        }
+        data.truncate(byte_count);
+
+        if self.sample_type.is_packed(self.bits_per_sample) {
+            let samples_per_row = block.width as usize * self.plane_samples();
+            return Ok(unpack_samples(&data, self.bits_per_sample, samples_per_row)?.to_ne_bytes());
+        }
+        swap_byte_order(&mut data, self.sample_size(), self.little_endian);
+        if let Some(ycbcr) = &self.ycbcr {
+            let units = PixelBuffer::from_ne_bytes(self.sample_type, &data);
+            let pixels = ycbcr.unpack_buffer(&units, self.block_width, self.block_rows(index))?;
+            return Ok(pixels.to_ne_bytes());
+        }
+        Ok(data)
+    }
+
+    /// Read the compressed data of block `index` from `reader`.
+    fn read_block_data<R: Read + Seek>(&self, reader: &mut R, index: usize) -> Result<Vec<u8>> {
+        let missing = DecodingError::MissingBlock { index };
+        let offset = *self.offsets.get(index).ok_or(missing)?;
+        let byte_count = *self.byte_counts.get(index).ok_or(missing)?;
+
+        reader.seek(SeekFrom::Start(offset))?;
+        let mut data = Vec::new();
+        reader.by_ref().take(byte_count).read_to_end(&mut data)?;
+        if (data.len() as u64) < byte_count {
+            return Err(DecodingError::TruncatedBlock { index }.into());
+        }
+        Ok(data)
+    }
+
+    /// Copy the pixels of decoded block `index` which fall within `region` into `output`, a
+    /// buffer holding the pixels of `region`.
+    fn copy_block(
+        &self,
+        data: &[u8],
+        index: usize,
+        region: &Rect,
+        output: &mut [u8],
+    ) -> Result<()> {
+        let block = self.block_rect(index);
+        let overlap = match block.intersection(region) {
+            Some(overlap) => overlap,
+            None => return Ok(()),
+        };
+
+        let pixel_size = self.pixel_size();
+        let row_bytes = |width: u32| {
+            (width as usize)
+                .checked_mul(pixel_size)
+                .ok_or(DecodingError::ImageTooLarge)
+        };
+        let block_row_bytes = row_bytes(self.block_width)?;
+        let region_row_bytes = row_bytes(region.width)?;
+        let span = overlap.width as usize * pixel_size;
+        for y in overlap.y..overlap.y + overlap.height {
+            let source = (y - block.y) as usize * block_row_bytes
+                + (overlap.x - block.x) as usize * pixel_size;
+            let destination = (y - region.y) as usize * region_row_bytes
+                + (overlap.x - region.x) as usize * pixel_size;
+            output[destination..destination + span].copy_from_slice(&data[source..source + span]);
+        }
+        Ok(())
+    }
+
+    /// Indices of the blocks overlapping `region`.
+    fn blocks_overlapping(&self, region: &Rect) -> Vec<usize> {
+        let image = Rect {
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+        };
+        let region = match image.intersection(region) {
+            Some(region) => region,
+            None => return Vec::new(),
+        };
+
+        let columns =
+            region.x / self.block_width..=(region.x + region.width - 1) / self.block_width;
+        let rows =
+            region.y / self.block_height..=(region.y + region.height - 1) / self.block_height;
+        let across = self.blocks_across() as usize;
+        rows.flat_map(|row| {
+            columns
+                .clone()
+                .map(move |column| row as usize * across + column as usize)
+        })
+        .collect()
+    }
+
+    /// Decode the blocks overlapping `region`, copying their pixels into `output`, a buffer
+    /// of native-endian samples holding the pixels of `region` in the layout of the file.
+    /// Regions without any pixels are left empty.
+    fn decode_rect_into<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        region: &Rect,
+        output: &mut [u8],
+    ) -> Result<()> {
+        if region.width == 0 || region.height == 0 {
+            return Ok(());
+        }
+        let plane_size = output.len() / self.planes();
+        let blocks = self.blocks_overlapping(region);
+        for (plane, output) in output.chunks_exact_mut(plane_size).enumerate() {
+            for index in &blocks {
+                let index = plane * self.blocks_per_plane() + index;
+                let block = self.read_block(reader, index)?;
+                self.copy_block(&block, index, region, output)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rearrange the samples of `data` from the layout of the file into the output layout.
+    fn to_output_layout(&self, data: &mut [u8]) {
+        if !self.planar && self.planar_output {
+            deinterleave(data, self.samples_per_pixel.into(), self.sample_size());
+        } else if self.planar && !self.planar_output {
+            interleave(data, self.samples_per_pixel.into(), self.sample_size());
+        }
+    }
+
+    /// Rearrange the samples of `data` from the output layout into the layout of the file.
+    fn to_file_layout(&self, data: &mut [u8]) {
+        if !self.planar && self.planar_output {
+            interleave(data, self.samples_per_pixel.into(), self.sample_size());
+        } else if self.planar && !self.planar_output {
+            deinterleave(data, self.samples_per_pixel.into(), self.sample_size());
+        }
+    }
+
+    /// `PLANAR_CONFIGURATION` of decoded images.
+    fn output_planar_configuration(&self) -> u16 {
+        if self.planar_output {
+            planar_configuration::PLANAR
+        } else {
+            planar_configuration::CHUNKY
+        }
+    }
+
+    /// Number of samples in `region`.
+    fn region_samples(&self, region: &Rect) -> Result<usize> {
+        Ok((region.width as usize)
+            .checked_mul(region.height as usize)
+            .and_then(|pixels| pixels.checked_mul(self.samples_per_pixel.into()))
+            .ok_or(DecodingError::ImageTooLarge)?)
+    }
+
+    /// Decode the blocks overlapping `region` into an image of just that region.
+    fn decode_rect<R: Read + Seek>(&self, reader: &mut R, region: Rect) -> Result<Image> {
+        let size = self
+            .region_samples(&region)?
+            .checked_mul(self.sample_size())
+            .ok_or(DecodingError::ImageTooLarge)?;
+        let mut data = vec![0; size];
+        self.decode_rect_into(reader, &region, &mut data)?;
+        self.to_output_layout(&mut data);
+
+        Ok(Image {
+            width: region.width,
+            height: region.height,
+            samples_per_pixel: self.samples_per_pixel,
+            photometric: self.photometric,
+            planar_configuration: self.output_planar_configuration(),
+            data: PixelBuffer::from_ne_bytes(self.sample_type, &data),
+        })
+    }
+
+    /// Decode the `width` by `height` pixel region with its top left corner at (`x`, `y`),
+    /// reading only the strips or tiles which overlap it. Parts of the region lying outside of
+    /// the image are left zeroed.
+    pub fn read_region<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<Image> {
+        self.decode_rect(
+            reader,
+            Rect {
+                x,
+                y,
+                width,
+                height,
+            },
+        )
+    }
+
+    /// Like `read_region`, but copies the pixels into `buffer`, which must hold exactly
+    /// `width * height * samples_per_pixel` samples of this decoder's sample type, laid out like
+    /// decoded images. Parts of the region lying outside of the image are left untouched.
+    pub fn read_region_into<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        buffer: &mut PixelBuffer,
+    ) -> Result<()> {
+        let region = Rect {
+            x,
+            y,
+            width,
+            height,
+        };
+        if buffer.sample_type() != self.sample_type
+            || buffer.len() != self.region_samples(&region)?
+        {
+            return Err(DecodingError::BufferMismatch.into());
+        }
+
+        let mut data = buffer.to_ne_bytes();
+        self.to_file_layout(&mut data);
+        self.decode_rect_into(reader, &region, &mut data)?;
+        self.to_output_layout(&mut data);
+        *buffer = PixelBuffer::from_ne_bytes(self.sample_type, &data);
+        Ok(())
+    }
+
+    /// Decode the entire image.
+    pub fn decode<R: Read + Seek>(&self, reader: &mut R) -> Result<Image> {
+        self.decode_rect(
+            reader,
+            Rect {
+                x: 0,
+                y: 0,
+                width: self.width,
+                height: self.height,
+            },
+        )
+    }
+
+    /// Decode strip `index`, gathering the strip from every plane of planar images. Fails if the
+    /// image is tiled.
+    pub fn read_strip<R: Read + Seek>(&self, reader: &mut R, index: usize) -> Result<Image> {
+        if self.tiled {
+            return Err(DecodingError::NotStriped.into());
+        }
+        if index >= self.blocks_per_plane() {
+            return Err(DecodingError::MissingBlock { index }.into());
+        }
+        self.decode_rect(reader, self.block_rect(index))
+    }
+
+    /// Decode the tile at (`column`, `row`) in the grid of tiles, gathering the tile from every
+    /// plane of planar images and cropping away any padding beyond the right or bottom edge of
+    /// the image. Fails if the image is not tiled.
+    pub fn read_tile<R: Read + Seek>(
+        &self,
+        reader: &mut R,
+        column: u32,
+        row: u32,
+    ) -> Result<Image> {
+        if !self.tiled {
+            return Err(DecodingError::NotTiled.into());
+        }
+        let index = row as usize * self.blocks_across() as usize + column as usize;
+        if column >= self.blocks_across() || row >= self.blocks_down() {
+            return Err(DecodingError::MissingBlock { index }.into());
+        }
+        self.decode_rect(reader, self.block_rect(index))
+    }
+}
+
+/// Read the width or height in `tag`, failing with `ImageTooLarge` if it does not fit in 32 bits.
+fn dimension(ifd: &IFD, tag: u16) -> Result<u32> {
+    match ifd.unsigned(tag) {
+        Err(FieldExtractionError::WrongDataType { .. }) if ifd.unsigned::<u64>(tag).is_ok() => {
+            Err(DecodingError::ImageTooLarge.into())
+        }
+        result => Ok(result?),
+    }
+}
@@ -0,0 +1,483 @@
+use crate::baseline::{
+    constants::{
+        compression, extra_samples, fill_order, photometic_interpretation, planar_configuration,
+        predictor, resolution_unit, sample_format,
+    },
+    tags,
+};
+use crate::compression::{codec_for, BlockDimensions, Codec, Deflate, Predictor, Uncompressed};
+use crate::errors::{EncodingError, Result};
+use crate::image::{
+    color_samples, pack_samples, packed_row_bytes, reverse_bits, swap_byte_order, Image,
+    PixelBuffer, SampleType, YCbCr,
+};
+use crate::lowlevel::{IFDField, MetadataWriter, IFD};
+use byteorder::{ByteOrder, WriteBytesExt};
+use std::io::{Seek, Write};
+
+/// Uncompressed strip size aimed for by default, as recommended by the TIFF specification.
+pub const DEFAULT_STRIP_BYTES: usize = 8 * 1024;
+
+/// Tiles must measure a multiple of this many pixels in each direction.
+pub const TILE_SIZE_MULTIPLE: u32 = 16;
+
+/// Encodes images into compressed strips or tiles, describing them with the baseline tags.
+pub struct ImageEncoder {
+    /// Value of the `COMPRESSION` tag.
+    compression: u16,
+    /// Compresses each strip or tile.
+    codec: Box<dyn Codec>,
+    /// Value of the `PREDICTOR` tag.
+    predictor: u16,
+    /// Uncompressed size in bytes each strip should not exceed, unless a single row is larger.
+    strip_bytes: usize,
+    /// Width and height of each tile, if the image is to be cut into tiles rather than strips.
+    tile_size: Option<(u32, u32)>,
+    /// Bits to pack each sample into, if not the full size of the samples' type.
+    bits_per_sample: Option<u16>,
+    /// Value of the `FILL_ORDER` tag.
+    fill_order: u16,
+    /// Subsampling and coefficients of YCbCr images.
+    ycbcr: Option<YCbCr>,
+}
+
+/// How the samples of an image are laid out within each strip or tile.
+struct Layout {
+    sample_type: SampleType,
+    bits_per_sample: u16,
+    /// Samples of each pixel within one plane.
+    plane_samples: usize,
+    /// Whether samples are packed into fewer bits than their type holds.
+    packed: bool,
+    /// Subsampling of YCbCr images, if chroma is subsampled.
+    ycbcr: Option<YCbCr>,
+    predictor: Predictor,
+}
+
+impl Default for ImageEncoder {
+    fn default() -> Self {
+        Self {
+            compression: compression::UNCOMPRESSED,
+            codec: Box::new(Uncompressed),
+            predictor: predictor::NONE,
+            strip_bytes: DEFAULT_STRIP_BYTES,
+            tile_size: None,
+            bits_per_sample: None,
+            fill_order: fill_order::MSB_TO_LSB,
+            ycbcr: None,
+        }
+    }
+}
+
+impl ImageEncoder {
+    /// Create an encoder writing uncompressed strips of about `DEFAULT_STRIP_BYTES` each.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Compress strips or tiles with the codec for a value of the `COMPRESSION` tag.
+    pub fn with_compression(self, compression: u16) -> Result<Self> {
+        let codec = codec_for(compression)?;
+        Ok(self.with_codec(compression, codec))
+    }
+
+    /// Compress strips or tiles with `codec`, recording `compression` as the `COMPRESSION` tag.
+    /// Useful for codecs with settings of their own, such as `Deflate::with_level`.
+    pub fn with_codec(mut self, compression: u16, codec: Box<dyn Codec>) -> Self {
+        self.compression = compression;
+        self.codec = codec;
+        self
+    }
+
+    /// Compress strips or tiles with Deflate at `level`, from 0 (store) to 9 (smallest). Keeps a
+    /// `COMPRESSION` of `DEFLATE_PKZIP` if already chosen, and uses `ADOBE_DEFLATE` otherwise.
+    pub fn with_deflate_level(self, level: u32) -> Self {
+        let compression = match self.compression {
+            compression::DEFLATE_PKZIP => compression::DEFLATE_PKZIP,
+            _ => compression::ADOBE_DEFLATE,
+        };
+        self.with_codec(compression, Box::new(Deflate::with_level(level)))
+    }
+
+    /// Apply `predictor` to each strip or tile before compressing it.
+    pub fn with_predictor(mut self, predictor: u16) -> Self {
+        self.predictor = predictor;
+        self
+    }
+
+    /// Aim for strips of `strip_bytes` bytes before compression.
+    pub fn with_strip_bytes(mut self, strip_bytes: usize) -> Self {
+        self.strip_bytes = strip_bytes;
+        self
+    }
+
+    /// Cut images into tiles of `width` by `height` pixels instead of strips. Both must be
+    /// non-zero multiples of `TILE_SIZE_MULTIPLE`. Tiles on the right and bottom edges are padded
+    /// with zeroed pixels.
+    pub fn with_tiles(mut self, width: u32, height: u32) -> Result<Self> {
+        if width == 0
+            || height == 0
+            || !width.is_multiple_of(TILE_SIZE_MULTIPLE)
+            || !height.is_multiple_of(TILE_SIZE_MULTIPLE)
+        {
+            return Err(EncodingError::InvalidTileSize { width, height }.into());
+        }
+        self.tile_size = Some((width, height));
+        Ok(self)
+    }
+
+    /// Pack unsigned integer samples into `bits_per_sample` bits each, which must be no more than
+    /// the size of their type. Higher bits of samples are discarded.
+    pub fn with_bits_per_sample(mut self, bits_per_sample: u16) -> Self {
+        self.bits_per_sample = Some(bits_per_sample);
+        self
+    }
+
+    /// Store the bits within each byte in `fill_order`.
+    pub fn with_fill_order(mut self, fill_order: u16) -> Self {
+        self.fill_order = fill_order;
+        self
+    }
+
+    /// Subsample and describe images with a `PHOTOMETRIC_INTERPRETATION` of YCbCr using
+    /// `ycbcr`. Such images hold full resolution u8, u16 or u32 samples, as produced by
+    /// `YCbCr::from_rgb_image`.
+    pub fn with_ycbcr(mut self, ycbcr: YCbCr) -> Self {
+        self.ycbcr = Some(ycbcr);
+        self
+    }
+
+    /// The `ROWS_PER_STRIP` chosen for `image`: as many rows as fit in the target strip size,
+    /// but at least one. Subsampled YCbCr strips are rounded up to whole rows of data units.
+    pub fn rows_per_strip(&self, image: &Image) -> u32 {
+        let row_bytes = match self.bits_per_sample {
+            Some(bits_per_sample) => packed_row_bytes(
+                image.width as usize * usize::from(image.samples_per_pixel) / planes(image),
+                bits_per_sample,
+            ),
+            None => row_bytes(image),
+        }
+        .max(1);
+        let mut rows = (self.strip_bytes / row_bytes).max(1);
+        if let Some(ycbcr) = self.subsampling(image) {
+            let vertical = usize::from(ycbcr.subsampling.1.max(1));
+            rows = rows.div_ceil(vertical) * vertical;
+        }
+        rows.min(image.height.max(1) as usize) as u32
+    }
+
+    /// The YCbCr subsampling applied to `image`, if it is YCbCr and its chroma is subsampled.
+    fn subsampling(&self, image: &Image) -> Option<YCbCr> {
+        self.ycbcr
+            .filter(|_| image.photometric == photometic_interpretation::YCBCR)
+            .filter(YCbCr::is_subsampled)
+    }
+
+    /// Width and height of each strip or tile of `image`. Strips span the entire image width.
+    fn block_size(&self, image: &Image) -> (u32, u32) {
+        self.tile_size
+            .unwrap_or_else(|| (image.width, self.rows_per_strip(image)))
+    }
+
+    /// Write the strips or tiles of `image` to `writer` at its current position in the byte
+    /// order `E`. Planar images are written with a separate set of strips or tiles for each
+    /// sample. Returns an IFD holding every baseline tag needed to read the image back, to which
+    /// further tags may be added before writing it with `MetadataWriter::write_ifd`.
+    pub fn encode<E: ByteOrder, W: Write + Seek>(
+        &self,
+        image: &Image,
+        writer: &mut W,
+    ) -> Result<IFD> {
+        let samples = (image.width as usize)
+            .checked_mul(image.height as usize)
+            .and_then(|pixels| pixels.checked_mul(image.samples_per_pixel.into()))
+            .ok_or(EncodingError::ImageTooLarge)?;
+        if image.data.len() != samples {
+            return Err(EncodingError::BufferMismatch.into());
+        }
+
+        let sample_type = image.data.sample_type();
+        let bits_per_sample = self
+            .bits_per_sample
+            .unwrap_or(sample_type.byte_size() as u16 * 8);
+        let packed = sample_type.is_packed(bits_per_sample);
+        if packed
+            && (bits_per_sample == 0
+                || usize::from(bits_per_sample) > sample_type.byte_size() * 8
+                || sample_type.sample_format() != sample_format::UNSIGNED_INTEGER)
+        {
+            return Err(EncodingError::UnsupportedBitDepth { bits_per_sample }.into());
+        }
+
+        let ycbcr = self.subsampling(image);
+        if ycbcr.is_some()
+            && (image.is_planar()
+                || image.samples_per_pixel != 3
+                || packed
+                || !matches!(
+                    sample_type,
+                    SampleType::U8 | SampleType::U16 | SampleType::U32
+                ))
+        {
+            return Err(EncodingError::UnsupportedSubsampledLayout.into());
+        }
+
+        // Packed or subsampled samples are left in native order until each block is rearranged
+        let little_endian = E::read_u16(&[1, 0]) == 1;
+        let mut data = image.data.to_ne_bytes();
+        if !packed && ycbcr.is_none() {
+            swap_byte_order(&mut data, sample_type.byte_size(), little_endian);
+        }
+
+        let planes = planes(image);
+        let layout = Layout {
+            sample_type,
+            bits_per_sample,
+            plane_samples: usize::from(image.samples_per_pixel) / planes,
+            packed,
+            ycbcr,
+            predictor: Predictor {
+                predictor: self.predictor,
+                samples_per_pixel: image.samples_per_pixel / planes as u16,
+                bits_per_sample,
+                little_endian,
+            },
+        };
+
+        let (block_width, block_height) = self.block_size(image);
+        let pixel_size = layout.plane_samples * sample_type.byte_size();
+        let mut offsets = Vec::new();
+        let mut byte_counts = Vec::new();
+        // Planar images are written as a complete set of strips or tiles for each sample in turn
+        for plane in data.chunks((data.len() / planes).max(1)) {
+            for y in (0..image.height).step_by(block_height as usize) {
+                for x in (0..image.width).step_by(block_width.max(1) as usize) {
+                    // Tiles are always full-sized, while the last strip is cut short
+                    let height = if self.tile_size.is_some() {
+                        block_height
+                    } else {
+                        block_height.min(image.height - y)
+                    };
+                    let block = BlockDimensions {
+                        width: block_width,
+                        height,
+                        row_bytes: (block_width as usize)
+                            .checked_mul(pixel_size)
+                            .ok_or(EncodingError::ImageTooLarge)?,
+                    };
+                    let mut pixels = copy_block(image, plane, x, y, &block)?;
+                    // Zeroed padding would bleed into the chroma of pixels on the edge
+                    if layout.ycbcr.is_some() {
+                        extend_edges(image, x, y, &block, &mut pixels);
+                    }
+                    let compressed = self.encode_block(&layout, pixels, block)?;
+
+                    offsets.push(writer.stream_position()?);
+                    byte_counts.push(compressed.len() as u64);
+                    writer.write_all(&compressed)?;
+                }
+            }
+        }
+
+        let mut ifd = IFD::new();
+        if let (Some(ycbcr), photometic_interpretation::YCBCR) = (self.ycbcr, image.photometric) {
+            ycbcr.write_to_ifd(&mut ifd);
+        }
+        let entries = &mut ifd.entries;
+        let spp = usize::from(image.samples_per_pixel);
+        entries.insert(tags::IMAGE_WIDTH, IFDField::Long(Box::new([image.width])));
+        entries.insert(tags::IMAGE_LENGTH, IFDField::Long(Box::new([image.height])));
+        entries.insert(
+            tags::BITS_PER_SAMPLE,
+            IFDField::Short(vec![bits_per_sample; spp].into_boxed_slice()),
+        );
+        entries.insert(
+            tags::COMPRESSION,
+            IFDField::Short(Box::new([self.compression])),
+        );
+        entries.insert(
+            tags::PHOTOMETRIC_INTERPRETATION,
+            IFDField::Short(Box::new([image.photometric])),
+        );
+        entries.insert(
+            tags::SAMPLES_PER_PIXEL,
+            IFDField::Short(Box::new([image.samples_per_pixel])),
+        );
+        if self.tile_size.is_some() {
+            entries.insert(tags::TILE_WIDTH, IFDField::Long(Box::new([block_width])));
+            entries.insert(tags::TILE_LENGTH, IFDField::Long(Box::new([block_height])));
+            entries.insert(tags::TILE_OFFSETS, IFDField::offsets(offsets));
+            entries.insert(tags::TILE_BYTE_COUNTS, IFDField::offsets(byte_counts));
+        } else {
+            entries.insert(tags::STRIP_OFFSETS, IFDField::offsets(offsets));
+            entries.insert(
+                tags::ROWS_PER_STRIP,
+                IFDField::Long(Box::new([block_height])),
+            );
+            entries.insert(tags::STRIP_BYTE_COUNTS, IFDField::offsets(byte_counts));
+        }
+        entries.insert(tags::X_RESOLUTION, IFDField::Rational(Box::new([(72, 1)])));
+        entries.insert(tags::Y_RESOLUTION, IFDField::Rational(Box::new([(72, 1)])));
+        entries.insert(
+            tags::RESOLUTION_UNIT,
+            IFDField::Short(Box::new([resolution_unit::INCH])),
+        );
+        entries.insert(
+            tags::PLANAR_CONFIGURATION,
+            IFDField::Short(Box::new([if image.is_planar() {
+                planar_configuration::PLANAR
+            } else {
+                planar_configuration::CHUNKY
+            }])),
+        );
+
+        let color_samples = color_samples(image.photometric);
+        if image.samples_per_pixel > color_samples {
+            let extra = usize::from(image.samples_per_pixel - color_samples);
+            entries.insert(
+                tags::EXTRA_SAMPLES,
+                IFDField::Short(vec![extra_samples::UNSPECIFIED; extra].into_boxed_slice()),
+            );
+        }
+        if self.fill_order != fill_order::MSB_TO_LSB {
+            entries.insert(
+                tags::FILL_ORDER,
+                IFDField::Short(Box::new([self.fill_order])),
+            );
+        }
+        if self.predictor != predictor::NONE {
+            entries.insert(tags::PREDICTOR, IFDField::Short(Box::new([self.predictor])));
+        }
+        if sample_type.sample_format() != sample_format::UNSIGNED_INTEGER {
+            entries.insert(
+                tags::SAMPLE_FORMAT,
+                IFDField::Short(vec![sample_type.sample_format(); spp].into_boxed_slice()),
+            );
+        }
+
+        Ok(ifd)
+    }
+
+    /// Pack or subsample, predict and compress the `pixels` of a single strip or tile shaped like
+    /// `block`.
+    fn encode_block(
+        &self,
+        layout: &Layout,
+        mut pixels: Vec<u8>,
+        block: BlockDimensions,
+    ) -> Result<Vec<u8>> {
+        let block = if layout.packed {
+            let samples_per_row = block.width as usize * layout.plane_samples;
+            pixels = pack_samples(
+                &PixelBuffer::from_ne_bytes(layout.sample_type, &pixels),
+                layout.bits_per_sample,
+                samples_per_row,
+            )?;
+            BlockDimensions {
+                row_bytes: packed_row_bytes(samples_per_row, layout.bits_per_sample),
+                ..block
+            }
+        } else if let Some(ycbcr) = &layout.ycbcr {
+            let units = ycbcr.pack_buffer(
+                &PixelBuffer::from_ne_bytes(layout.sample_type, &pixels),
+                block.width,
+                block.height,
+            )?;
+            pixels = units.to_ne_bytes();
+            let sample_size = layout.sample_type.byte_size();
+            swap_byte_order(&mut pixels, sample_size, layout.predictor.little_endian);
+            let (across, down) = ycbcr.units(block.width, block.height);
+            BlockDimensions {
+                width: block.width,
+                height: down as u32,
+                row_bytes: across * ycbcr.unit_samples() * sample_size,
+            }
+        } else {
+            block
+        };
+
+        if self.predictor != predictor::NONE {
+            layout.predictor.encode(&mut pixels, &block)?;
+        }
+        let mut compressed = self.codec.compress(&pixels, &block)?;
+        if self.fill_order == fill_order::LSB_TO_MSB {
+            reverse_bits(&mut compressed);
+        }
+        Ok(compressed)
+    }
+
+    /// Write the strips or tiles of `image` followed by the IFD describing them, returning the
+    /// position of the IFD within the file.
+    pub fn write_image<E: ByteOrder, W: WriteBytesExt + Seek>(
+        &self,
+        image: &Image,
+        metadata_writer: &mut MetadataWriter<E>,
+        writer: &mut W,
+    ) -> Result<u64> {
+        let ifd = self.encode::<E, W>(image, writer)?;
+        metadata_writer.write_ifd(&ifd, writer)
+    }
+}
+
+/// Copy the pixels of the block shaped like `block` with its top left corner at (`x`, `y`) out of
+/// `data`, the samples of `image` (or of one of its planes) as bytes. Parts of the block beyond
+/// the image are left zeroed.
+fn copy_block(
+    image: &Image,
+    data: &[u8],
+    x: u32,
+    y: u32,
+    block: &BlockDimensions,
+) -> Result<Vec<u8>> {
+    let byte_count = block
+        .byte_count()
+        .map_err(|_| EncodingError::ImageTooLarge)?;
+    let mut pixels = vec![0; byte_count];
+    let image_row_bytes = row_bytes(image);
+    let pixel_size = image_row_bytes / image.width as usize;
+    let span = block.width.min(image.width - x) as usize * pixel_size;
+    let rows = block.height.min(image.height - y) as usize;
+    for row in 0..rows {
+        let source = (y as usize + row) * image_row_bytes + x as usize * pixel_size;
+        let destination = row * block.row_bytes;
+        pixels[destination..destination + span].copy_from_slice(&data[source..source + span]);
+    }
+    Ok(pixels)
+}
+
+/// Fill the parts of `pixels`, copied by `copy_block`, which lie beyond the right and bottom
+/// edges of `image` with the nearest pixel within it.
+fn extend_edges(image: &Image, x: u32, y: u32, block: &BlockDimensions, pixels: &mut [u8]) {
+    let pixel_size = row_bytes(image) / image.width as usize;
+    let span = block.width.min(image.width - x) as usize * pixel_size;
+    let rows = block.height.min(image.height - y) as usize;
+    if span == 0 || rows == 0 {
+        return;
+    }
+    for row in pixels.chunks_mut(block.row_bytes).take(rows) {
+        let (inside, outside) = row.split_at_mut(span);
+        for pixel in outside.chunks_mut(pixel_size) {
+            pixel.copy_from_slice(&inside[span - pixel_size..]);
+        }
+    }
+    let last_row = (rows - 1) * block.row_bytes;
+    let (inside, outside) = pixels.split_at_mut(rows * block.row_bytes);
+    for row in outside.chunks_mut(block.row_bytes) {
+        row.copy_from_slice(&inside[last_row..]);
+    }
+}
+
+/// Number of planes the samples of `image` are stored in.
+fn planes(image: &Image) -> usize {
+    if image.is_planar() {
+        usize::from(image.samples_per_pixel).max(1)
+    } else {
+        1
+    }
+}
+
+/// Size in bytes of a single row of `image`, or of one of its planes.
+fn row_bytes(image: &Image) -> usize {
+    image.width as usize * usize::from(image.samples_per_pixel) / planes(image)
+        * image.data.sample_type().byte_size()
+}
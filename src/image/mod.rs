@@ -0,0 +1,313 @@
+use crate::baseline::constants::{photometic_interpretation, planar_configuration, sample_format};
+use crate::errors::{DecodingError, FieldExtractionError, Result};
+
+/// Decoding of pixel data
+pub(crate) mod decoder;
+pub use decoder::*;
+
+/// Packing of samples of arbitrary bit depths
+pub(crate) mod bits;
+pub use bits::*;
+
+/// Conversion of photometric interpretations to RGB
+pub(crate) mod color;
+pub use color::*;
+
+/// YCbCr subsampling and conversion
+pub(crate) mod ycbcr;
+pub use ycbcr::*;
+
+/// Encoding of pixel data
+pub(crate) mod encoder;
+pub use encoder::*;
+
+/// The in-memory type of a single sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleType {
+    U8,
+    U16,
+    U32,
+    U64,
+    I8,
+    I16,
+    I32,
+    I64,
+    F32,
+    F64,
+}
+
+impl SampleType {
+    /// Find the sample type for a `SAMPLE_FORMAT` and `BITS_PER_SAMPLE` combination. Unsigned
+    /// samples of other depths up to 32 bits are held in the smallest type which fits them.
+    pub fn from_format(format: u16, bits_per_sample: u16) -> Result<Self> {
+        Ok(match (format, bits_per_sample) {
+            (sample_format::UNSIGNED_INTEGER, 1..=8) => SampleType::U8,
+            (sample_format::UNSIGNED_INTEGER, 9..=16) => SampleType::U16,
+            (sample_format::UNSIGNED_INTEGER, 17..=32) => SampleType::U32,
+            (sample_format::UNSIGNED_INTEGER, 64) => SampleType::U64,
+            (sample_format::SIGNED_INTEGER, 8) => SampleType::I8,
+            (sample_format::SIGNED_INTEGER, 16) => SampleType::I16,
+            (sample_format::SIGNED_INTEGER, 32) => SampleType::I32,
+            (sample_format::SIGNED_INTEGER, 64) => SampleType::I64,
+            (sample_format::IEEE_FLOATING_POINT, 32) => SampleType::F32,
+            (sample_format::IEEE_FLOATING_POINT, 64) => SampleType::F64,
+            _ => {
+                return Err(DecodingError::UnsupportedSampleFormat {
+                    sample_format: format,
+                    bits_per_sample,
+                }
+                .into())
+            }
+        })
+    }
+
+    /// Returns true if samples of `bits_per_sample` bits are packed into bits rather than being
+    /// stored as whole samples of this type.
+    pub fn is_packed(self, bits_per_sample: u16) -> bool {
+        usize::from(bits_per_sample) != self.byte_size() * 8
+    }
+
+    /// Size in bytes of a single sample.
+    pub fn byte_size(self) -> usize {
+        match self {
+            SampleType::U8 | SampleType::I8 => 1,
+            SampleType::U16 | SampleType::I16 => 2,
+            SampleType::U32 | SampleType::I32 | SampleType::F32 => 4,
+            SampleType::U64 | SampleType::I64 | SampleType::F64 => 8,
+        }
+    }
+
+    /// Value of the `SAMPLE_FORMAT` tag for this type.
+    pub fn sample_format(self) -> u16 {
+        match self {
+            SampleType::U8 | SampleType::U16 | SampleType::U32 | SampleType::U64 => {
+                sample_format::UNSIGNED_INTEGER
+            }
+            SampleType::I8 | SampleType::I16 | SampleType::I32 | SampleType::I64 => {
+                sample_format::SIGNED_INTEGER
+            }
+            SampleType::F32 | SampleType::F64 => sample_format::IEEE_FLOATING_POINT,
+        }
+    }
+}
+
+/// Typed samples of an image.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PixelBuffer {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    U32(Vec<u32>),
+    U64(Vec<u64>),
+    I8(Vec<i8>),
+    I16(Vec<i16>),
+    I32(Vec<i32>),
+    I64(Vec<i64>),
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+}
+
+/// Convert native-endian bytes into a typed vector.
+macro_rules! from_ne_bytes {
+    ($t:ty, $bytes:expr) => {
+        $bytes
+            .chunks_exact(std::mem::size_of::<$t>())
+            .map(|chunk| <$t>::from_ne_bytes(chunk.try_into().unwrap()))
+            .collect()
+    };
+}
+
+/// Convert a typed slice into native-endian bytes.
+macro_rules! to_ne_bytes {
+    ($samples:expr) => {
+        $samples
+            .iter()
+            .flat_map(|sample| sample.to_ne_bytes().to_vec())
+            .collect()
+    };
+}
+
+impl PixelBuffer {
+    /// Create a buffer of `sample_type` from samples stored as native-endian bytes.
+    pub fn from_ne_bytes(sample_type: SampleType, bytes: &[u8]) -> Self {
+        use std::convert::TryInto;
+        match sample_type {
+            SampleType::U8 => PixelBuffer::U8(bytes.to_vec()),
+            SampleType::U16 => PixelBuffer::U16(from_ne_bytes!(u16, bytes)),
+            SampleType::U32 => PixelBuffer::U32(from_ne_bytes!(u32, bytes)),
+            SampleType::U64 => PixelBuffer::U64(from_ne_bytes!(u64, bytes)),
+            SampleType::I8 => PixelBuffer::I8(from_ne_bytes!(i8, bytes)),
+            SampleType::I16 => PixelBuffer::I16(from_ne_bytes!(i16, bytes)),
+            SampleType::I32 => PixelBuffer::I32(from_ne_bytes!(i32, bytes)),
+            SampleType::I64 => PixelBuffer::I64(from_ne_bytes!(i64, bytes)),
+            SampleType::F32 => PixelBuffer::F32(from_ne_bytes!(f32, bytes)),
+            SampleType::F64 => PixelBuffer::F64(from_ne_bytes!(f64, bytes)),
+        }
+    }
+
+    /// Copy the samples of this buffer into native-endian bytes.
+    pub fn to_ne_bytes(&self) -> Vec<u8> {
+        match self {
+            PixelBuffer::U8(samples) => samples.clone(),
+            PixelBuffer::U16(samples) => to_ne_bytes!(samples),
+            PixelBuffer::U32(samples) => to_ne_bytes!(samples),
+            PixelBuffer::U64(samples) => to_ne_bytes!(samples),
+            PixelBuffer::I8(samples) => to_ne_bytes!(samples),
+            PixelBuffer::I16(samples) => to_ne_bytes!(samples),
+            PixelBuffer::I32(samples) => to_ne_bytes!(samples),
+            PixelBuffer::I64(samples) => to_ne_bytes!(samples),
+            PixelBuffer::F32(samples) => to_ne_bytes!(samples),
+            PixelBuffer::F64(samples) => to_ne_bytes!(samples),
+        }
+    }
+
+    /// The type of the samples in this buffer.
+    pub fn sample_type(&self) -> SampleType {
+        match self {
+            PixelBuffer::U8(_) => SampleType::U8,
+            PixelBuffer::U16(_) => SampleType::U16,
+            PixelBuffer::U32(_) => SampleType::U32,
+            PixelBuffer::U64(_) => SampleType::U64,
+            PixelBuffer::I8(_) => SampleType::I8,
+            PixelBuffer::I16(_) => SampleType::I16,
+            PixelBuffer::I32(_) => SampleType::I32,
+            PixelBuffer::I64(_) => SampleType::I64,
+            PixelBuffer::F32(_) => SampleType::F32,
+            PixelBuffer::F64(_) => SampleType::F64,
+        }
+    }
+
+    /// Number of samples in this buffer.
+    pub fn len(&self) -> usize {
+        match self {
+            PixelBuffer::U8(samples) => samples.len(),
+            PixelBuffer::U16(samples) => samples.len(),
+            PixelBuffer::U32(samples) => samples.len(),
+            PixelBuffer::U64(samples) => samples.len(),
+            PixelBuffer::I8(samples) => samples.len(),
+            PixelBuffer::I16(samples) => samples.len(),
+            PixelBuffer::I32(samples) => samples.len(),
+            PixelBuffer::I64(samples) => samples.len(),
+            PixelBuffer::F32(samples) => samples.len(),
+            PixelBuffer::F64(samples) => samples.len(),
+        }
+    }
+
+    /// Returns true if this buffer holds no samples.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A decoded image, with pixels in rows from top to bottom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// Number of samples (channels) in each pixel.
+    pub samples_per_pixel: u16,
+    /// `PHOTOMETRIC_INTERPRETATION` of the samples.
+    pub photometric: u16,
+    /// `PLANAR_CONFIGURATION` of the samples: `CHUNKY` if they are interleaved pixel by pixel,
+    /// or `PLANAR` if each sample forms a whole plane of its own, one after another.
+    pub planar_configuration: u16,
+    /// The samples themselves.
+    pub data: PixelBuffer,
+}
+
+impl Image {
+    /// Returns true if the samples are stored one plane after another.
+    pub fn is_planar(&self) -> bool {
+        self.planar_configuration == planar_configuration::PLANAR
+    }
+
+    /// A copy of this image with its samples stored one plane after another.
+    pub fn to_planar(&self) -> Image {
+        self.with_layout(planar_configuration::PLANAR)
+    }
+
+    /// A copy of this image with its samples interleaved pixel by pixel.
+    pub fn to_interleaved(&self) -> Image {
+        self.with_layout(planar_configuration::CHUNKY)
+    }
+
+    /// A copy of this image with its samples rearranged into `planar_configuration`.
+    fn with_layout(&self, planar_configuration: u16) -> Image {
+        let mut image = self.clone();
+        if self.is_planar() != (planar_configuration == planar_configuration::PLANAR) {
+            let sample_type = self.data.sample_type();
+            let mut data = self.data.to_ne_bytes();
+            let planes = self.samples_per_pixel.into();
+            if self.is_planar() {
+                interleave(&mut data, planes, sample_type.byte_size());
+            } else {
+                deinterleave(&mut data, planes, sample_type.byte_size());
+            }
+            image.data = PixelBuffer::from_ne_bytes(sample_type, &data);
+            image.planar_configuration = planar_configuration;
+        }
+        image
+    }
+}
+
+/// Rearrange `planes` planes of samples of `sample_size` bytes, stored one after another, into
+/// interleaved pixels.
+pub(crate) fn interleave(data: &mut [u8], planes: usize, sample_size: usize) {
+    if planes < 2 {
+        return;
+    }
+    let plane_size = data.len() / planes;
+    let planar = data.to_vec();
+    for (index, sample) in data.chunks_exact_mut(sample_size).enumerate() {
+        let source = (index % planes) * plane_size + (index / planes) * sample_size;
+        sample.copy_from_slice(&planar[source..source + sample_size]);
+    }
+}
+
+/// Rearrange interleaved pixels of `planes` samples of `sample_size` bytes into planes stored
+/// one after another.
+pub(crate) fn deinterleave(data: &mut [u8], planes: usize, sample_size: usize) {
+    if planes < 2 {
+        return;
+    }
+    let plane_size = data.len() / planes;
+    let interleaved = data.to_vec();
+    for (index, sample) in interleaved.chunks_exact(sample_size).enumerate() {
+        let destination = (index % planes) * plane_size + (index / planes) * sample_size;
+        data[destination..destination + sample_size].copy_from_slice(sample);
+    }
+}
+
+/// Swap samples of `sample_size` bytes between `little_endian` order and native order. The swap is
+/// its own inverse, so this converts in either direction.
+pub(crate) fn swap_byte_order(data: &mut [u8], sample_size: usize, little_endian: bool) {
+    if sample_size > 1 && little_endian != cfg!(target_endian = "little") {
+        data.chunks_exact_mut(sample_size)
+            .for_each(|sample| sample.reverse());
+    }
+}
+
+/// The value shared by every sample of a per-sample field read from `tag`, such as
+/// `BITS_PER_SAMPLE`.
+pub(crate) fn uniform_value<T: Copy + PartialEq>(tag: u16, values: &[T]) -> Result<T> {
+    let first = *values
+        .first()
+        .ok_or(FieldExtractionError::InsufficientData { tag })?;
+    if values.iter().any(|&value| value != first) {
+        return Err(DecodingError::NonUniformSamples { tag }.into());
+    }
+    Ok(first)
+}
+
+/// Number of samples making up the colour of a pixel under `photometric`. Any further samples
+/// are extra samples, such as alpha.
+pub(crate) fn color_samples(photometric: u16) -> u16 {
+    match photometric {
+        photometic_interpretation::RGB
+        | photometic_interpretation::YCBCR
+        | photometic_interpretation::CIELAB => 3,
+        photometic_interpretation::CMYK => 4,
+        _ => 1,
+    }
+}
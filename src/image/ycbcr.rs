@@ -0,0 +1,369 @@
+use crate::baseline::{
+    constants::{photometic_interpretation, planar_configuration, ycbcr_positioning},
+    tags,
+};
+use crate::errors::{ColorError, DecodingError, FieldExtractionError, Result};
+use crate::image::{Image, PixelBuffer};
+use crate::lowlevel::{IFDField, IFD};
+
+/// Denominator used when writing the rational YCbCr fields.
+const RATIONAL_SCALE: u32 = 10_000;
+
+/// How YCbCr samples are subsampled and converted to RGB, as described by the `Y_CBCR_*` and
+/// `REFERENCE_BLACK_WHITE` tags.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct YCbCr {
+    /// Proportions of red, green and blue making up luma (`Y_CBCR_COEFFICIENTS`).
+    pub coefficients: [f64; 3],
+    /// Sample values of black and white for Y, Cb and Cr in turn (`REFERENCE_BLACK_WHITE`).
+    pub reference_black_white: [f64; 6],
+    /// Width and height in luma samples of the area covered by one pair of chroma samples
+    /// (`Y_CBCR_SUB_SAMPLING`).
+    pub subsampling: (u16, u16),
+    /// Where chroma samples sit relative to the luma samples they cover (`Y_CBCR_POSITIONING`).
+    pub positioning: u16,
+}
+
+impl YCbCr {
+    /// The defaults of the TIFF specification for samples of `bits_per_sample` bits: ITU-R BT.601
+    /// coefficients, full range references, 2x2 subsampling and centered chroma.
+    pub fn new(bits_per_sample: u16) -> Self {
+        let max = 2f64.powi(bits_per_sample.into()) - 1.0;
+        let middle = 2f64.powi(i32::from(bits_per_sample) - 1);
+        Self {
+            coefficients: [0.299, 0.587, 0.114],
+            reference_black_white: [0.0, max, middle, max, middle, max],
+            subsampling: (2, 2),
+            positioning: ycbcr_positioning::CENTERED,
+        }
+    }
+
+    /// Read the YCbCr tags of `ifd`, applying the defaults for any which are absent.
+    pub fn from_ifd(ifd: &IFD, bits_per_sample: u16) -> Result<Self> {
+        let mut ycbcr = Self::new(bits_per_sample);
+        if let Some(coefficients) = rationals(ifd, tags::Y_CBCR_COEFFICIENTS, 3)? {
+            ycbcr.coefficients.copy_from_slice(&coefficients);
+        }
+        if let Some(references) = rationals(ifd, tags::REFERENCE_BLACK_WHITE, 6)? {
+            ycbcr.reference_black_white.copy_from_slice(&references);
+        }
+        match ifd.get::<Vec<u64>>(tags::Y_CBCR_SUB_SAMPLING) {
+            Err(FieldExtractionError::MissingTag { .. }) => {}
+            Ok(ref factors) if factors.len() >= 2 => {
+                ycbcr.subsampling = (factors[0] as u16, factors[1] as u16);
+            }
+            Ok(_) => {
+                return Err(FieldExtractionError::InsufficientData {
+                    tag: tags::Y_CBCR_SUB_SAMPLING,
+                }
+                .into())
+            }
+            Err(error) => return Err(error.into()),
+        }
+        ycbcr.positioning =
+            ifd.unsigned_or(tags::Y_CBCR_POSITIONING, ycbcr_positioning::CENTERED)?;
+
+        let (horizontal, vertical) = ycbcr.subsampling;
+        if !matches!(horizontal, 1 | 2 | 4)
+            || !matches!(vertical, 1 | 2 | 4)
+            || vertical > horizontal
+        {
+            return Err(DecodingError::InvalidSubsampling {
+                horizontal,
+                vertical,
+            }
+            .into());
+        }
+        Ok(ycbcr)
+    }
+
+    /// Store these settings in `ifd`.
+    pub fn write_to_ifd(&self, ifd: &mut IFD) {
+        let rational = |value: f64| {
+            (
+                (value * f64::from(RATIONAL_SCALE)).round() as u32,
+                RATIONAL_SCALE,
+            )
+        };
+        ifd.entries.insert(
+            tags::Y_CBCR_COEFFICIENTS,
+            IFDField::Rational(self.coefficients.iter().map(|&c| rational(c)).collect()),
+        );
+        ifd.entries.insert(
+            tags::Y_CBCR_SUB_SAMPLING,
+            IFDField::Short(Box::new([self.subsampling.0, self.subsampling.1])),
+        );
+        ifd.entries.insert(
+            tags::Y_CBCR_POSITIONING,
+            IFDField::Short(Box::new([self.positioning])),
+        );
+        ifd.entries.insert(
+            tags::REFERENCE_BLACK_WHITE,
+            IFDField::Rational(
+                self.reference_black_white
+                    .iter()
+                    .map(|&reference| rational(reference))
+                    .collect(),
+            ),
+        );
+    }
+
+    /// Returns true if chroma is stored at a lower resolution than luma.
+    pub fn is_subsampled(&self) -> bool {
+        self.subsampling != (1, 1)
+    }
+
+    /// Number of data units, each holding the luma samples of one subsampling block followed by
+    /// a Cb and a Cr sample, across and down an area of `width` by `height` pixels.
+    pub fn units(&self, width: u32, height: u32) -> (usize, usize) {
+        (
+            width.div_ceil(self.subsampling.0.into()) as usize,
+            height.div_ceil(self.subsampling.1.into()) as usize,
+        )
+    }
+
+    /// Number of samples in a single data unit.
+    pub fn unit_samples(&self) -> usize {
+        usize::from(self.subsampling.0) * usize::from(self.subsampling.1) + 2
+    }
+
+    /// Unpack the data units covering an area of `width` by `height` pixels into full resolution
+    /// interleaved Y, Cb, Cr pixels, interpolating chroma between the positions of its samples.
+    pub fn unpack(&self, data: &[u32], width: u32, height: u32) -> Vec<u32> {
+        let (h, v) = (
+            usize::from(self.subsampling.0),
+            usize::from(self.subsampling.1),
+        );
+        let (across, down) = self.units(width, height);
+        let unit_samples = self.unit_samples();
+        let unit = |column: usize, row: usize| {
+            let start = (row * across + column) * unit_samples;
+            data.get(start..start + unit_samples)
+        };
+
+        let (width, height) = (width as usize, height as usize);
+        let mut pixels = vec![0; width * height * 3];
+        for y in 0..height {
+            for x in 0..width {
+                let luma = unit(x / h, y / v).map_or(0, |unit| unit[(y % v) * h + x % h]);
+                let pixel = (y * width + x) * 3;
+                pixels[pixel] = luma;
+                for chroma in 0..2 {
+                    pixels[pixel + 1 + chroma] = self.interpolate(x, y, across, down, |c, r| {
+                        unit(c, r).map_or(0.0, |unit| f64::from(unit[h * v + chroma]))
+                    });
+                }
+            }
+        }
+        pixels
+    }
+
+    /// Interpolate the chroma at pixel (`x`, `y`) from the chroma samples of the surrounding data
+    /// units, found through `sample`, taking account of where the samples are positioned.
+    fn interpolate<F: Fn(usize, usize) -> f64>(
+        &self,
+        x: usize,
+        y: usize,
+        across: usize,
+        down: usize,
+        sample: F,
+    ) -> u32 {
+        let (h, v) = (f64::from(self.subsampling.0), f64::from(self.subsampling.1));
+        // Position of the pixel in units of data units, relative to the first chroma sample
+        let (u, w) = if self.positioning == ycbcr_positioning::COSITED {
+            (x as f64 / h, y as f64 / v)
+        } else {
+            ((x as f64 + 0.5) / h - 0.5, (y as f64 + 0.5) / v - 0.5)
+        };
+        let clamp = |position: f64, count: usize| position.clamp(0.0, (count - 1) as f64);
+        let (u, w) = (clamp(u, across), clamp(w, down));
+        let (left, top) = (u.floor() as usize, w.floor() as usize);
+        let (right, bottom) = ((left + 1).min(across - 1), (top + 1).min(down - 1));
+        let (fx, fy) = (u - left as f64, w - top as f64);
+
+        let upper = sample(left, top) * (1.0 - fx) + sample(right, top) * fx;
+        let lower = sample(left, bottom) * (1.0 - fx) + sample(right, bottom) * fx;
+        (upper * (1.0 - fy) + lower * fy).round() as u32
+    }
+
+    /// Pack full resolution interleaved Y, Cb, Cr pixels of an area of `width` by `height`
+    /// pixels into data units. Chroma is averaged over each block, or taken from its first pixel
+    /// when cosited. Blocks overhanging the area repeat its last row and column.
+    pub fn pack(&self, pixels: &[u32], width: u32, height: u32) -> Vec<u32> {
+        let (h, v) = (
+            usize::from(self.subsampling.0),
+            usize::from(self.subsampling.1),
+        );
+        let (across, down) = self.units(width, height);
+        let (width, height) = (width as usize, height as usize);
+        let pixel = |x: usize, y: usize| {
+            let index = (y.min(height - 1) * width + x.min(width - 1)) * 3;
+            &pixels[index..index + 3]
+        };
+
+        let mut data = Vec::with_capacity(across * down * self.unit_samples());
+        for row in 0..down {
+            for column in 0..across {
+                let (x, y) = (column * h, row * v);
+                for dy in 0..v {
+                    for dx in 0..h {
+                        data.push(pixel(x + dx, y + dy)[0]);
+                    }
+                }
+                for chroma in 1..3 {
+                    data.push(if self.positioning == ycbcr_positioning::COSITED {
+                        pixel(x, y)[chroma]
+                    } else {
+                        let sum: u64 = (0..v)
+                            .flat_map(|dy| (0..h).map(move |dx| (dx, dy)))
+                            .map(|(dx, dy)| u64::from(pixel(x + dx, y + dy)[chroma]))
+                            .sum();
+                        ((sum + (h * v) as u64 / 2) / (h * v) as u64) as u32
+                    });
+                }
+            }
+        }
+        data
+    }
+
+    /// Like `unpack`, for a buffer of u8, u16 or u32 samples.
+    pub fn unpack_buffer(
+        &self,
+        data: &PixelBuffer,
+        width: u32,
+        height: u32,
+    ) -> Result<PixelBuffer> {
+        let samples = buffer_samples(data)?;
+        Ok(typed_buffer(data, self.unpack(&samples, width, height)))
+    }
+
+    /// Like `pack`, for a buffer of u8, u16 or u32 samples.
+    pub fn pack_buffer(
+        &self,
+        pixels: &PixelBuffer,
+        width: u32,
+        height: u32,
+    ) -> Result<PixelBuffer> {
+        let samples = buffer_samples(pixels)?;
+        Ok(typed_buffer(pixels, self.pack(&samples, width, height)))
+    }
+
+    /// Convert an interleaved 8 or 16-bit RGB `image` to full resolution YCbCr, ready to be
+    /// subsampled by `ImageEncoder::with_ycbcr`. Any samples beyond the first three are dropped.
+    pub fn from_rgb_image(&self, image: &Image) -> Result<Image> {
+        if image.photometric != photometic_interpretation::RGB || image.samples_per_pixel < 3 {
+            return Err(ColorError::ImageMismatch.into());
+        }
+        let image = image.to_interleaved();
+        let samples = buffer_samples(&image.data)?;
+        let max = match image.data {
+            PixelBuffer::U8(_) => f64::from(u8::MAX),
+            PixelBuffer::U16(_) => f64::from(u16::MAX),
+            _ => f64::from(u32::MAX),
+        };
+        let ycbcr = samples
+            .chunks_exact(usize::from(image.samples_per_pixel))
+            .flat_map(|pixel| {
+                let rgb = [0, 1, 2].map(|channel| f64::from(pixel[channel]) / max);
+                self.from_rgb(rgb, max).map(|sample| sample as u32)
+            })
+            .collect();
+        Ok(Image {
+            width: image.width,
+            height: image.height,
+            samples_per_pixel: 3,
+            photometric: photometic_interpretation::YCBCR,
+            planar_configuration: planar_configuration::CHUNKY,
+            data: typed_buffer(&image.data, ycbcr),
+        })
+    }
+
+    /// Convert raw Y, Cb and Cr samples to RGB between 0 and 1.
+    pub fn to_rgb(&self, y: f64, cb: f64, cr: f64, max: f64) -> [f64; 3] {
+        let [red, green, blue] = self.coefficients;
+        let luma = self.normalize(y, 0, max);
+        let cb = self.normalize(cb, 1, max);
+        let cr = self.normalize(cr, 2, max);
+
+        let r = luma + (2.0 - 2.0 * red) * cr;
+        let b = luma + (2.0 - 2.0 * blue) * cb;
+        let g = (luma - blue * b - red * r) / green;
+        [r.clamp(0.0, 1.0), g.clamp(0.0, 1.0), b.clamp(0.0, 1.0)]
+    }
+
+    /// Convert RGB between 0 and 1 to raw Y, Cb and Cr samples.
+    pub fn from_rgb(&self, rgb: [f64; 3], max: f64) -> [f64; 3] {
+        let [red, green, blue] = self.coefficients;
+        let luma = red * rgb[0] + green * rgb[1] + blue * rgb[2];
+        let cb = (rgb[2] - luma) / (2.0 - 2.0 * blue);
+        let cr = (rgb[0] - luma) / (2.0 - 2.0 * red);
+        [
+            self.denormalize(luma, 0, max).clamp(0.0, max),
+            self.denormalize(cb, 1, max).clamp(0.0, max),
+            self.denormalize(cr, 2, max).clamp(0.0, max),
+        ]
+    }
+
+    /// Map a raw sample of component `index` (0 for Y) onto 0 to 1 for luma, or -0.5 to 0.5 for
+    /// chroma, using the reference black and white. Chroma spans the coding range of `max`.
+    fn normalize(&self, sample: f64, index: usize, max: f64) -> f64 {
+        let black = self.reference_black_white[index * 2];
+        let white = self.reference_black_white[index * 2 + 1];
+        let scale = if index == 0 {
+            1.0
+        } else {
+            (max / 2.0).floor() / max
+        };
+        (sample - black) / (white - black) * scale
+    }
+
+    /// The inverse of `normalize`.
+    fn denormalize(&self, value: f64, index: usize, max: f64) -> f64 {
+        let black = self.reference_black_white[index * 2];
+        let white = self.reference_black_white[index * 2 + 1];
+        let scale = if index == 0 {
+            1.0
+        } else {
+            (max / 2.0).floor() / max
+        };
+        (value / scale * (white - black) + black).round()
+    }
+}
+
+/// Read `count` rational values of `tag` as floating point, or `None` if the tag is absent.
+fn rationals(ifd: &IFD, tag: u16, count: usize) -> Result<Option<Vec<f64>>> {
+    let values: &[(u32, u32)] = match ifd.get(tag) {
+        Err(FieldExtractionError::MissingTag { .. }) => return Ok(None),
+        result => result?,
+    };
+    if values.len() < count {
+        return Err(FieldExtractionError::InsufficientData { tag }.into());
+    }
+    Ok(Some(
+        values[..count]
+            .iter()
+            .map(|&(numerator, denominator)| f64::from(numerator) / f64::from(denominator.max(1)))
+            .collect(),
+    ))
+}
+
+/// The samples of a buffer of u8, u16 or u32 samples, widened to u32.
+fn buffer_samples(data: &PixelBuffer) -> Result<Vec<u32>> {
+    Ok(match data {
+        PixelBuffer::U8(samples) => samples.iter().map(|&sample| sample.into()).collect(),
+        PixelBuffer::U16(samples) => samples.iter().map(|&sample| sample.into()).collect(),
+        PixelBuffer::U32(samples) => samples.clone(),
+        _ => return Err(DecodingError::UnsupportedSubsampledLayout.into()),
+    })
+}
+
+/// A buffer of the same type as `like` holding `samples`.
+fn typed_buffer(like: &PixelBuffer, samples: Vec<u32>) -> PixelBuffer {
+    match like {
+        PixelBuffer::U8(_) => PixelBuffer::U8(samples.iter().map(|&sample| sample as u8).collect()),
+        PixelBuffer::U16(_) => {
+            PixelBuffer::U16(samples.iter().map(|&sample| sample as u16).collect())
+        }
+        _ => PixelBuffer::U32(samples),
+    }
+}
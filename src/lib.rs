@@ -6,5 +6,18 @@ pub mod lowlevel;
 /// Helpers for baseline TIFF interpretation
 pub mod baseline;
 
+/// Strip and tile compression schemes
+pub mod compression;
+
+/// Pixel data decoding and encoding
+pub mod image;
+
+/// GPS coordinates of the GPS info IFD
+pub mod gps;
+
+/// GeoTIFF georeferencing
+pub mod geotiff;
+
 /// Error types
 pub mod errors;
+pub use errors::{Error, Result};
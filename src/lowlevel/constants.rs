@@ -3,6 +3,8 @@ pub mod header_magic {
     pub const LITTLE_ENDIAN_MAGIC: [u8; 2] = [b'I', b'I'];
     pub const BIG_ENDIAN_MAGIC: [u8; 2] = [b'M', b'M'];
     pub const VERSION_MAGIC: u16 = 42;
+    pub const BIGTIFF_VERSION_MAGIC: u16 = 43;
+    pub const BIGTIFF_OFFSET_SIZE: u16 = 8;
 }
 
 /// IFD Field types
@@ -22,4 +24,10 @@ pub mod ifd_field_type_magic {
     pub const IFD_TYPE_SRATIONAL: u16 = 0x000A;
     pub const IFD_TYPE_FLOAT: u16 = 0x000B;
     pub const IFD_TYPE_DOUBLE: u16 = 0x000C;
+    pub const IFD_TYPE_IFD: u16 = 0x000D;
+
+    // BigTIFF
+    pub const IFD_TYPE_LONG8: u16 = 0x0010;
+    pub const IFD_TYPE_SLONG8: u16 = 0x0011;
+    pub const IFD_TYPE_IFD8: u16 = 0x0012;
 }
@@ -1,44 +1,45 @@
-use crate::errors::FieldExtractionError;
+use crate::errors::{FieldConversionError, FieldExtractionError};
 use crate::lowlevel::{IFDField, IFD};
 use std::convert::TryInto;
 
 impl IFD {
     pub fn get<'a, T>(&'a self, tag: u16) -> Result<T, FieldExtractionError>
     where
-        &'a IFDField: TryInto<T, Error = FieldExtractionError>,
+        &'a IFDField: TryInto<T, Error = FieldConversionError>,
     {
         self.entries
             .get(&tag)
             .ok_or(FieldExtractionError::MissingTag { tag })?
             .try_into()
+            .map_err(|error: FieldConversionError| error.for_tag(tag))
     }
 }
 
 macro_rules! impl_ifdfield_conv {
     { $t:ty, $v:path } => {
         impl<'a> TryInto<&'a [$t]> for &'a IFDField {
-            type Error = FieldExtractionError;
+            type Error = FieldConversionError;
             fn try_into(self) -> Result<&'a [$t], Self::Error> {
                 match self {
                     $v(val) => Ok(val),
-                    _ => Err(FieldExtractionError::WrongDataType),
+                    _ => Err(FieldConversionError::WrongDataType),
                 }
             }
         }
 
         impl<'a> TryInto<&'a $t> for &'a IFDField {
-            type Error = FieldExtractionError;
+            type Error = FieldConversionError;
             fn try_into(self) -> Result<&'a $t, Self::Error> {
                 let array: &[$t] = self.try_into()?;
-                array.get(0).ok_or(FieldExtractionError::InsufficientData)
+                array.get(0).ok_or(FieldConversionError::InsufficientData)
             }
         }
 
         impl<'a> TryInto<$t> for &'a IFDField {
-            type Error = FieldExtractionError;
+            type Error = FieldConversionError;
             fn try_into(self) -> Result<$t, Self::Error> {
                 let array: &[$t] = self.try_into()?;
-                Ok(array.get(0).ok_or(FieldExtractionError::InsufficientData)?.clone())
+                Ok(array.get(0).ok_or(FieldConversionError::InsufficientData)?.clone())
             }
         }
 
@@ -51,3 +52,26 @@ impl_ifdfield_conv!(u16, IFDField::Short);
 impl_ifdfield_conv!(u32, IFDField::Long);
 impl_ifdfield_conv!(String, IFDField::Ascii);
 impl_ifdfield_conv!((u32, u32), IFDField::Rational);
+impl_ifdfield_conv!(i8, IFDField::SByte);
+impl_ifdfield_conv!(i16, IFDField::SShort);
+impl_ifdfield_conv!(i32, IFDField::SLong);
+impl_ifdfield_conv!((i32, i32), IFDField::SRational);
+impl_ifdfield_conv!(f32, IFDField::Float);
+impl_ifdfield_conv!(f64, IFDField::Double);
+impl_ifdfield_conv!(u64, IFDField::Long8);
+impl_ifdfield_conv!(i64, IFDField::SLong8);
+
+/// Widens any unsigned integer field, which is useful for offsets and byte counts that may be
+/// stored as SHORT, LONG or LONG8 depending on the writer.
+impl TryInto<Vec<u64>> for &IFDField {
+    type Error = FieldConversionError;
+    fn try_into(self) -> Result<Vec<u64>, Self::Error> {
+        match self {
+            IFDField::Byte(val) => Ok(val.iter().map(|&v| v.into()).collect()),
+            IFDField::Short(val) => Ok(val.iter().map(|&v| v.into()).collect()),
+            IFDField::Long(val) => Ok(val.iter().map(|&v| v.into()).collect()),
+            IFDField::Long8(val) | IFDField::Ifd8(val) => Ok(val.to_vec()),
+            _ => Err(FieldConversionError::WrongDataType),
+        }
+    }
+}
@@ -1,21 +1,83 @@
+use crate::errors::Result;
 use crate::lowlevel::constants::header_magic::{
-    BIG_ENDIAN_MAGIC, LITTLE_ENDIAN_MAGIC, VERSION_MAGIC,
+    BIGTIFF_OFFSET_SIZE, BIGTIFF_VERSION_MAGIC, BIG_ENDIAN_MAGIC, LITTLE_ENDIAN_MAGIC,
+    VERSION_MAGIC,
 };
 use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt};
-use failure::{Fail, Fallible};
-use std::io::Write;
+use std::io::{self, Write};
+use thiserror::Error;
 
 /// Represents an error encountered when reading a TIFF header.
-#[derive(Fail, Debug)]
+#[derive(Error, Debug)]
 pub enum HeaderError {
-    #[fail(display = "Bad endian magic number: {:?}", culprit)]
+    #[error("Bad endian magic number: {culprit:?}")]
     BadEndianMagic { culprit: [u8; 2] },
-    #[fail(display = "Bad magic number: {:?}", magic)]
+    #[error("Bad magic number: {magic:?}")]
     BadMagic { magic: u16 },
+    #[error("Unsupported BigTIFF offset size: {size}")]
+    BadOffsetSize { size: u16 },
+    #[error("Non-zero BigTIFF reserved header field: {value}")]
+    BadReserved { value: u16 },
+}
+
+/// The layout of a TIFF file, which decides the width of its offsets and counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TiffFormat {
+    /// Classic TIFF (version 42) with 32-bit offsets and counts.
+    #[default]
+    Classic,
+    /// BigTIFF (version 43) with 64-bit offsets and counts.
+    BigTiff,
+}
+
+impl TiffFormat {
+    /// Size in bytes of an offset, which is also the size of the value/offset slot of an IFD entry.
+    pub fn offset_size(self) -> usize {
+        match self {
+            TiffFormat::Classic => 4,
+            TiffFormat::BigTiff => 8,
+        }
+    }
+
+    /// Read an offset (or IFD entry count) of this format's width from `reader`.
+    pub fn read_offset<E: ByteOrder, R: ReadBytesExt>(self, reader: &mut R) -> io::Result<u64> {
+        match self {
+            TiffFormat::Classic => reader.read_u32::<E>().map(u64::from),
+            TiffFormat::BigTiff => reader.read_u64::<E>(),
+        }
+    }
+
+    /// Write an offset (or IFD entry count) of this format's width to `writer`. Fails instead of
+    /// truncating values which do not fit within a classic TIFF.
+    pub fn write_offset<E: ByteOrder, W: WriteBytesExt>(
+        self,
+        writer: &mut W,
+        offset: u64,
+    ) -> io::Result<()> {
+        match self {
+            TiffFormat::Classic => writer.write_u32::<E>(classic_u32(offset)?),
+            TiffFormat::BigTiff => writer.write_u64::<E>(offset),
+        }
+    }
+}
+
+/// Narrow `value` to the 32 bits available in a classic TIFF.
+pub(crate) fn classic_u32(value: u64) -> io::Result<u32> {
+    if value > u64::from(u32::MAX) {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "Value {} exceeds the 32-bit limit of classic TIFF, use BigTIFF instead",
+                value
+            ),
+        ))
+    } else {
+        Ok(value as u32)
+    }
 }
 
 /// Determine the endian of the file in `reader`. Returns `true` if the file is little-endian.
-pub fn read_header_endian<R: ReadBytesExt>(reader: &mut R) -> Fallible<bool> {
+pub fn read_header_endian<R: ReadBytesExt>(reader: &mut R) -> Result<bool> {
     let mut endian_magic = [0u8; 2];
     reader.read_exact(&mut endian_magic)?;
     match endian_magic {
@@ -25,13 +87,23 @@ pub fn read_header_endian<R: ReadBytesExt>(reader: &mut R) -> Fallible<bool> {
     }
 }
 
-/// Read and check the magic number from `reader`.
-pub fn read_header_magic<E: ByteOrder, R: ReadBytesExt>(reader: &mut R) -> Fallible<()> {
-    let magic = reader.read_u16::<E>()?;
-    if magic != VERSION_MAGIC {
-        Err(HeaderError::BadMagic { magic }.into())
-    } else {
-        Ok(())
+/// Read and check the magic number from `reader`, returning the format it describes. For
+/// BigTIFF files this also consumes the offset size and reserved fields of the header.
+pub fn read_header_magic<E: ByteOrder, R: ReadBytesExt>(reader: &mut R) -> Result<TiffFormat> {
+    match reader.read_u16::<E>()? {
+        VERSION_MAGIC => Ok(TiffFormat::Classic),
+        BIGTIFF_VERSION_MAGIC => {
+            let size = reader.read_u16::<E>()?;
+            if size != BIGTIFF_OFFSET_SIZE {
+                return Err(HeaderError::BadOffsetSize { size }.into());
+            }
+            let value = reader.read_u16::<E>()?;
+            if value != 0 {
+                return Err(HeaderError::BadReserved { value }.into());
+            }
+            Ok(TiffFormat::BigTiff)
+        }
+        magic => Err(HeaderError::BadMagic { magic }.into()),
     }
 }
 
@@ -40,12 +112,27 @@ fn endian_type_is_little<E: ByteOrder>() -> bool {
     E::read_u16(&[42, 0]) == 42
 }
 
-/// Write the TIFF endian header and magic number to `writer`.
+/// Write the classic TIFF endian header and magic number to `writer`.
 pub fn write_header<E: ByteOrder, W: Write>(writer: &mut W) -> Result<(), std::io::Error> {
+    write_header_format::<E, W>(writer, TiffFormat::Classic)
+}
+
+/// Write the TIFF endian header and magic number for `format` to `writer`.
+pub fn write_header_format<E: ByteOrder, W: Write>(
+    writer: &mut W,
+    format: TiffFormat,
+) -> Result<(), std::io::Error> {
     if endian_type_is_little::<E>() {
-        writer.write(&LITTLE_ENDIAN_MAGIC)
+        writer.write_all(&LITTLE_ENDIAN_MAGIC)
     } else {
-        writer.write(&BIG_ENDIAN_MAGIC)
+        writer.write_all(&BIG_ENDIAN_MAGIC)
     }?;
-    writer.write_u16::<E>(VERSION_MAGIC)
+    match format {
+        TiffFormat::Classic => writer.write_u16::<E>(VERSION_MAGIC),
+        TiffFormat::BigTiff => {
+            writer.write_u16::<E>(BIGTIFF_VERSION_MAGIC)?;
+            writer.write_u16::<E>(BIGTIFF_OFFSET_SIZE)?;
+            writer.write_u16::<E>(0)
+        }
+    }
 }
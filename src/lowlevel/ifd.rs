@@ -1,7 +1,9 @@
-use crate::lowlevel::ifd_field::IFDField;
+use crate::baseline::tags;
+use crate::lowlevel::header::TiffFormat;
+use crate::lowlevel::ifd_field::{FieldTypeSizes, IFDField};
 use crate::lowlevel::raw_ifd::{RawIFD, RawIFDField};
 use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::io::{self, Seek};
 
 /// A high-level representation of an Image File Directory.
@@ -9,6 +11,10 @@ use std::io::{self, Seek};
 pub struct IFD {
     /// HashMap of entries by Tag to IFDField
     pub entries: HashMap<u16, IFDField>,
+    /// IFDs found through the offsets held by pointer tags such as `SUB_IFDS` and `EXIF_IFD`, by
+    /// tag. Each offset of the tag leads to a chain of IFDs, which are read along with their own
+    /// children.
+    pub children: BTreeMap<u16, Vec<Box<[IFD]>>>,
 }
 
 impl IFD {
@@ -16,29 +22,98 @@ impl IFD {
     pub fn new() -> Self {
         Self {
             entries: HashMap::new(),
+            children: BTreeMap::new(),
         }
     }
 
+    /// The chains of IFDs pointed to by the `SUB_IFDS` tag, such as reduced-resolution versions
+    /// of this image.
+    pub fn sub_ifds(&self) -> &[Box<[IFD]>] {
+        self.children
+            .get(&tags::SUB_IFDS)
+            .map_or(&[], |chains| chains.as_slice())
+    }
+
+    /// The first IFD pointed to by pointer tag `tag`, such as `EXIF_IFD`.
+    pub fn child(&self, tag: u16) -> Option<&IFD> {
+        self.children.get(&tag)?.first()?.first()
+    }
+
+    /// Make `ifd` the only IFD pointed to by pointer tag `tag`, such as `EXIF_IFD`. The tag itself
+    /// is filled in by `MetadataWriter::write_ifd`.
+    pub fn set_child(&mut self, tag: u16, ifd: IFD) {
+        self.children
+            .insert(tag, vec![vec![ifd].into_boxed_slice()]);
+    }
+
+    /// The EXIF private IFD, pointed to by the `EXIF_IFD` tag.
+    pub fn exif(&self) -> Option<&IFD> {
+        self.child(tags::EXIF_IFD)
+    }
+
+    /// Make `exif` the EXIF private IFD of this IFD.
+    pub fn set_exif(&mut self, exif: IFD) {
+        self.set_child(tags::EXIF_IFD, exif)
+    }
+
+    /// The Interoperability IFD of an EXIF IFD, pointed to by `exif::INTEROPERABILITY_IFD`.
+    pub fn interoperability(&self) -> Option<&IFD> {
+        self.child(tags::exif::INTEROPERABILITY_IFD)
+    }
+
+    /// Make `interoperability` the Interoperability IFD of this EXIF IFD.
+    pub fn set_interoperability(&mut self, interoperability: IFD) {
+        self.set_child(tags::exif::INTEROPERABILITY_IFD, interoperability)
+    }
+
+    /// The GPS info IFD, pointed to by the `GPS_IFD` tag.
+    pub fn gps(&self) -> Option<&IFD> {
+        self.child(tags::GPS_IFD)
+    }
+
+    /// Make `gps` the GPS info IFD of this IFD.
+    pub fn set_gps(&mut self, gps: IFD) {
+        self.set_child(tags::GPS_IFD, gps)
+    }
+
+    /// Add a chain of IFDs to those pointed to by the `SUB_IFDS` tag. The tag itself is filled in
+    /// by `MetadataWriter::write_ifd`.
+    pub fn add_sub_ifds(&mut self, chain: Vec<IFD>) {
+        self.children
+            .entry(tags::SUB_IFDS)
+            .or_default()
+            .push(chain.into_boxed_slice());
+    }
+
     /// Read the fields from `reader` into memory, (de)referencing information from `raw_ifd`.
+    /// `sizes` decides which fields of otherwise unknown types are kept as opaque data. Child IFDs
+    /// are left for `MetadataReader` to find.
     pub fn read_from<E: ByteOrder, R: ReadBytesExt + Seek>(
         reader: &mut R,
         raw_ifd: &RawIFD,
+        format: TiffFormat,
+        sizes: &FieldTypeSizes,
     ) -> Result<Self, io::Error> {
         Ok(Self {
             entries: raw_ifd
                 .entries
                 .iter()
                 .map(|field| {
-                    IFDField::read_from::<E, R>(reader, field).map(|data| (field.tag, data))
+                    IFDField::read_from::<E, R>(reader, field, format, sizes)
+                        .map(|data| (field.tag, data))
                 })
                 .collect::<Result<HashMap<u16, IFDField>, io::Error>>()?,
+            children: BTreeMap::new(),
         })
     }
 
     /// Write the fields into `writer`, returning a RawIFD describing their locations and/or data.
+    /// Fails on `IFDField::Unrecognized` fields, as their data cannot be relocated; register the
+    /// sizes of such types with `MetadataReader::read_header_with_sizes`, or remove the fields.
     pub fn write_to<E: ByteOrder, W: WriteBytesExt + Seek>(
         &self,
         writer: &mut W,
+        format: TiffFormat,
     ) -> Result<RawIFD, io::Error> {
         // Tags must be sorted in ascending order
         let mut sorted_entries = self.entries.iter().collect::<Vec<(&u16, &IFDField)>>();
@@ -46,7 +121,7 @@ impl IFD {
         Ok(RawIFD {
             entries: sorted_entries
                 .iter()
-                .map(|(&tag, data)| data.write_to::<E, W>(writer, tag))
+                .map(|(&tag, data)| data.write_to::<E, W>(writer, tag, format))
                 .collect::<Result<Vec<RawIFDField>, io::Error>>()?,
         })
     }
@@ -1,6 +1,45 @@
-use crate::lowlevel::{constants::ifd_field_type_magic::*, raw_ifd::RawIFDField};
+use crate::lowlevel::{
+    constants::ifd_field_type_magic::*,
+    header::TiffFormat,
+    raw_ifd::{ensure_available, RawIFDField},
+};
 use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt};
-use std::io::{self, Cursor, Seek, SeekFrom};
+use std::collections::HashMap;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+
+/// Registry of element sizes for field types which have no variant of their own, allowing their
+/// data to be read as `IFDField::Opaque` blobs instead of `IFDField::Unrecognized` pointers.
+#[derive(Debug, Clone)]
+pub struct FieldTypeSizes {
+    sizes: HashMap<u16, u64>,
+}
+
+impl Default for FieldTypeSizes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FieldTypeSizes {
+    /// Create an empty registry. Fields of the IFD type (13) are only kept as opaque data if its
+    /// size is registered, as their payload holds offsets which are invalid once rewritten.
+    pub fn new() -> Self {
+        Self {
+            sizes: HashMap::new(),
+        }
+    }
+
+    /// Register `size` as the byte size of a single element of `tag_type`.
+    pub fn register(&mut self, tag_type: u16, size: u64) -> &mut Self {
+        self.sizes.insert(tag_type, size);
+        self
+    }
+
+    /// Look up the byte size of a single element of `tag_type`, if known.
+    pub fn get(&self, tag_type: u16) -> Option<u64> {
+        field_type_size(tag_type).or_else(|| self.sizes.get(&tag_type).copied())
+    }
+}
 
 /// IFD Field Data, essentially a dynamic type representing TIFF's array fields.
 #[derive(Debug, Clone, PartialEq)]
@@ -12,33 +51,64 @@ pub enum IFDField {
     Short(Box<[u16]>),
     Long(Box<[u32]>),
     Rational(Box<[(u32, u32)]>),
-    /// The `type` field of the tag was unrecognized when reading. This variant will be ignored
-    /// when writing, as there is no way to know how to write it correctly.
+    /// The `type` field of the tag was unrecognized when reading. Writing this variant fails, as
+    /// there is no way to know where its data lives or how to write it correctly.
     Unrecognized {
         /// Integer representing the type of this tag.
         tag_type: u16,
         /// Integer representing the quantity (not byte count) of this tag.
-        count: u32,
+        count: u64,
         /// Either the tag's value, or a pointer to a location within the file.
-        value_or_offset: [u8; 4],
+        value_or_offset: [u8; 8],
+    },
+    /// A field of a type without its own variant, read using an element size registered in
+    /// `FieldTypeSizes`. The data is kept verbatim in the byte order of the source file, so it
+    /// must be written in that same byte order (at a fresh offset if necessary).
+    Opaque {
+        /// Integer representing the type of this tag.
+        tag_type: u16,
+        /// Integer representing the quantity (not byte count) of this tag.
+        count: u64,
+        /// The dereferenced data of this tag.
+        data: Box<[u8]>,
     },
-    /* Sbyte(Box<[i8]>),
+    SByte(Box<[i8]>),
     SShort(Box<[i16]>),
     SLong(Box<[i32]>),
     SRational(Box<[(i32, i32)]>),
+    Float(Box<[f32]>),
     Double(Box<[f64]>),
-    Float(Box<[f32]>), */
+    /// BigTIFF only.
+    Long8(Box<[u64]>),
+    /// BigTIFF only.
+    SLong8(Box<[i64]>),
+    /// BigTIFF only. Offsets to other IFDs.
+    Ifd8(Box<[u64]>),
 }
 
 impl IFDField {
     /// Read the content from `reader` into this IFDField, dereferencing offset pointers from `field`.
+    /// Fields of types without their own variant are read as `Opaque` if `sizes` knows their size.
     pub fn read_from<E: ByteOrder, R: ReadBytesExt + Seek>(
         reader: &mut R,
         field: &RawIFDField,
+        format: TiffFormat,
+        sizes: &FieldTypeSizes,
     ) -> Result<Self, io::Error> {
-        if tag_exceeds_ifd_field(field.tag_type, field.count) {
-            let tag_data_offset = field.value_or_offset.as_ref().read_u32::<E>()?;
-            reader.seek(SeekFrom::Start(tag_data_offset.into()))?;
+        if field_type_size(field.tag_type).is_none() {
+            if let Some(size) = sizes.get(field.tag_type) {
+                return Self::read_opaque::<E, R>(reader, field, format, size);
+            }
+        }
+
+        if tag_exceeds_ifd_field(field.tag_type, field.count, format) {
+            let tag_data_offset =
+                format.read_offset::<E, _>(&mut field.value_or_offset.as_ref())?;
+            reader.seek(SeekFrom::Start(tag_data_offset))?;
+            let byte_count = field_type_size(field.tag_type)
+                .unwrap_or(0)
+                .saturating_mul(field.count);
+            ensure_available(reader, byte_count)?;
             Self::from_raw_field_reader::<E, R>(reader, field.tag_type, field.count)
         } else {
             let mut tag_data_cursor = Cursor::new(field.value_or_offset);
@@ -46,10 +116,44 @@ impl IFDField {
         }
     }
 
+    fn read_opaque<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        field: &RawIFDField,
+        format: TiffFormat,
+        size: u64,
+    ) -> Result<Self, io::Error> {
+        let byte_count = size.checked_mul(field.count).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Tag {:#X} data size overflows", field.tag),
+            )
+        })?;
+
+        let data = if byte_count > format.offset_size() as u64 {
+            let tag_data_offset =
+                format.read_offset::<E, _>(&mut field.value_or_offset.as_ref())?;
+            reader.seek(SeekFrom::Start(tag_data_offset))?;
+            let mut buffer = Vec::new();
+            reader.by_ref().take(byte_count).read_to_end(&mut buffer)?;
+            if buffer.len() as u64 != byte_count {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            buffer.into_boxed_slice()
+        } else {
+            field.value_or_offset[..byte_count as usize].into()
+        };
+
+        Ok(IFDField::Opaque {
+            tag_type: field.tag_type,
+            count: field.count,
+            data,
+        })
+    }
+
     fn from_raw_field_reader<E: ByteOrder, R: ReadBytesExt>(
         reader: &mut R,
         tag_type: u16,
-        count: u32,
+        count: u64,
     ) -> Result<Self, io::Error> {
         Ok(match tag_type {
             IFD_TYPE_BYTE => {
@@ -88,14 +192,55 @@ impl IFDField {
                 reader.read_exact(&mut buffer)?;
                 IFDField::Undefined(buffer.into_boxed_slice())
             }
-            IFD_TYPE_SBYTE => unimplemented!("SByte IFD values"),
-            IFD_TYPE_SSHORT => unimplemented!("SShort IFD values"),
-            IFD_TYPE_SLONG => unimplemented!("SLong IFD values"),
-            IFD_TYPE_SRATIONAL => unimplemented!("SRational IFD values"),
-            IFD_TYPE_FLOAT => unimplemented!("Float IFD values"),
-            IFD_TYPE_DOUBLE => unimplemented!("Double IFD values"),
+            IFD_TYPE_SBYTE => {
+                let mut buffer = vec![0; count as usize];
+                reader.read_i8_into(&mut buffer)?;
+                IFDField::SByte(buffer.into_boxed_slice())
+            }
+            IFD_TYPE_SSHORT => {
+                let mut buffer = vec![0; count as usize];
+                reader.read_i16_into::<E>(&mut buffer)?;
+                IFDField::SShort(buffer.into_boxed_slice())
+            }
+            IFD_TYPE_SLONG => {
+                let mut buffer = vec![0; count as usize];
+                reader.read_i32_into::<E>(&mut buffer)?;
+                IFDField::SLong(buffer.into_boxed_slice())
+            }
+            IFD_TYPE_SRATIONAL => {
+                let mut rational_buffer = Vec::with_capacity(count as usize);
+                for _ in 0..count {
+                    rational_buffer.push((reader.read_i32::<E>()?, reader.read_i32::<E>()?));
+                }
+                IFDField::SRational(rational_buffer.into_boxed_slice())
+            }
+            IFD_TYPE_FLOAT => {
+                let mut buffer = vec![0.0; count as usize];
+                reader.read_f32_into::<E>(&mut buffer)?;
+                IFDField::Float(buffer.into_boxed_slice())
+            }
+            IFD_TYPE_DOUBLE => {
+                let mut buffer = vec![0.0; count as usize];
+                reader.read_f64_into::<E>(&mut buffer)?;
+                IFDField::Double(buffer.into_boxed_slice())
+            }
+            IFD_TYPE_LONG8 => {
+                let mut buffer = vec![0; count as usize];
+                reader.read_u64_into::<E>(&mut buffer)?;
+                IFDField::Long8(buffer.into_boxed_slice())
+            }
+            IFD_TYPE_SLONG8 => {
+                let mut buffer = vec![0; count as usize];
+                reader.read_i64_into::<E>(&mut buffer)?;
+                IFDField::SLong8(buffer.into_boxed_slice())
+            }
+            IFD_TYPE_IFD8 => {
+                let mut buffer = vec![0; count as usize];
+                reader.read_u64_into::<E>(&mut buffer)?;
+                IFDField::Ifd8(buffer.into_boxed_slice())
+            }
             _ => {
-                let mut value_or_offset = [0u8; 4];
+                let mut value_or_offset = [0u8; 8];
                 reader.read_exact(&mut value_or_offset)?;
                 IFDField::Unrecognized {
                     tag_type,
@@ -106,20 +251,42 @@ impl IFDField {
         })
     }
 
-    /// Write this IFDField into `writer`
+    /// Write this IFDField into `writer`. Fails if the field is `Unrecognized`, or if it cannot be
+    /// represented in `format`.
     pub fn write_to<E: ByteOrder, W: WriteBytesExt + Seek>(
         &self,
         writer: &mut W,
         tag: u16,
+        format: TiffFormat,
     ) -> Result<RawIFDField, io::Error> {
-        let mut value_or_offset = [0u8; 4];
+        let mut value_or_offset = [0u8; 8];
         let mut cursor = Cursor::new(&mut value_or_offset[..]);
 
         let tag_type = self.type_number();
-        let count = self.count() as u32;
-        if tag_exceeds_ifd_field(tag_type, count) {
-            let data_offset = writer.seek(SeekFrom::Current(0))? as u32;
-            cursor.write_u32::<E>(data_offset)?;
+        if let Self::Unrecognized { .. } = self {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "Tag {:#X} has unrecognized type {}, whose size must be registered to write it",
+                    tag, tag_type
+                ),
+            ));
+        }
+        if format == TiffFormat::Classic && is_bigtiff_type(tag_type) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Tag {:#X} has a type only valid in BigTIFF files", tag),
+            ));
+        }
+
+        let count = self.count() as u64;
+        let exceeds_ifd_field = match self {
+            Self::Opaque { data, .. } => data.len() > format.offset_size(),
+            _ => tag_exceeds_ifd_field(tag_type, count, format),
+        };
+        if exceeds_ifd_field {
+            let data_offset = writer.stream_position()?;
+            format.write_offset::<E, _>(&mut cursor, data_offset)?;
             self.write_field_into::<E, _>(writer)?;
         } else {
             self.write_field_into::<E, _>(&mut cursor)?;
@@ -139,12 +306,12 @@ impl IFDField {
         writer: &mut W,
     ) -> Result<(), io::Error> {
         match self {
-            Self::Undefined(bytes) => writer.write_all(&bytes),
-            Self::Byte(bytes) => writer.write_all(&bytes),
+            Self::Undefined(bytes) => writer.write_all(bytes),
+            Self::Byte(bytes) => writer.write_all(bytes),
             Self::Ascii(strings) => {
                 for string in strings.iter() {
                     writer.write_all(string.as_bytes())?;
-                    writer.write_all(&[b'\0'])?;
+                    writer.write_all(b"\0")?;
                 }
                 Ok(())
             }
@@ -159,9 +326,34 @@ impl IFDField {
                     .write_u32::<E>(*a)
                     .and_then(|()| writer.write_u32::<E>(*b))
             }),
+            Self::SByte(sbytes) => sbytes.iter().try_for_each(|sbyte| writer.write_i8(*sbyte)),
+            Self::SShort(sshorts) => sshorts
+                .iter()
+                .try_for_each(|sshort| writer.write_i16::<E>(*sshort)),
+            Self::SLong(slongs) => slongs
+                .iter()
+                .try_for_each(|slong| writer.write_i32::<E>(*slong)),
+            Self::SRational(srationals) => srationals.iter().try_for_each(|(a, b)| {
+                writer
+                    .write_i32::<E>(*a)
+                    .and_then(|()| writer.write_i32::<E>(*b))
+            }),
+            Self::Float(floats) => floats
+                .iter()
+                .try_for_each(|float| writer.write_f32::<E>(*float)),
+            Self::Double(doubles) => doubles
+                .iter()
+                .try_for_each(|double| writer.write_f64::<E>(*double)),
+            Self::Long8(long8s) | Self::Ifd8(long8s) => long8s
+                .iter()
+                .try_for_each(|long8| writer.write_u64::<E>(*long8)),
+            Self::SLong8(slong8s) => slong8s
+                .iter()
+                .try_for_each(|slong8| writer.write_i64::<E>(*slong8)),
             Self::Unrecognized {
                 value_or_offset, ..
             } => writer.write_all(value_or_offset),
+            Self::Opaque { data, .. } => writer.write_all(data),
         }
     }
 
@@ -173,7 +365,7 @@ impl IFDField {
             Self::Ascii(strings) => {
                 let mut length: usize = 0;
                 for string in strings.iter() {
-                    length += string.as_bytes().len();
+                    length += string.len();
                     length += 1; // For null character
                 }
                 length
@@ -181,7 +373,17 @@ impl IFDField {
             Self::Short(data) => data.len(),
             Self::Long(data) => data.len(),
             Self::Rational(data) => data.len(),
+            Self::SByte(data) => data.len(),
+            Self::SShort(data) => data.len(),
+            Self::SLong(data) => data.len(),
+            Self::SRational(data) => data.len(),
+            Self::Float(data) => data.len(),
+            Self::Double(data) => data.len(),
+            Self::Long8(data) => data.len(),
+            Self::SLong8(data) => data.len(),
+            Self::Ifd8(data) => data.len(),
             Self::Unrecognized { count, .. } => *count as usize,
+            Self::Opaque { count, .. } => *count as usize,
         }
     }
 
@@ -193,37 +395,55 @@ impl IFDField {
             Self::Short(_) => IFD_TYPE_SHORT,
             Self::Long(_) => IFD_TYPE_LONG,
             Self::Rational(_) => IFD_TYPE_RATIONAL,
+            Self::SByte(_) => IFD_TYPE_SBYTE,
+            Self::SShort(_) => IFD_TYPE_SSHORT,
+            Self::SLong(_) => IFD_TYPE_SLONG,
+            Self::SRational(_) => IFD_TYPE_SRATIONAL,
+            Self::Float(_) => IFD_TYPE_FLOAT,
+            Self::Double(_) => IFD_TYPE_DOUBLE,
+            Self::Long8(_) => IFD_TYPE_LONG8,
+            Self::SLong8(_) => IFD_TYPE_SLONG8,
+            Self::Ifd8(_) => IFD_TYPE_IFD8,
             Self::Unrecognized { tag_type, .. } => *tag_type,
+            Self::Opaque { tag_type, .. } => *tag_type,
+        }
+    }
+
+    /// Store file offsets or byte counts as LONG where possible. LONG8 is only valid in BigTIFFs,
+    /// so writing a classic TIFF which has outgrown 32-bit offsets fails instead of truncating them.
+    pub fn offsets(offsets: Vec<u64>) -> Self {
+        if offsets.iter().all(|&offset| offset <= u64::from(u32::MAX)) {
+            Self::Long(offsets.iter().map(|&offset| offset as u32).collect())
+        } else {
+            Self::Long8(offsets.into_boxed_slice())
         }
     }
 }
 
-/// Decide whether or not the specified count of this tag type exceeds the 4-byte
-/// 'value_or_offset' field within the IFD tag field.
-pub fn tag_exceeds_ifd_field(tag_type: u16, count: u32) -> bool {
+/// Size in bytes of a single element of a recognized field type.
+pub fn field_type_size(tag_type: u16) -> Option<u64> {
     match tag_type {
-        // Single byte per unit fields
-        IFD_TYPE_BYTE if count > 4 => true,
-        IFD_TYPE_ASCII if count > 4 => true,
-        IFD_TYPE_SBYTE if count > 4 => true,
-        IFD_TYPE_UNDEFINED if count > 4 => true,
-
-        // Two byte per unit fields
-        IFD_TYPE_SHORT if count > 2 => true,
-        IFD_TYPE_SSHORT if count > 2 => true,
-
-        // Four byte per unit fields
-        IFD_TYPE_LONG if count > 1 => true,
-        IFD_TYPE_SLONG if count > 1 => true,
-        IFD_TYPE_FLOAT if count > 1 => true,
-
-        // 5+ byte per unit fields
-        IFD_TYPE_RATIONAL => true,
-        IFD_TYPE_SRATIONAL => true,
-        IFD_TYPE_DOUBLE => true,
+        IFD_TYPE_BYTE | IFD_TYPE_ASCII | IFD_TYPE_SBYTE | IFD_TYPE_UNDEFINED => Some(1),
+        IFD_TYPE_SHORT | IFD_TYPE_SSHORT => Some(2),
+        IFD_TYPE_LONG | IFD_TYPE_SLONG | IFD_TYPE_FLOAT => Some(4),
+        IFD_TYPE_RATIONAL | IFD_TYPE_SRATIONAL | IFD_TYPE_DOUBLE => Some(8),
+        IFD_TYPE_LONG8 | IFD_TYPE_SLONG8 | IFD_TYPE_IFD8 => Some(8),
+        _ => None,
+    }
+}
+
+/// Returns true for field types which may only appear in BigTIFF files.
+pub fn is_bigtiff_type(tag_type: u16) -> bool {
+    matches!(tag_type, IFD_TYPE_LONG8 | IFD_TYPE_SLONG8 | IFD_TYPE_IFD8)
+}
 
+/// Decide whether or not the specified count of this tag type exceeds the
+/// 'value_or_offset' field within the IFD tag field (4 bytes, or 8 bytes in BigTIFF).
+pub fn tag_exceeds_ifd_field(tag_type: u16, count: u64, format: TiffFormat) -> bool {
+    match field_type_size(tag_type) {
+        Some(size) => size.saturating_mul(count) > format.offset_size() as u64,
         // Otherwise, assume it fits (As it is unrecognized and custom-defined)
-        _ => false,
+        None => false,
     }
 }
 
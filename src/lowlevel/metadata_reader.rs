@@ -1,49 +1,82 @@
+use crate::baseline::tags;
+use crate::errors::{Error, FieldConversionError, Result};
 use crate::lowlevel::{
-    header::{read_header_endian, read_header_magic},
+    constants::ifd_field_type_magic::{IFD_TYPE_IFD, IFD_TYPE_LONG},
+    header::{read_header_endian, read_header_magic, TiffFormat},
     ifd::IFD,
+    ifd_field::{FieldTypeSizes, IFDField},
     raw_ifd::*,
 };
 use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt};
-use failure::Fallible;
+use std::convert::TryInto;
 use std::io::{Seek, SeekFrom};
 
 /// A TIFF metadata (header/IFD) reader.
 pub struct MetadataReader {
     /// Whether the file is in little endian byte order.
     is_little_endian: bool,
+    /// Whether the file is a classic TIFF or a BigTIFF.
+    format: TiffFormat,
+    /// Element sizes of field types to be kept as opaque data.
+    sizes: FieldTypeSizes,
     /// Table of IFDs read.
     ifd_table: Box<[IFD]>,
 }
 
 impl MetadataReader {
     /// Create a new MetadataReader from `reader`, reading the entire IFD table from the file.
-    /// Assumes the cursor is positioned at a 32-bit pointer to the first valid IFD.
-    pub fn read_header<R: ReadBytesExt + Seek>(reader: &mut R) -> Fallible<Self> {
+    /// Assumes the cursor is positioned at the beginning of a TIFF file.
+    pub fn read_header<R: ReadBytesExt + Seek>(reader: &mut R) -> Result<Self> {
+        Self::read_header_with_sizes(reader, FieldTypeSizes::new())
+    }
+
+    /// Like `read_header`, but fields of types registered in `sizes` which have no `IFDField`
+    /// variant of their own are kept as `IFDField::Opaque` data so that they may be rewritten.
+    pub fn read_header_with_sizes<R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        sizes: FieldTypeSizes,
+    ) -> Result<Self> {
         let is_little_endian = read_header_endian(reader)?;
 
-        let ifd_table = if is_little_endian {
-            read_ifd_table_endian::<LittleEndian, R>(reader)?
+        let (format, ifd_table) = if is_little_endian {
+            read_ifd_table_endian::<LittleEndian, R>(reader, &sizes)?
         } else {
-            read_ifd_table_endian::<BigEndian, R>(reader)?
+            read_ifd_table_endian::<BigEndian, R>(reader, &sizes)?
         };
 
         Ok(Self {
             is_little_endian,
+            format,
+            sizes,
             ifd_table,
         })
     }
 
-    /// Read the IFD table starting at `offset` within the reader (For use with sub-IFDs for example).
+    /// Read the IFD table starting at `offset` within the reader, along with any child IFDs, for
+    /// IFDs which are not reachable through the file's own chain and pointer tags.
     pub fn read_external_ifd_table<R: ReadBytesExt + Seek>(
         &mut self,
         offset: u64,
         reader: &mut R,
-    ) -> Fallible<Box<[IFD]>> {
-        reader.seek(SeekFrom::Start(offset))?;
+    ) -> Result<Box<[IFD]>> {
         if self.is_little_endian {
-            read_ifd_table_endian::<LittleEndian, R>(reader)
+            read_ifds_at::<LittleEndian, R>(
+                reader,
+                offset,
+                self.format,
+                &self.sizes,
+                &mut Vec::new(),
+                0,
+            )
         } else {
-            read_ifd_table_endian::<BigEndian, R>(reader)
+            read_ifds_at::<BigEndian, R>(
+                reader,
+                offset,
+                self.format,
+                &self.sizes,
+                &mut Vec::new(),
+                0,
+            )
         }
     }
 
@@ -52,41 +85,181 @@ impl MetadataReader {
         self.is_little_endian
     }
 
-    /// Returns an iterator over references to this file's IFDs in the order they were read.
+    /// Returns whether the file is a classic TIFF or a BigTIFF.
+    pub fn format(&self) -> TiffFormat {
+        self.format
+    }
+
+    /// Returns an iterator over references to this file's IFDs in the order they were read. IFDs
+    /// pointed to by tags such as `SUB_IFDS` are reached through the `children` of their parent.
     pub fn ifds(&self) -> impl Iterator<Item = &IFD> {
         self.ifd_table.iter()
     }
 }
 
-/// Read all of the IFDs with the specified endian.
-/// Assumes the cursor is positioned at the beginning of a TIFF file.
+/// Tags whose values are offsets of further IFDs, which are read into `IFD::children`.
+const IFD_POINTER_TAGS: &[u16] = &[
+    tags::SUB_IFDS,
+    tags::EXIF_IFD,
+    tags::exif::INTEROPERABILITY_IFD,
+    tags::GPS_IFD,
+];
+
+/// Deepest nesting of IFDs within the children of other IFDs which is read, guarding against
+/// unbounded recursion through malformed files. Pointer tags of IFDs nested this deeply are not
+/// followed.
+pub const MAX_IFD_DEPTH: usize = 32;
+
+/// Read all of the IFDs with the specified endian, returning them along with the file's format.
+/// Assumes the cursor is positioned just after the endian marker of a TIFF file.
 pub fn read_ifd_table_endian<E: ByteOrder, R: ReadBytesExt + Seek>(
     reader: &mut R,
-) -> Fallible<Box<[IFD]>> {
-    read_header_magic::<E, _>(reader)?;
-    let raw_ifds = read_raw_ifds::<E, R>(reader)?;
+    sizes: &FieldTypeSizes,
+) -> Result<(TiffFormat, Box<[IFD]>)> {
+    let format = read_header_magic::<E, _>(reader)?;
+    let first_ifd_offset = format.read_offset::<E, _>(reader)?;
+    Ok((
+        format,
+        read_ifds_at::<E, R>(reader, first_ifd_offset, format, sizes, &mut Vec::new(), 0)?,
+    ))
+}
+
+/// Read the chain of IFDs beginning at `offset` into memory, along with the trees of IFDs their
+/// pointer tags lead to. Offsets in `pointers_encountered` are not read again, and any offsets
+/// read are added to it. `depth` is the number of pointer tags followed to reach the chain; the
+/// pointer tags of IFDs at `MAX_IFD_DEPTH` are not followed.
+///
+/// Only failures to read the chain itself are errors. Pointer tags which cannot be followed, or
+/// lead to chains which cannot be read, are skipped and stay in `entries` as they are.
+fn read_ifds_at<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    offset: u64,
+    format: TiffFormat,
+    sizes: &FieldTypeSizes,
+    pointers_encountered: &mut Vec<u64>,
+    depth: usize,
+) -> Result<Box<[IFD]>> {
+    let raw_ifds = read_raw_ifd_chain::<E, R>(reader, offset, format, pointers_encountered)?;
     let mut ifds = Vec::with_capacity(raw_ifds.len());
-    for raw_ifd in raw_ifds.iter() {
-        ifds.push(IFD::read_from::<E, _>(reader, &raw_ifd)?);
+    for (offset, raw_ifd) in raw_ifds {
+        let mut ifd =
+            IFD::read_from::<E, _>(reader, &raw_ifd, format, sizes).map_err(|e| Error::Ifd {
+                offset,
+                source: Box::new(e.into()),
+            })?;
+        if depth == MAX_IFD_DEPTH {
+            ifds.push(ifd);
+            continue;
+        }
+        for &tag in IFD_POINTER_TAGS {
+            let offsets = match ifd.entries.get(&tag) {
+                Some(field) => match ifd_offsets::<E, R>(reader, field, tag, format) {
+                    Ok(offsets) => offsets,
+                    Err(_) => continue,
+                },
+                None => continue,
+            };
+            let mut chains = Vec::with_capacity(offsets.len());
+            for offset in offsets {
+                let chain = read_ifds_at::<E, R>(
+                    reader,
+                    offset,
+                    format,
+                    sizes,
+                    pointers_encountered,
+                    depth + 1,
+                );
+                // Chains already read elsewhere come back empty
+                match chain {
+                    Ok(chain) if !chain.is_empty() => chains.push(chain),
+                    _ => {}
+                }
+            }
+            // Without any chains the pointer tag stays as it is, rather than being dropped when
+            // the IFD is written
+            if !chains.is_empty() {
+                ifd.children.insert(tag, chains);
+            }
+        }
+        ifds.push(ifd);
     }
     Ok(ifds.into_boxed_slice())
 }
 
-/// Read all IFDs from `reader` table into memory sequentially.
-/// Assumes the cursor is positioned at a 32-bit pointer to the first valid IFD.
+/// Read the IFD offsets held by `field`, the value of pointer tag `tag`. These may be stored as
+/// LONG or LONG8, or as the IFD type, which is unrecognized unless its size has been registered.
+fn ifd_offsets<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    field: &IFDField,
+    tag: u16,
+    format: TiffFormat,
+) -> Result<Vec<u64>> {
+    match field {
+        IFDField::Opaque {
+            tag_type: IFD_TYPE_IFD,
+            data,
+            ..
+        } => Ok(data
+            .chunks_exact(4)
+            .map(|offset| E::read_u32(offset).into())
+            .collect()),
+        // The IFD type is laid out like LONG, so it can be read as such
+        &IFDField::Unrecognized {
+            tag_type: IFD_TYPE_IFD,
+            count,
+            value_or_offset,
+        } => {
+            let raw_field = RawIFDField {
+                tag,
+                tag_type: IFD_TYPE_LONG,
+                count,
+                value_or_offset,
+            };
+            let field =
+                IFDField::read_from::<E, R>(reader, &raw_field, format, &FieldTypeSizes::new())?;
+            ifd_offsets::<E, R>(reader, &field, tag, format)
+        }
+        field => Ok(field
+            .try_into()
+            .map_err(|error: FieldConversionError| error.for_tag(tag))?),
+    }
+}
+
+/// Read all IFDs of the chain beginning at `first_ifd_offset` into memory sequentially.
 pub fn read_raw_ifds<E: ByteOrder, R: ReadBytesExt + Seek>(
     reader: &mut R,
-) -> Fallible<Box<[RawIFD]>> {
+    first_ifd_offset: u64,
+    format: TiffFormat,
+) -> Result<Box<[RawIFD]>> {
+    let ifds = read_raw_ifd_chain::<E, R>(reader, first_ifd_offset, format, &mut Vec::new())?;
+    Ok(ifds.into_iter().map(|(_, ifd)| ifd).collect())
+}
+
+/// Read the IFDs of the chain beginning at `first_ifd_offset` along with their offsets, stopping
+/// at any offset in `pointers_encountered` and adding the offsets read to it.
+fn read_raw_ifd_chain<E: ByteOrder, R: ReadBytesExt + Seek>(
+    reader: &mut R,
+    first_ifd_offset: u64,
+    format: TiffFormat,
+    pointers_encountered: &mut Vec<u64>,
+) -> Result<Vec<(u64, RawIFD)>> {
     let mut ifds = Vec::new();
-    let mut pointers_encountered = Vec::new(); // Break if a loop is found within the IFD pointers
-    loop {
-        let next_ifd_offset = reader.read_u32::<E>()?;
-        if next_ifd_offset == 0 || pointers_encountered.contains(&next_ifd_offset) {
-            break;
-        }
-        pointers_encountered.push(next_ifd_offset);
-        reader.seek(SeekFrom::Start(next_ifd_offset.into()))?;
-        ifds.push(RawIFD::read_from::<E, R>(reader)?);
+    let mut next_ifd_offset = first_ifd_offset;
+    // Break if a loop is found within the IFD pointers
+    while next_ifd_offset != 0 && !pointers_encountered.contains(&next_ifd_offset) {
+        let offset = next_ifd_offset;
+        pointers_encountered.push(offset);
+        let mut read_ifd = || -> Result<(RawIFD, u64)> {
+            reader.seek(SeekFrom::Start(offset))?;
+            let ifd = RawIFD::read_from::<E, R>(reader, format)?;
+            Ok((ifd, format.read_offset::<E, R>(reader)?))
+        };
+        let (ifd, next) = read_ifd().map_err(|source| Error::Ifd {
+            offset,
+            source: Box::new(source),
+        })?;
+        ifds.push((offset, ifd));
+        next_ifd_offset = next;
     }
-    Ok(ifds.into_boxed_slice())
+    Ok(ifds)
 }
@@ -1,6 +1,10 @@
-use crate::lowlevel::{header::write_header, ifd::IFD};
+use crate::errors::Result;
+use crate::lowlevel::{
+    header::{write_header_format, TiffFormat},
+    ifd::IFD,
+    ifd_field::IFDField,
+};
 use byteorder::{ByteOrder, WriteBytesExt};
-use failure::Fallible;
 use std::io::{Seek, SeekFrom};
 use std::marker::PhantomData;
 
@@ -8,58 +12,63 @@ use std::marker::PhantomData;
 pub struct MetadataWriter<E: ByteOrder> {
     /// File position of the last written IFD pointer
     last_ifd_pointer_position: u64,
+    /// Whether a classic TIFF or a BigTIFF is being written
+    format: TiffFormat,
     _phantomdata: PhantomData<E>,
 }
 
 impl<E: ByteOrder> MetadataWriter<E> {
-    /// Create a new MetadataWriter, writing a header to `writer`.
+    /// Create a new MetadataWriter, writing a classic TIFF header to `writer`.
     /// Note: Assumes the cursor is in a position ready for writing the new file.
-    pub fn write_header<W: WriteBytesExt + Seek>(writer: &mut W) -> Fallible<Self> {
+    pub fn write_header<W: WriteBytesExt + Seek>(writer: &mut W) -> Result<Self> {
+        Self::write_header_format(writer, TiffFormat::Classic)
+    }
+
+    /// Create a new MetadataWriter, writing a header for `format` to `writer`.
+    /// Note: Assumes the cursor is in a position ready for writing the new file.
+    pub fn write_header_format<W: WriteBytesExt + Seek>(
+        writer: &mut W,
+        format: TiffFormat,
+    ) -> Result<Self> {
         // Write the header
-        write_header::<E, _>(writer)?;
+        write_header_format::<E, _>(writer, format)?;
 
         // Write zero for the first IFD pointer, and remember where you were
-        let last_ifd_pointer_position = writer.seek(SeekFrom::Current(0))?;
-        writer.write_u32::<E>(0)?;
+        let last_ifd_pointer_position = writer.stream_position()?;
+        format.write_offset::<E, _>(writer, 0)?;
 
         Ok(Self {
             last_ifd_pointer_position,
+            format,
             _phantomdata: PhantomData,
         })
     }
 
+    /// Returns whether a classic TIFF or a BigTIFF is being written.
+    pub fn format(&self) -> TiffFormat {
+        self.format
+    }
+
     /// Write a single IFD (and its data) into `writer`. Note: the cursor shall be
     /// advanced to a position after the data and IFD, ready for another write. Returns the
     /// position within the file of the beginning of the IFD just written (Useful for sub-IFDs).
-    pub fn write_ifd<W: WriteBytesExt + Seek>(
-        &mut self,
-        ifd: &IFD,
-        writer: &mut W,
-    ) -> Fallible<u64> {
-        // Write out the ifd's long-form data
-        let raw_ifd = ifd.write_to::<E, _>(writer)?;
-
-        // Save the current cursor position as it will become the pointer to the next IFD
-        let ifd_table_position = writer.seek(SeekFrom::Current(0))?;
-
-        // Write the IFD describing the data into the file
-        raw_ifd.write_to::<E, _>(writer)?;
-
-        // Create a pointer to the 'next IFD' pointer
-        let next_ifd_table_pointer_position = writer.seek(SeekFrom::Current(0))?;
-
-        // Write zero to that pointer for now
-        writer.write_u32::<E>(0)?;
+    /// The chains of IFDs in `ifd.children` are written first, and the offsets of their first IFDs
+    /// replace the values of their pointer tags, such as `SUB_IFDS`.
+    /// Fails if any offset does not fit within the format being written.
+    pub fn write_ifd<W: WriteBytesExt + Seek>(&mut self, ifd: &IFD, writer: &mut W) -> Result<u64> {
+        let (ifd_table_position, next_ifd_table_pointer_position) =
+            self.write_detached_ifd(ifd, writer)?;
 
         // Save the position after the end of the table to restore it so this function seems to
         // write only the table and data sequentially
-        let position_after_table = writer.seek(SeekFrom::Current(0))?;
+        let position_after_table = writer.stream_position()?;
 
         // Seek to the last pointer
         let _ = writer.seek(SeekFrom::Start(self.last_ifd_pointer_position));
 
         // Write the position of the IFD we just wrote to it
-        writer.write_u32::<E>(ifd_table_position as u32)?;
+        self.format
+            .write_offset::<E, _>(writer, ifd_table_position)?;
 
         // Save the pointer to the 'next IFD' in our struct
         self.last_ifd_pointer_position = next_ifd_table_pointer_position;
@@ -69,4 +78,83 @@ impl<E: ByteOrder> MetadataWriter<E> {
 
         Ok(ifd_table_position)
     }
+
+    /// Write `ifd`, its data and its children into `writer` without linking it into the file's
+    /// chain of IFDs. Returns the position of the IFD and of its zeroed 'next IFD' pointer.
+    fn write_detached_ifd<W: WriteBytesExt + Seek>(
+        &self,
+        ifd: &IFD,
+        writer: &mut W,
+    ) -> Result<(u64, u64)> {
+        // Children come first, so that their offsets are known when the parent is written
+        let mut patched;
+        let ifd = if ifd.children.is_empty() {
+            ifd
+        } else {
+            patched = ifd.clone();
+            for (&tag, chains) in &ifd.children {
+                let mut offsets = Vec::with_capacity(chains.len());
+                for chain in chains.iter().filter(|chain| !chain.is_empty()) {
+                    offsets.push(self.write_ifd_chain(chain, writer)?);
+                }
+                if offsets.is_empty() {
+                    patched.entries.remove(&tag);
+                } else {
+                    patched.entries.insert(tag, self.ifd_offsets_field(offsets));
+                }
+            }
+            &patched
+        };
+
+        // Write out the ifd's long-form data
+        let raw_ifd = ifd.write_to::<E, _>(writer, self.format)?;
+
+        // Save the current cursor position as it will become the pointer to the next IFD
+        let ifd_table_position = writer.stream_position()?;
+
+        // Write the IFD describing the data into the file
+        raw_ifd.write_to::<E, _>(writer, self.format)?;
+
+        // Create a pointer to the 'next IFD' pointer
+        let next_ifd_table_pointer_position = writer.stream_position()?;
+
+        // Write zero to that pointer for now
+        self.format.write_offset::<E, _>(writer, 0)?;
+
+        Ok((ifd_table_position, next_ifd_table_pointer_position))
+    }
+
+    /// Write the IFDs of `chain`, each pointing to the next, returning the position of the first.
+    fn write_ifd_chain<W: WriteBytesExt + Seek>(
+        &self,
+        chain: &[IFD],
+        writer: &mut W,
+    ) -> Result<u64> {
+        let mut first_ifd_position = 0;
+        let mut last_pointer_position = None;
+        for ifd in chain {
+            let (ifd_table_position, next_pointer_position) =
+                self.write_detached_ifd(ifd, writer)?;
+            match last_pointer_position {
+                Some(last_pointer_position) => {
+                    let position_after_table = writer.stream_position()?;
+                    writer.seek(SeekFrom::Start(last_pointer_position))?;
+                    self.format
+                        .write_offset::<E, _>(writer, ifd_table_position)?;
+                    writer.seek(SeekFrom::Start(position_after_table))?;
+                }
+                None => first_ifd_position = ifd_table_position,
+            }
+            last_pointer_position = Some(next_pointer_position);
+        }
+        Ok(first_ifd_position)
+    }
+
+    /// A field holding IFD offsets, as LONG in classic TIFFs or IFD8 in BigTIFFs.
+    fn ifd_offsets_field(&self, offsets: Vec<u64>) -> IFDField {
+        match self.format {
+            TiffFormat::Classic => IFDField::offsets(offsets),
+            TiffFormat::BigTiff => IFDField::Ifd8(offsets.into()),
+        }
+    }
 }
@@ -1,6 +1,5 @@
 /// Conversions between some primitive types and IFDFieldData
 pub(crate) mod field_conversions;
-pub use field_conversions::*;
 
 /// TIFF metadata reader
 pub(crate) mod metadata_reader;
@@ -1,5 +1,6 @@
+use crate::lowlevel::header::TiffFormat;
 use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt};
-use std::io::Error;
+use std::io::{Error, ErrorKind, Seek, SeekFrom};
 
 /// A struct representing a low-level IFD value.
 #[derive(Debug, Clone, Copy)]
@@ -11,23 +12,26 @@ pub struct RawIFDField {
     pub tag_type: u16,
 
     /// Quantity (not byte count) of data in the field.
-    pub count: u32,
+    pub count: u64,
 
     /// Field representing either the value of the tag (if it is small enough)
-    /// or the file offset of the tag's data.
-    pub value_or_offset: [u8; 4],
+    /// or the file offset of the tag's data. Classic TIFFs only use the first four bytes.
+    pub value_or_offset: [u8; 8],
 }
 
 impl RawIFDField {
     /// Read the field value from `reader`.
-    pub fn read_from<E: ByteOrder, R: ReadBytesExt>(reader: &mut R) -> Result<Self, Error> {
+    pub fn read_from<E: ByteOrder, R: ReadBytesExt>(
+        reader: &mut R,
+        format: TiffFormat,
+    ) -> Result<Self, Error> {
         Ok(Self {
             tag: reader.read_u16::<E>()?,
             tag_type: reader.read_u16::<E>()?,
-            count: reader.read_u32::<E>()?,
+            count: format.read_offset::<E, R>(reader)?,
             value_or_offset: {
-                let mut buffer = [0; 4];
-                reader.read_exact(&mut buffer)?;
+                let mut buffer = [0; 8];
+                reader.read_exact(&mut buffer[..format.offset_size()])?;
                 buffer
             },
         })
@@ -37,11 +41,12 @@ impl RawIFDField {
     pub fn write_to<E: ByteOrder, W: WriteBytesExt>(
         &self,
         writer: &mut W,
+        format: TiffFormat,
     ) -> Result<(), std::io::Error> {
         writer.write_u16::<E>(self.tag)?;
         writer.write_u16::<E>(self.tag_type)?;
-        writer.write_u32::<E>(self.count)?;
-        writer.write_all(&self.value_or_offset)
+        format.write_offset::<E, W>(writer, self.count)?;
+        writer.write_all(&self.value_or_offset[..format.offset_size()])
     }
 }
 
@@ -53,29 +58,63 @@ pub struct RawIFD {
 
 impl RawIFD {
     /// Read an entire IFD from `reader` excluding the offset to the next IFD.
-    pub fn read_from<E: ByteOrder, R: ReadBytesExt>(reader: &mut R) -> Result<Self, Error> {
+    pub fn read_from<E: ByteOrder, R: ReadBytesExt + Seek>(
+        reader: &mut R,
+        format: TiffFormat,
+    ) -> Result<Self, Error> {
         // Read length header
-        let field_count = reader.read_u16::<E>()? as usize;
+        let field_count = match format {
+            TiffFormat::Classic => reader.read_u16::<E>()?.into(),
+            TiffFormat::BigTiff => reader.read_u64::<E>()?,
+        };
+        let entry_size = 4 + 2 * format.offset_size() as u64;
+        ensure_available(reader, field_count.saturating_mul(entry_size))?;
 
         // Read entries
-        let mut entries = Vec::with_capacity(field_count);
+        let mut entries = Vec::with_capacity(field_count as usize);
         for _ in 0..field_count {
-            entries.push(RawIFDField::read_from::<E, R>(reader)?);
+            entries.push(RawIFDField::read_from::<E, R>(reader, format)?);
         }
         Ok(Self { entries })
     }
 
     /// Write an entire IFD to `writer` excluding the offset to the next IFD.
-    pub fn write_to<E: ByteOrder, W: WriteBytesExt>(&self, writer: &mut W) -> Result<(), Error> {
-        assert!(self.entries.len() < std::u16::MAX as usize);
-
+    pub fn write_to<E: ByteOrder, W: WriteBytesExt>(
+        &self,
+        writer: &mut W,
+        format: TiffFormat,
+    ) -> Result<(), Error> {
         // Write length header
-        writer.write_u16::<E>(self.entries.len() as u16)?;
+        match format {
+            TiffFormat::Classic => {
+                assert!(self.entries.len() < u16::MAX as usize);
+                writer.write_u16::<E>(self.entries.len() as u16)?;
+            }
+            TiffFormat::BigTiff => writer.write_u64::<E>(self.entries.len() as u64)?,
+        }
 
         // Write entries
         for field in &self.entries {
-            field.write_to::<E, W>(writer)?;
+            field.write_to::<E, W>(writer, format)?;
         }
         Ok(())
     }
 }
+
+/// Fail unless `reader` holds at least `byte_count` more bytes. Counts read from a file are
+/// checked with this before allocating, so that a malformed file cannot request huge buffers.
+pub(crate) fn ensure_available<R: Seek>(reader: &mut R, byte_count: u64) -> Result<(), Error> {
+    let position = reader.stream_position()?;
+    let end = reader.seek(SeekFrom::End(0))?;
+    reader.seek(SeekFrom::Start(position))?;
+    if end.saturating_sub(position) < byte_count {
+        return Err(Error::new(
+            ErrorKind::UnexpectedEof,
+            format!(
+                "{} bytes requested at offset {}, past the end of the file",
+                byte_count, position
+            ),
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use byteorder::{BigEndian, LittleEndian};
+use common::round_trip;
+use std::io::Cursor;
+use tiffy::baseline::tags;
+use tiffy::lowlevel::{FieldTypeSizes, IFDField, MetadataReader, MetadataWriter, TiffFormat, IFD};
+
+/// An IFD holding fields of every type valid in both classic TIFFs and BigTIFFs.
+fn common_ifd() -> IFD {
+    let mut ifd = IFD::new();
+    ifd.entries
+        .insert(tags::IMAGE_WIDTH, IFDField::Long(Box::new([3000])));
+    ifd.entries
+        .insert(tags::BITS_PER_SAMPLE, IFDField::Short(Box::new([8, 8, 8])));
+    ifd.entries.insert(
+        tags::SOFTWARE,
+        IFDField::Ascii(Box::new(["tiffy".to_string()])),
+    );
+    ifd.entries
+        .insert(tags::X_RESOLUTION, IFDField::Rational(Box::new([(300, 1)])));
+    ifd
+}
+
+#[test]
+fn converts_between_classic_and_bigtiff() {
+    let ifds = [common_ifd(), common_ifd()];
+    let classic = round_trip::<LittleEndian>(&ifds, TiffFormat::Classic, FieldTypeSizes::new());
+    assert_eq!(classic.format(), TiffFormat::Classic);
+    let classic: Vec<IFD> = classic.ifds().cloned().collect();
+    let big = round_trip::<BigEndian>(&classic, TiffFormat::BigTiff, FieldTypeSizes::new());
+    assert_eq!(big.format(), TiffFormat::BigTiff);
+    let big: Vec<IFD> = big.ifds().cloned().collect();
+    let classic_again =
+        round_trip::<LittleEndian>(&big, TiffFormat::Classic, FieldTypeSizes::new());
+    assert_eq!(classic_again.format(), TiffFormat::Classic);
+    let classic_again: Vec<IFD> = classic_again.ifds().cloned().collect();
+    for read in &[classic, big, classic_again] {
+        assert_eq!(read.len(), 2);
+        for ifd in read {
+            assert_eq!(ifd.entries, common_ifd().entries);
+        }
+    }
+}
+
+#[test]
+fn round_trips_bigtiff_only_types() {
+    let mut ifd = common_ifd();
+    ifd.entries.insert(
+        tags::STRIP_OFFSETS,
+        IFDField::Long8(Box::new([0x1_0000_0000, 8])),
+    );
+    ifd.entries
+        .insert(tags::STRIP_BYTE_COUNTS, IFDField::Long8(Box::new([16])));
+    ifd.entries
+        .insert(65000, IFDField::SLong8(Box::new([i64::MIN, -1, 7])));
+    ifd.entries
+        .insert(65001, IFDField::Ifd8(Box::new([0xDEAD_BEEF_0000])));
+    let ifds = [ifd.clone()];
+    for reader in &[
+        round_trip::<LittleEndian>(&ifds, TiffFormat::BigTiff, FieldTypeSizes::new()),
+        round_trip::<BigEndian>(&ifds, TiffFormat::BigTiff, FieldTypeSizes::new()),
+    ] {
+        let read = reader.ifds().next().unwrap();
+        assert_eq!(read.entries, ifd.entries);
+        assert_eq!(
+            read.get::<Vec<u64>>(tags::STRIP_OFFSETS).unwrap(),
+            [0x1_0000_0000, 8]
+        );
+    }
+
+    // LONG8 and friends cannot be stored in a classic TIFF
+    let mut file = Cursor::new(Vec::new());
+    let mut metadata_writer = MetadataWriter::<LittleEndian>::write_header(&mut file).unwrap();
+    assert!(metadata_writer.write_ifd(&ifd, &mut file).is_err());
+}
+
+#[test]
+fn rejects_counts_larger_than_the_file() {
+    // A BigTIFF whose first IFD claims 2^48 entries
+    let mut file = b"II\x2b\x00\x08\x00\x00\x00\x10\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    file.extend_from_slice(&(1u64 << 48).to_le_bytes());
+    file.extend_from_slice(&[0; 8]);
+    assert!(MetadataReader::read_header(&mut Cursor::new(file)).is_err());
+
+    // A single LONG8 field claiming 2^40 values stored at offset 0
+    let mut file = b"II\x2b\x00\x08\x00\x00\x00\x10\x00\x00\x00\x00\x00\x00\x00".to_vec();
+    file.extend_from_slice(&1u64.to_le_bytes());
+    file.extend_from_slice(&tags::STRIP_OFFSETS.to_le_bytes());
+    file.extend_from_slice(&16u16.to_le_bytes());
+    file.extend_from_slice(&(1u64 << 40).to_le_bytes());
+    file.extend_from_slice(&0u64.to_le_bytes());
+    file.extend_from_slice(&0u64.to_le_bytes());
+    assert!(MetadataReader::read_header(&mut Cursor::new(file)).is_err());
+}
@@ -0,0 +1,176 @@
+mod common;
+
+use byteorder::LittleEndian;
+use common::write_and_read;
+use tiffy::baseline::constants::{compression, photometic_interpretation, t4_options};
+use tiffy::compression::{codec_for, BlockDimensions, Codec, Fax};
+use tiffy::image::{Image, ImageDecoder, ImageEncoder, PixelBuffer};
+use tiffy::lowlevel::{FieldTypeSizes, TiffFormat};
+
+/// Pack a string of '0' and '1' characters MSB-first, ignoring spaces and padding with zeros.
+fn pack_bits(bits: &str) -> Vec<u8> {
+    let bits: Vec<u8> = bits
+        .bytes()
+        .filter(|&b| b != b' ')
+        .map(|b| b - b'0')
+        .collect();
+    bits.chunks(8)
+        .map(|byte| {
+            byte.iter()
+                .enumerate()
+                .fold(0u8, |acc, (i, &bit)| acc | (bit << (7 - i)))
+        })
+        .collect()
+}
+
+/// Rows of 8 pixels, one byte each.
+fn block(height: u32) -> BlockDimensions {
+    BlockDimensions {
+        width: 8,
+        height,
+        row_bytes: 1,
+    }
+}
+
+/// Deterministic bilevel rows of `width` pixels, with runs of widely varying lengths.
+fn test_rows(width: u32, height: u32) -> Vec<u8> {
+    let row_bytes = (width as usize).div_ceil(8);
+    let mut data = vec![0; row_bytes * height as usize];
+    let mut seed = 0x1234_5678u32;
+    for row in data.chunks_mut(row_bytes) {
+        let mut x = 0;
+        let mut black = false;
+        while x < width {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let run = match seed >> 28 {
+                0 => (seed >> 8) % 4000,
+                1..=4 => (seed >> 8) % 100,
+                _ => (seed >> 8) % 6,
+            };
+            for x in x..(x + run).min(width) {
+                if black {
+                    row[x as usize / 8] |= 0x80 >> (x % 8);
+                }
+            }
+            x += run;
+            black = !black;
+        }
+    }
+    data
+}
+
+#[test]
+fn decodes_modified_huffman_fixture() {
+    // White 4, black 4 | white 0, black 8, each row padded to a byte
+    let stream = pack_bits("1011 011 0  00110101 000101 00");
+    let codec = codec_for(compression::CCITT_1D).unwrap();
+    assert_eq!(codec.decompress(&stream, &block(2)).unwrap(), [0x0F, 0xFF]);
+    assert_eq!(codec.compress(&[0x0F, 0xFF], &block(2)).unwrap(), stream);
+}
+
+#[test]
+fn decodes_group_3_fixture_with_fill_bits() {
+    // Each EOL is padded to end on a byte boundary
+    let stream = pack_bits("0000 000000000001 1011 011 00000 000000000001 10011");
+    let codec = Fax::new(compression::GROUP_3_FAX, t4_options::FILL_BITS).unwrap();
+    assert_eq!(codec.decompress(&stream, &block(2)).unwrap(), [0x0F, 0x00]);
+    assert_eq!(codec.compress(&[0x0F, 0x00], &block(2)).unwrap(), stream);
+}
+
+#[test]
+fn decodes_group_3_two_dimensional_fixture() {
+    // A one-dimensional row of white 4, black 4, then a two-dimensional row of VL1 VL1 V0
+    let stream = pack_bits("000000000001 1 1011 011 000000000001 0 010 010 1");
+    let codec = Fax::new(compression::GROUP_3_FAX, t4_options::TWO_DIMENSIONAL).unwrap();
+    assert_eq!(codec.decompress(&stream, &block(2)).unwrap(), [0x0F, 0x1E]);
+    assert_eq!(codec.compress(&[0x0F, 0x1E], &block(2)).unwrap(), stream);
+}
+
+#[test]
+fn decodes_group_4_fixture() {
+    // Horizontal white 4, black 4, then VL1 VL1 V0 and the end of facsimile block
+    let stream = pack_bits("001 1011 011 010 010 1 000000000001 000000000001");
+    let codec = codec_for(compression::GROUP_4_FAX).unwrap();
+    assert_eq!(codec.decompress(&stream, &block(2)).unwrap(), [0x0F, 0x1E]);
+    assert_eq!(codec.compress(&[0x0F, 0x1E], &block(2)).unwrap(), stream);
+}
+
+#[test]
+fn rejects_invalid_codes() {
+    let codec = codec_for(compression::GROUP_4_FAX).unwrap();
+    // VR3 from an imaginary white row points beyond the end of the row
+    assert!(codec.decompress(&pack_bits("0000011"), &block(1)).is_err());
+    // Runs of white 5 then black 4 overflow the row
+    let codec = codec_for(compression::CCITT_1D).unwrap();
+    assert!(codec.decompress(&pack_bits("1100 011"), &block(1)).is_err());
+    assert!(Fax::new(compression::GROUP_4_FAX, t4_options::TWO_DIMENSIONAL).is_err());
+}
+
+#[test]
+fn round_trips_every_scheme() {
+    let codecs = [
+        Fax::new(compression::CCITT_1D, 0).unwrap(),
+        Fax::new(compression::GROUP_3_FAX, 0).unwrap(),
+        Fax::new(compression::GROUP_3_FAX, t4_options::FILL_BITS).unwrap(),
+        Fax::new(
+            compression::GROUP_3_FAX,
+            t4_options::TWO_DIMENSIONAL | t4_options::FILL_BITS,
+        )
+        .unwrap(),
+        Fax::new(compression::GROUP_4_FAX, 0).unwrap(),
+    ];
+    for codec in &codecs {
+        for &(width, height) in &[(1, 3), (13, 7), (1728, 20), (6000, 9)] {
+            let data = test_rows(width, height);
+            let block = BlockDimensions {
+                width,
+                height,
+                row_bytes: (width as usize).div_ceil(8),
+            };
+            let compressed = codec.compress(&data, &block).unwrap();
+            assert_eq!(
+                codec.decompress(&compressed, &block).unwrap(),
+                data,
+                "{:?} {}x{}",
+                codec,
+                width,
+                height
+            );
+        }
+    }
+}
+
+#[test]
+fn round_trips_bilevel_images() {
+    let (width, height) = (300, 70);
+    let image = Image {
+        width,
+        height,
+        samples_per_pixel: 1,
+        photometric: photometic_interpretation::WHITEISZERO,
+        planar_configuration: 1,
+        data: tiffy::image::unpack_samples(&test_rows(width, height), 1, width as usize).unwrap(),
+    };
+    assert!(matches!(image.data, PixelBuffer::U8(_)));
+    for &scheme in &[
+        compression::CCITT_1D,
+        compression::GROUP_3_FAX,
+        compression::GROUP_4_FAX,
+    ] {
+        let encoder = ImageEncoder::new()
+            .with_compression(scheme)
+            .unwrap()
+            .with_bits_per_sample(1)
+            .with_strip_bytes(400)
+            .with_fill_order(2);
+        let (mut file, reader) = write_and_read::<LittleEndian>(
+            TiffFormat::Classic,
+            FieldTypeSizes::new(),
+            |metadata_writer, file| {
+                encoder.write_image(&image, metadata_writer, file).unwrap();
+            },
+        );
+        let decoder = ImageDecoder::new(reader.ifds().next().unwrap(), true).unwrap();
+        assert_eq!(decoder.decode(&mut file).unwrap(), image);
+    }
+}
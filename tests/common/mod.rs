@@ -0,0 +1,37 @@
+//! Helpers shared by the integration tests. Each test crate uses only some of them.
+#![allow(dead_code)]
+
+use byteorder::ByteOrder;
+use std::io::Cursor;
+use tiffy::lowlevel::{FieldTypeSizes, MetadataReader, MetadataWriter, TiffFormat, IFD};
+
+/// Start a new file in `format` and byte order `E`, let `write` add image data and IFDs to it,
+/// then read it back with `sizes` registered. Returns the file along with its metadata.
+pub fn write_and_read<E: ByteOrder>(
+    format: TiffFormat,
+    sizes: FieldTypeSizes,
+    write: impl FnOnce(&mut MetadataWriter<E>, &mut Cursor<Vec<u8>>),
+) -> (Cursor<Vec<u8>>, MetadataReader) {
+    let mut file = Cursor::new(Vec::new());
+    let mut metadata_writer = MetadataWriter::<E>::write_header_format(&mut file, format).unwrap();
+    write(&mut metadata_writer, &mut file);
+
+    let mut file = Cursor::new(file.into_inner());
+    let reader = MetadataReader::read_header_with_sizes(&mut file, sizes).unwrap();
+    (file, reader)
+}
+
+/// Write `ifds` as a chain in `format` and byte order `E`, and read them back with `sizes`
+/// registered.
+pub fn round_trip<E: ByteOrder>(
+    ifds: &[IFD],
+    format: TiffFormat,
+    sizes: FieldTypeSizes,
+) -> MetadataReader {
+    let (_, reader) = write_and_read::<E>(format, sizes, |metadata_writer, file| {
+        for ifd in ifds {
+            metadata_writer.write_ifd(ifd, file).unwrap();
+        }
+    });
+    reader
+}
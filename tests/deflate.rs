@@ -0,0 +1,149 @@
+mod common;
+
+use byteorder::LittleEndian;
+use common::write_and_read;
+use tiffy::baseline::{
+    constants::{compression, photometic_interpretation, predictor},
+    tags,
+};
+use tiffy::compression::{codec_for, BlockDimensions, Codec, Deflate};
+use tiffy::image::{Image, ImageDecoder, ImageEncoder, PixelBuffer};
+use tiffy::lowlevel::{FieldTypeSizes, TiffFormat};
+
+/// A block of `height` rows of `row_bytes` bytes each.
+fn block(row_bytes: usize, height: u32) -> BlockDimensions {
+    BlockDimensions {
+        width: row_bytes as u32,
+        height,
+        row_bytes,
+    }
+}
+
+/// Rows of smooth gradients with some noise, filling `block`.
+fn test_data(block: &BlockDimensions) -> Vec<u8> {
+    let mut seed = 0x1234_5678u32;
+    (0..block.byte_count().unwrap())
+        .map(|i| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (i % block.row_bytes + i / block.row_bytes + (seed >> 28) as usize) as u8
+        })
+        .collect()
+}
+
+#[test]
+fn compresses_blocks_at_several_levels() {
+    let block = block(201, 45);
+    let data = test_data(&block);
+    let mut sizes = Vec::new();
+    for &level in &[0, 1, 6, 9] {
+        let codec = Deflate::with_level(level);
+        assert_eq!(codec.level(), level);
+        let compressed = codec.compress(&data, &block).unwrap();
+        assert_eq!(codec.decompress(&compressed, &block).unwrap(), data);
+        sizes.push(compressed.len());
+    }
+    // Storing without compression is larger than the data, and higher levels do no worse here
+    assert!(sizes[0] > data.len());
+    assert!(sizes[1] < sizes[0]);
+    assert!(sizes[3] <= sizes[2] && sizes[2] <= sizes[1]);
+
+    // Levels beyond 9 are clamped
+    assert_eq!(Deflate::with_level(20).level(), 9);
+}
+
+#[test]
+fn decompresses_both_compression_values() {
+    let block = block(40, 33);
+    let data = test_data(&block);
+    let compressed = Deflate::default().compress(&data, &block).unwrap();
+    for &value in &[compression::ADOBE_DEFLATE, compression::DEFLATE_PKZIP] {
+        let codec = codec_for(value).unwrap();
+        assert_eq!(codec.decompress(&compressed, &block).unwrap(), data);
+    }
+}
+
+/// An RGB image of `width` by `height` pixels with smooth gradients and some noise.
+fn test_image(width: u32, height: u32) -> Image {
+    let mut seed = 0x1234_5678u32;
+    Image {
+        width,
+        height,
+        samples_per_pixel: 3,
+        photometric: photometic_interpretation::RGB,
+        planar_configuration: 1,
+        data: PixelBuffer::U8(
+            (0..width * height * 3)
+                .map(|i| {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                    (i / 3 % width + i / 3 / width + (seed >> 28)) as u8
+                })
+                .collect(),
+        ),
+    }
+}
+
+/// Encode `image` with `encoder` into a new file and decode it again, returning the image along
+/// with its `COMPRESSION` tag and the total size of its compressed strips.
+fn round_trip(encoder: &ImageEncoder, image: &Image) -> (Image, u16, u64) {
+    let (mut file, reader) = write_and_read::<LittleEndian>(
+        TiffFormat::Classic,
+        FieldTypeSizes::new(),
+        |metadata_writer, file| {
+            let ifd = encoder.encode::<LittleEndian, _>(image, file).unwrap();
+            metadata_writer.write_ifd(&ifd, file).unwrap();
+        },
+    );
+    let ifd = reader.ifds().next().unwrap();
+    let decoded = ImageDecoder::new(ifd, false)
+        .unwrap()
+        .decode(&mut file)
+        .unwrap();
+    let compressed_size = ifd
+        .get::<Vec<u64>>(tags::STRIP_BYTE_COUNTS)
+        .unwrap()
+        .iter()
+        .sum();
+    (
+        decoded,
+        ifd.get(tags::COMPRESSION).unwrap(),
+        compressed_size,
+    )
+}
+
+#[test]
+fn round_trips_at_several_levels() {
+    let image = test_image(67, 45);
+    let mut sizes = Vec::new();
+    for &level in &[0, 1, 6, 9] {
+        let encoder = ImageEncoder::new()
+            .with_strip_bytes(1024)
+            .with_deflate_level(level);
+        let (decoded, compression, size) = round_trip(&encoder, &image);
+        assert_eq!(decoded, image);
+        assert_eq!(compression, compression::ADOBE_DEFLATE);
+        sizes.push(size);
+    }
+    // Storing without compression is larger than the image, and higher levels do no worse here
+    assert!(sizes[0] > 67 * 45 * 3);
+    assert!(sizes[1] < sizes[0]);
+    assert!(sizes[3] <= sizes[2] && sizes[2] <= sizes[1]);
+}
+
+#[test]
+fn decodes_both_deflate_compression_values() {
+    let image = test_image(40, 33);
+    for &value in &[compression::ADOBE_DEFLATE, compression::DEFLATE_PKZIP] {
+        let encoder = ImageEncoder::new()
+            .with_compression(value)
+            .unwrap()
+            .with_predictor(predictor::HORIZONTAL_DIFFERENCING);
+        let (decoded, compression, _) = round_trip(&encoder, &image);
+        assert_eq!(decoded, image);
+        assert_eq!(compression, value);
+
+        // Setting the level keeps the chosen compression value
+        let (decoded, compression, _) = round_trip(&encoder.with_deflate_level(9), &image);
+        assert_eq!(decoded, image);
+        assert_eq!(compression, value);
+    }
+}
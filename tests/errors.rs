@@ -0,0 +1,36 @@
+use std::error::Error as _;
+use std::io::Cursor;
+use tiffy::baseline::tags;
+use tiffy::errors::FieldExtractionError;
+use tiffy::lowlevel::{IFDField, MetadataReader, IFD};
+use tiffy::Error;
+
+#[test]
+fn reports_the_location_of_errors() {
+    // A little-endian header pointing to an IFD claiming more entries than the file holds
+    let mut file = Cursor::new(b"II\x2a\x00\x08\x00\x00\x00\x05\x00\x00\x01".to_vec());
+    let error = match MetadataReader::read_header(&mut file) {
+        Err(error) => error,
+        Ok(_) => panic!("expected a truncated IFD to fail"),
+    };
+    assert!(matches!(error, Error::Ifd { offset: 8, .. }));
+    assert_eq!(error.to_string(), "Failed to read the IFD at offset 8");
+    let source = error.source().unwrap();
+    assert_eq!(
+        source.to_string(),
+        "60 bytes requested at offset 10, past the end of the file"
+    );
+
+    // Field errors carry the tag they concern
+    let mut ifd = IFD::new();
+    ifd.entries
+        .insert(tags::IMAGE_LENGTH, IFDField::Ascii(Box::new([])));
+    assert_eq!(
+        ifd.get::<u32>(tags::IMAGE_LENGTH),
+        Err(FieldExtractionError::WrongDataType {
+            tag: tags::IMAGE_LENGTH
+        })
+    );
+    let error: Error = ifd.get::<u32>(tags::COMPRESSION).unwrap_err().into();
+    assert_eq!(error.to_string(), "Missing tag 0x103");
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use byteorder::{BigEndian, LittleEndian};
+use common::{round_trip, write_and_read};
+use tiffy::baseline::tags::{self, exif};
+use tiffy::lowlevel::{FieldTypeSizes, IFDField, TiffFormat, IFD};
+
+/// An EXIF IFD with a few common tags and an Interoperability IFD.
+fn exif_ifd() -> IFD {
+    let mut interoperability = IFD::new();
+    interoperability.entries.insert(
+        exif::INTEROPERABILITY_INDEX,
+        IFDField::Ascii(Box::new(["R98".to_string()])),
+    );
+    interoperability.entries.insert(
+        exif::INTEROPERABILITY_VERSION,
+        IFDField::Undefined(Box::new(*b"0100")),
+    );
+
+    let mut exif_ifd = IFD::new();
+    exif_ifd
+        .entries
+        .insert(exif::EXIF_VERSION, IFDField::Undefined(Box::new(*b"0232")));
+    exif_ifd.entries.insert(
+        exif::EXPOSURE_TIME,
+        IFDField::Rational(Box::new([(1, 250)])),
+    );
+    exif_ifd
+        .entries
+        .insert(exif::F_NUMBER, IFDField::Rational(Box::new([(28, 10)])));
+    exif_ifd.entries.insert(
+        exif::PHOTOGRAPHIC_SENSITIVITY,
+        IFDField::Short(Box::new([400])),
+    );
+    exif_ifd.entries.insert(
+        exif::DATE_TIME_ORIGINAL,
+        IFDField::Ascii(Box::new(["2020:02:29 12:34:56".to_string()])),
+    );
+    exif_ifd.set_interoperability(interoperability);
+    exif_ifd
+}
+
+#[test]
+fn round_trips_exif_and_interoperability_ifds() {
+    for &format in &[TiffFormat::Classic, TiffFormat::BigTiff] {
+        let mut main = IFD::new();
+        main.entries
+            .insert(tags::IMAGE_WIDTH, IFDField::Long(Box::new([1])));
+        main.set_exif(exif_ifd());
+
+        let reader = round_trip::<LittleEndian>(&[main, IFD::new()], format, FieldTypeSizes::new());
+        // The EXIF IFD is not part of the main chain of IFDs
+        assert_eq!(reader.ifds().count(), 2);
+        let read_main = reader.ifds().next().unwrap();
+        let offsets: Vec<u64> = read_main.get(tags::EXIF_IFD).unwrap();
+        assert_eq!(offsets.len(), 1);
+
+        let read_exif = read_main.exif().unwrap();
+        let expected = exif_ifd();
+        for tag in expected.entries.keys() {
+            assert_eq!(read_exif.entries.get(tag), expected.entries.get(tag));
+        }
+        assert_eq!(
+            read_exif.get::<(u32, u32)>(exif::EXPOSURE_TIME).unwrap(),
+            (1, 250)
+        );
+        assert_eq!(read_exif.interoperability(), expected.interoperability());
+        assert!(read_main.interoperability().is_none());
+    }
+}
+
+#[test]
+fn replaces_stale_exif_pointers() {
+    // A pointer left over from another file is replaced by the offset of the EXIF IFD written
+    let mut main = IFD::new();
+    main.entries
+        .insert(tags::EXIF_IFD, IFDField::Long(Box::new([0xDEAD])));
+    main.set_exif(exif_ifd());
+
+    let mut main_offset = 0;
+    let (_, reader) = write_and_read::<BigEndian>(
+        TiffFormat::Classic,
+        FieldTypeSizes::new(),
+        |metadata_writer, file| main_offset = metadata_writer.write_ifd(&main, file).unwrap(),
+    );
+    let read_main = reader.ifds().next().unwrap();
+    let exif_offset = read_main.get::<u32>(tags::EXIF_IFD).unwrap();
+    assert!(u64::from(exif_offset) < main_offset);
+    assert_eq!(
+        read_main
+            .exif()
+            .unwrap()
+            .get::<u16>(exif::PHOTOGRAPHIC_SENSITIVITY)
+            .unwrap(),
+        400
+    );
+}
+
+#[test]
+fn reads_ifds_with_dangling_exif_pointers() {
+    let mut main = IFD::new();
+    main.entries
+        .insert(tags::IMAGE_WIDTH, IFDField::Long(Box::new([1])));
+    main.entries
+        .insert(tags::EXIF_IFD, IFDField::Long(Box::new([0xDEAD])));
+
+    // The EXIF IFD lies past the end of the file, but the IFD pointing to it is still read
+    let reader = round_trip::<LittleEndian>(&[main], TiffFormat::Classic, FieldTypeSizes::new());
+    let read_main = reader.ifds().next().unwrap();
+    assert!(read_main.exif().is_none());
+    assert_eq!(read_main.get::<u32>(tags::IMAGE_WIDTH).unwrap(), 1);
+    assert_eq!(read_main.get::<u32>(tags::EXIF_IFD).unwrap(), 0xDEAD);
+}
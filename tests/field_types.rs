@@ -0,0 +1,97 @@
+mod common;
+
+use byteorder::{BigEndian, LittleEndian};
+use common::round_trip;
+use std::io::Cursor;
+use std::slice;
+use tiffy::lowlevel::{FieldTypeSizes, IFDField, MetadataWriter, TiffFormat, IFD};
+
+/// An IFD holding every signed and floating point field type, both small enough to be stored
+/// within their entry and large enough to need an offset.
+fn signed_and_float_ifd() -> IFD {
+    let mut ifd = IFD::new();
+    let fields = vec![
+        IFDField::SByte(Box::new([-128, 127])),
+        IFDField::SByte(Box::new([-1, 0, 1, -2, 2, -3, 3])),
+        IFDField::SShort(Box::new([-32768])),
+        IFDField::SShort(Box::new([-1, 2, -3, 32767])),
+        IFDField::SLong(Box::new([i32::MIN])),
+        IFDField::SLong(Box::new([-7, i32::MAX, 0])),
+        IFDField::SRational(Box::new([(-1, 3)])),
+        IFDField::SRational(Box::new([(i32::MIN, 7), (5, -2)])),
+        IFDField::Float(Box::new([-0.5])),
+        IFDField::Float(Box::new([1.5, f32::MAX, f32::MIN_POSITIVE])),
+        IFDField::Double(Box::new([-1.0e300])),
+        IFDField::Double(Box::new([std::f64::consts::PI, -0.0, f64::EPSILON])),
+    ];
+    for (tag, field) in (60000..).zip(fields) {
+        ifd.entries.insert(tag, field);
+    }
+    ifd
+}
+
+#[test]
+fn round_trips_signed_and_float_fields() {
+    let ifd = signed_and_float_ifd();
+    let ifds = [ifd.clone()];
+    for reader in &[
+        round_trip::<LittleEndian>(&ifds, TiffFormat::Classic, FieldTypeSizes::new()),
+        round_trip::<BigEndian>(&ifds, TiffFormat::Classic, FieldTypeSizes::new()),
+    ] {
+        assert_eq!(reader.ifds().next().unwrap().entries, ifd.entries);
+    }
+
+    let reader = round_trip::<BigEndian>(&ifds, TiffFormat::Classic, FieldTypeSizes::new());
+    let read = reader.ifds().next().unwrap();
+    assert_eq!(read.get::<i8>(60000).unwrap(), -128);
+    assert_eq!(read.get::<&[i16]>(60003).unwrap(), &[-1, 2, -3, 32767]);
+    assert_eq!(read.get::<i32>(60004).unwrap(), i32::MIN);
+    assert_eq!(read.get::<(i32, i32)>(60007).unwrap(), (i32::MIN, 7));
+    assert_eq!(read.get::<f32>(60008).unwrap(), -0.5);
+    assert_eq!(read.get::<f64>(60011).unwrap(), std::f64::consts::PI);
+    assert!(read.get::<u32>(60004).is_err());
+}
+
+#[test]
+fn round_trips_fields_of_registered_types() {
+    // A vendor-specific type with 2-byte elements, stored inline and at an offset
+    let mut ifd = IFD::new();
+    ifd.entries.insert(
+        60000,
+        IFDField::Opaque {
+            tag_type: 0x99,
+            count: 1,
+            data: Box::new([1, 2]),
+        },
+    );
+    ifd.entries.insert(
+        60001,
+        IFDField::Opaque {
+            tag_type: 0x99,
+            count: 3,
+            data: Box::new([1, 2, 3, 4, 5, 6]),
+        },
+    );
+    let mut sizes = FieldTypeSizes::new();
+    sizes.register(0x99, 2);
+    let reader = round_trip::<LittleEndian>(&[ifd.clone()], TiffFormat::Classic, sizes.clone());
+    let read = reader.ifds().next().unwrap();
+    assert_eq!(read.entries, ifd.entries);
+    let reader = round_trip::<LittleEndian>(slice::from_ref(read), TiffFormat::Classic, sizes);
+    assert_eq!(reader.ifds().next().unwrap().entries, ifd.entries);
+
+    // Without the size, the fields are unrecognized and cannot be written
+    let reader = round_trip::<BigEndian>(&[ifd], TiffFormat::Classic, FieldTypeSizes::new());
+    let read = reader.ifds().next().unwrap();
+    assert!(matches!(
+        read.entries[&60001],
+        IFDField::Unrecognized {
+            tag_type: 0x99,
+            count: 3,
+            ..
+        }
+    ));
+    let mut file = Cursor::new(Vec::new());
+    let mut metadata_writer = MetadataWriter::<BigEndian>::write_header(&mut file).unwrap();
+    assert!(metadata_writer.write_ifd(read, &mut file).is_err());
+}
@@ -0,0 +1,130 @@
+use byteorder::LittleEndian;
+use std::io::Cursor;
+use tiffy::baseline::constants::{compression, photometic_interpretation};
+use tiffy::baseline::{
+    tags, Compression, FillOrder, Orientation, Photometric, PlanarConfiguration, PredictorType,
+    Resolution, ResolutionUnit, SampleFormat,
+};
+use tiffy::errors::FieldExtractionError;
+use tiffy::image::{Image, ImageEncoder, PixelBuffer};
+use tiffy::lowlevel::{IFDField, IFD};
+
+#[test]
+fn applies_defaults_for_missing_tags() {
+    let mut ifd = IFD::new();
+    ifd.entries
+        .insert(tags::SAMPLES_PER_PIXEL, IFDField::Short(Box::new([3])));
+    assert_eq!(ifd.samples_per_pixel(), Ok(3));
+    assert_eq!(ifd.bits_per_sample(), Ok(vec![1, 1, 1]));
+    assert_eq!(
+        ifd.sample_format(),
+        Ok(vec![SampleFormat::UnsignedInteger; 3])
+    );
+    assert_eq!(ifd.compression(), Ok(Compression::Uncompressed));
+    assert_eq!(ifd.orientation(), Ok(Orientation::TopLeft));
+    assert_eq!(ifd.planar_configuration(), Ok(PlanarConfiguration::Chunky));
+    assert_eq!(ifd.fill_order(), Ok(FillOrder::MsbToLsb));
+    assert_eq!(ifd.predictor(), Ok(PredictorType::None));
+    assert_eq!(ifd.rows_per_strip(), Ok(u32::MAX));
+    assert_eq!(ifd.resolution(), Ok(None));
+
+    // Tags without a default must be present
+    assert_eq!(
+        ifd.width(),
+        Err(FieldExtractionError::MissingTag {
+            tag: tags::IMAGE_WIDTH
+        })
+    );
+    assert_eq!(
+        ifd.photometric(),
+        Err(FieldExtractionError::MissingTag {
+            tag: tags::PHOTOMETRIC_INTERPRETATION
+        })
+    );
+}
+
+#[test]
+fn accepts_any_unsigned_integer_type() {
+    let mut ifd = IFD::new();
+    ifd.entries
+        .insert(tags::IMAGE_WIDTH, IFDField::Short(Box::new([640])));
+    ifd.entries
+        .insert(tags::IMAGE_LENGTH, IFDField::Long(Box::new([480])));
+    ifd.entries
+        .insert(tags::ROWS_PER_STRIP, IFDField::Long8(Box::new([16])));
+    ifd.entries
+        .insert(tags::ORIENTATION, IFDField::Long(Box::new([6])));
+    ifd.entries.insert(
+        tags::BITS_PER_SAMPLE,
+        IFDField::Short(Box::new([8, 8, 8, 8])),
+    );
+    assert_eq!(ifd.width(), Ok(640));
+    assert_eq!(ifd.height(), Ok(480));
+    assert_eq!(ifd.rows_per_strip(), Ok(16));
+    assert_eq!(ifd.orientation(), Ok(Orientation::RightTop));
+    assert_eq!(ifd.bits_per_sample(), Ok(vec![8; 4]));
+
+    // Values which do not fit, and values of other types, are rejected
+    ifd.entries.insert(
+        tags::SAMPLES_PER_PIXEL,
+        IFDField::Long(Box::new([0x1_0000])),
+    );
+    assert_eq!(
+        ifd.samples_per_pixel(),
+        Err(FieldExtractionError::WrongDataType {
+            tag: tags::SAMPLES_PER_PIXEL
+        })
+    );
+    ifd.entries
+        .insert(tags::COMPRESSION, IFDField::SShort(Box::new([1])));
+    assert_eq!(
+        ifd.compression(),
+        Err(FieldExtractionError::WrongDataType {
+            tag: tags::COMPRESSION
+        })
+    );
+}
+
+#[test]
+fn converts_values_to_and_from_enums() {
+    assert_eq!(Compression::from(compression::LZW), Compression::Lzw);
+    assert_eq!(u16::from(Compression::AdobeDeflate), 8);
+    assert_eq!(Compression::from(0x8798), Compression::Other(0x8798));
+    assert_eq!(u16::from(Compression::Other(0x8798)), 0x8798);
+    assert_eq!(
+        Photometric::from(photometic_interpretation::YCBCR),
+        Photometric::YCbCr
+    );
+    assert_eq!(u16::from(ResolutionUnit::Centimeter), 3);
+}
+
+#[test]
+fn reads_tags_written_by_the_encoder() {
+    let image = Image {
+        width: 4,
+        height: 2,
+        samples_per_pixel: 3,
+        photometric: photometic_interpretation::RGB,
+        planar_configuration: 1,
+        data: PixelBuffer::U16((0..24).collect()),
+    };
+    let encoder = ImageEncoder::new()
+        .with_compression(compression::LZW)
+        .unwrap();
+    let ifd = encoder
+        .encode::<LittleEndian, _>(&image, &mut Cursor::new(Vec::new()))
+        .unwrap();
+    assert_eq!(ifd.width(), Ok(4));
+    assert_eq!(ifd.height(), Ok(2));
+    assert_eq!(ifd.bits_per_sample(), Ok(vec![16; 3]));
+    assert_eq!(ifd.compression(), Ok(Compression::Lzw));
+    assert_eq!(ifd.photometric(), Ok(Photometric::Rgb));
+    assert_eq!(
+        ifd.resolution(),
+        Ok(Some(Resolution {
+            x: (72, 1),
+            y: (72, 1),
+            unit: ResolutionUnit::Inch,
+        }))
+    );
+}
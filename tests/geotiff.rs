@@ -0,0 +1,159 @@
+mod common;
+
+use byteorder::LittleEndian;
+use common::round_trip;
+use tiffy::baseline::tags;
+use tiffy::geotiff::{
+    keys, AffineTransform, AngularUnit, GeoKeyDirectory, GeoKeyValue, GeoTiff, LinearUnit,
+    ModelType, RasterType, Tiepoint,
+};
+use tiffy::lowlevel::{FieldTypeSizes, IFDField, TiffFormat, IFD};
+
+/// An IFD with the GeoKeys of a UTM zone 33N image, as written by GDAL.
+fn utm_ifd() -> IFD {
+    let mut ifd = IFD::new();
+    ifd.entries.insert(
+        tags::GEO_KEY_DIRECTORY,
+        IFDField::Short(Box::new([
+            1, 1, 0, 6, //
+            1024, 0, 1, 1, //
+            1025, 0, 1, 1, //
+            1026, 34737, 22, 0, //
+            2054, 0, 1, 9102, //
+            2057, 34736, 1, 0, //
+            3072, 0, 1, 32633,
+        ])),
+    );
+    ifd.entries.insert(
+        tags::GEO_DOUBLE_PARAMS,
+        IFDField::Double(Box::new([6_378_137.0])),
+    );
+    ifd.entries.insert(
+        tags::GEO_ASCII_PARAMS,
+        IFDField::Ascii(Box::new(["WGS 84 / UTM zone 33N|".to_string()])),
+    );
+    ifd
+}
+
+#[test]
+fn decodes_geokeys() {
+    let directory = GeoKeyDirectory::from_ifd(&utm_ifd()).unwrap().unwrap();
+    assert_eq!(directory.revision, (1, 0));
+    assert_eq!(directory.keys.len(), 6);
+    assert_eq!(directory.model_type(), Some(ModelType::Projected));
+    assert_eq!(directory.raster_type(), RasterType::PixelIsArea);
+    assert_eq!(directory.projected_crs(), Some(32633));
+    assert_eq!(directory.geodetic_crs(), None);
+    assert_eq!(directory.angular_units(), Some(AngularUnit::Degree));
+    assert_eq!(directory.linear_units(), None);
+    assert_eq!(directory.citation(), Some("WGS 84 / UTM zone 33N"));
+    assert_eq!(
+        directory.double(keys::ELLIPSOID_SEMI_MAJOR_AXIS),
+        Some(6_378_137.0)
+    );
+    assert_eq!(LinearUnit::from_code(9001), LinearUnit::Metre);
+    assert_eq!(ModelType::from_code(keys::USER_DEFINED).code(), 32767);
+
+    assert_eq!(GeoKeyDirectory::from_ifd(&IFD::new()).unwrap(), None);
+    // Values outside the parameter tags are rejected
+    let mut ifd = utm_ifd();
+    ifd.entries.remove(&tags::GEO_DOUBLE_PARAMS);
+    assert!(GeoKeyDirectory::from_ifd(&ifd).is_err());
+    ifd.entries.insert(
+        tags::GEO_KEY_DIRECTORY,
+        IFDField::Short(Box::new([1, 1, 0, 2, 1024, 0, 1, 1])),
+    );
+    assert!(GeoKeyDirectory::from_ifd(&ifd).is_err());
+}
+
+#[test]
+fn computes_pixel_to_model_transforms() {
+    let geotiff = GeoTiff {
+        pixel_scale: Some([30.0, 30.0, 0.0]),
+        tiepoints: vec![Tiepoint {
+            raster: [10.0, 20.0, 0.0],
+            model: [500_000.0, 4_000_000.0, 0.0],
+        }],
+        ..GeoTiff::default()
+    };
+    let transform = geotiff.pixel_to_model().unwrap();
+    assert_eq!(transform.apply(10.0, 20.0), (500_000.0, 4_000_000.0));
+    assert_eq!(transform.apply(0.0, 0.0), (499_700.0, 4_000_600.0));
+    let (i, j) = transform.inverse().unwrap().apply(500_030.0, 3_999_970.0);
+    assert!((i - 11.0).abs() < 1e-9 && (j - 21.0).abs() < 1e-9);
+
+    // A rotated image described by a transformation matrix
+    let geotiff = GeoTiff {
+        transformation: Some([
+            0.0, -2.0, 0.0, 100.0, //
+            2.0, 0.0, 0.0, 50.0, //
+            0.0, 0.0, 0.0, 0.0, //
+            0.0, 0.0, 0.0, 1.0,
+        ]),
+        ..geotiff
+    };
+    let transform = geotiff.pixel_to_model().unwrap();
+    assert_eq!(transform.apply(1.0, 1.0), (98.0, 52.0));
+    assert_eq!(GeoTiff::default().pixel_to_model(), None);
+    let singular = AffineTransform {
+        coefficients: [1.0, 2.0, 0.0, 2.0, 4.0, 0.0],
+    };
+    assert_eq!(singular.inverse(), None);
+}
+
+#[test]
+fn round_trips_georeferencing_through_a_file() {
+    let mut keys = GeoKeyDirectory::new();
+    keys.set_model_type(ModelType::Geographic);
+    keys.set_raster_type(RasterType::PixelIsPoint);
+    keys.set_geodetic_crs(4326);
+    keys.set_angular_units(AngularUnit::Degree);
+    keys.set_citation("WGS 84");
+    keys.set(
+        keys::PROJ_STD_PARALLEL_1,
+        GeoKeyValue::Double(vec![33.0, 45.0]),
+    );
+    keys.set(keys::VERTICAL_CITATION, GeoKeyValue::Ascii("EGM96".into()));
+    keys.set(keys::VERTICAL_UNITS, GeoKeyValue::Short(vec![9001, 9002]));
+    let geotiff = GeoTiff {
+        keys,
+        pixel_scale: Some([0.25, 0.25, 0.0]),
+        tiepoints: vec![
+            Tiepoint {
+                raster: [0.0, 0.0, 0.0],
+                model: [-122.5, 37.8, 0.0],
+            },
+            Tiepoint {
+                raster: [4.0, 4.0, 0.0],
+                model: [-121.5, 36.8, 0.0],
+            },
+        ],
+        transformation: None,
+    };
+
+    let mut main = IFD::new();
+    main.entries
+        .insert(tags::IMAGE_WIDTH, IFDField::Long(Box::new([5])));
+    main.entries.insert(
+        tags::MODEL_TRANSFORMATION,
+        IFDField::Double(Box::new([0.0; 16])),
+    );
+    geotiff.write_to_ifd(&mut main).unwrap();
+    // Stale tags not used by the georeferencing are removed
+    assert!(!main.entries.contains_key(&tags::MODEL_TRANSFORMATION));
+    assert_eq!(
+        main.get::<String>(tags::GEO_ASCII_PARAMS).unwrap(),
+        "WGS 84|EGM96|"
+    );
+
+    let reader = round_trip::<LittleEndian>(&[main], TiffFormat::Classic, FieldTypeSizes::new());
+    let read = GeoTiff::from_ifd(reader.ifds().next().unwrap())
+        .unwrap()
+        .unwrap();
+    assert_eq!(read, geotiff);
+    assert_eq!(
+        read.pixel_to_model().unwrap().apply(4.0, 4.0),
+        (-121.5, 36.8)
+    );
+    assert_eq!(GeoTiff::from_ifd(&IFD::new()).unwrap(), None);
+}
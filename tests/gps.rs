@@ -0,0 +1,81 @@
+mod common;
+
+use byteorder::LittleEndian;
+use common::round_trip;
+use tiffy::baseline::tags::{self, gps};
+use tiffy::gps::{degrees_to_dms, dms_to_degrees, GpsCoordinates, EAST, NORTH, SOUTH, WEST};
+use tiffy::lowlevel::{FieldTypeSizes, IFDField, TiffFormat, IFD};
+
+#[test]
+fn converts_rational_triples_to_decimal_degrees() {
+    // 51° 28' 40.12" N, as written by many cameras with seconds in hundredths
+    let degrees = dms_to_degrees([(51, 1), (28, 1), (4012, 100)], false).unwrap();
+    assert!((degrees - 51.477_811).abs() < 1e-6);
+    // Minutes may be fractional too
+    let degrees = dms_to_degrees([(0, 1), (75, 10), (0, 1)], true).unwrap();
+    assert!((degrees + 0.125).abs() < 1e-12);
+    assert_eq!(dms_to_degrees([(1, 1), (0, 0), (0, 1)], false), None);
+
+    assert_eq!(
+        degrees_to_dms(-33.856_159, NORTH, SOUTH),
+        ([(33, 1), (51, 1), (221_724, 10_000)], SOUTH)
+    );
+    assert_eq!(
+        degrees_to_dms(151.215_256, EAST, WEST),
+        ([(151, 1), (12, 1), (549_216, 10_000)], EAST)
+    );
+    // Seconds which round up to a whole minute carry into the minutes and degrees
+    assert_eq!(
+        degrees_to_dms(9.999_999_999, EAST, WEST),
+        ([(10, 1), (0, 1), (0, 10_000)], EAST)
+    );
+}
+
+#[test]
+fn round_trips_coordinates_through_a_gps_ifd() {
+    let coordinates = GpsCoordinates {
+        latitude: -33.856_159,
+        longitude: -0.001,
+    };
+    let mut gps_ifd = IFD::new();
+    coordinates.write_to_ifd(&mut gps_ifd).unwrap();
+    let mut main = IFD::new();
+    main.entries
+        .insert(tags::IMAGE_WIDTH, IFDField::Long(Box::new([1])));
+    main.set_gps(gps_ifd);
+
+    let reader = round_trip::<LittleEndian>(&[main], TiffFormat::Classic, FieldTypeSizes::new());
+    let gps_ifd = reader.ifds().next().unwrap().gps().unwrap();
+    assert_eq!(
+        gps_ifd.get::<&[u8]>(gps::GPS_VERSION_ID).unwrap(),
+        &[2, 3, 0, 0]
+    );
+    assert_eq!(gps_ifd.get::<String>(gps::GPS_LONGITUDE_REF).unwrap(), WEST);
+    let read = GpsCoordinates::from_ifd(gps_ifd).unwrap().unwrap();
+    assert!((read.latitude - coordinates.latitude).abs() < 1e-7);
+    assert!((read.longitude - coordinates.longitude).abs() < 1e-7);
+}
+
+#[test]
+fn rejects_invalid_coordinates() {
+    assert_eq!(GpsCoordinates::from_ifd(&IFD::new()).unwrap(), None);
+
+    let mut gps_ifd = IFD::new();
+    GpsCoordinates {
+        latitude: 10.0,
+        longitude: 20.0,
+    }
+    .write_to_ifd(&mut gps_ifd)
+    .unwrap();
+    gps_ifd.entries.insert(
+        gps::GPS_LATITUDE_REF,
+        IFDField::Ascii(Box::new(["X".to_string()])),
+    );
+    assert!(GpsCoordinates::from_ifd(&gps_ifd).is_err());
+
+    let out_of_range = GpsCoordinates {
+        latitude: 91.0,
+        longitude: 0.0,
+    };
+    assert!(out_of_range.write_to_ifd(&mut IFD::new()).is_err());
+}
@@ -0,0 +1,522 @@
+mod common;
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use common::write_and_read;
+use std::io::Cursor;
+use tiffy::baseline::{
+    constants::{
+        compression, fill_order, ink_set, photometic_interpretation, planar_configuration,
+    },
+    tags,
+};
+use tiffy::compression::{codec_for, BlockDimensions};
+use tiffy::image::{ColorConverter, Image, ImageDecoder, ImageEncoder, PixelBuffer, YCbCr};
+use tiffy::lowlevel::{FieldTypeSizes, IFDField, TiffFormat, IFD};
+
+/// Encode `image` with `encoder` into a new file in byte order `E`, letting `edit` add tags to
+/// its IFD before it is written. Returns the file and the IFD read back from it.
+fn encode<E: ByteOrder>(
+    encoder: &ImageEncoder,
+    image: &Image,
+    edit: impl FnOnce(&mut IFD),
+) -> (Cursor<Vec<u8>>, IFD) {
+    let (file, reader) = write_and_read::<E>(
+        TiffFormat::Classic,
+        FieldTypeSizes::new(),
+        |metadata_writer, file| {
+            let mut ifd = encoder.encode::<E, _>(image, file).unwrap();
+            edit(&mut ifd);
+            metadata_writer.write_ifd(&ifd, file).unwrap();
+        },
+    );
+    let ifd = reader.ifds().next().unwrap().clone();
+    (file, ifd)
+}
+
+/// Prepare to decode an image encoded with `encode` in byte order `E`.
+fn decoder<E: ByteOrder>(ifd: &IFD) -> ImageDecoder {
+    ImageDecoder::new(ifd, E::read_u16(&[1, 0]) == 1).unwrap()
+}
+
+/// An interleaved image of `width` by `height` pixels with `samples_per_pixel` distinct 16-bit
+/// samples.
+fn test_image(width: u32, height: u32, samples_per_pixel: u16) -> Image {
+    let count = (width * height) as usize * usize::from(samples_per_pixel);
+    Image {
+        width,
+        height,
+        samples_per_pixel,
+        photometric: if samples_per_pixel == 1 {
+            photometic_interpretation::BLACKISZERO
+        } else {
+            photometic_interpretation::RGB
+        },
+        planar_configuration: planar_configuration::CHUNKY,
+        data: PixelBuffer::U16((0..count).map(|i| (i * 331 % 65521) as u16).collect()),
+    }
+}
+
+/// The `width` by `height` pixels of the interleaved 16-bit `image` at (`x`, `y`).
+fn crop(image: &Image, x: u32, y: u32, width: u32, height: u32) -> Image {
+    let samples = match &image.data {
+        PixelBuffer::U16(samples) => samples,
+        _ => unreachable!(),
+    };
+    let spp = usize::from(image.samples_per_pixel);
+    let data = (y..y + height)
+        .flat_map(|row| {
+            let start = (row * image.width + x) as usize * spp;
+            samples[start..start + width as usize * spp].to_vec()
+        })
+        .collect();
+    Image {
+        width,
+        height,
+        data: PixelBuffer::U16(data),
+        ..image.clone()
+    }
+}
+
+/// Lay out the interleaved 16-bit `image` as a little-endian file of `tile_width` by
+/// `tile_length` tiles compressed with `compression`, padding the tiles beyond the image with
+/// 0xFFFF. Returns the file and an IFD describing it.
+fn tile_by_hand(
+    image: &Image,
+    tile_width: u32,
+    tile_length: u32,
+    compression: u16,
+) -> (Cursor<Vec<u8>>, IFD) {
+    let samples = match &image.data {
+        PixelBuffer::U16(samples) => samples,
+        _ => unreachable!(),
+    };
+    let spp = u32::from(image.samples_per_pixel);
+    let codec = codec_for(compression).unwrap();
+    let block = BlockDimensions {
+        width: tile_width,
+        height: tile_length,
+        row_bytes: (tile_width * spp * 2) as usize,
+    };
+    let mut file = Vec::new();
+    let mut offsets = Vec::new();
+    let mut byte_counts = Vec::new();
+    for tile_y in (0..image.height).step_by(tile_length as usize) {
+        for tile_x in (0..image.width).step_by(tile_width as usize) {
+            let mut tile = Vec::new();
+            for y in tile_y..tile_y + tile_length {
+                for x in tile_x..tile_x + tile_width {
+                    for sample in 0..spp {
+                        let value = if x < image.width && y < image.height {
+                            samples[((y * image.width + x) * spp + sample) as usize]
+                        } else {
+                            0xFFFF
+                        };
+                        tile.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+            }
+            let compressed = codec.compress(&tile, &block).unwrap();
+            offsets.push(file.len() as u32);
+            byte_counts.push(compressed.len() as u32);
+            file.extend(compressed);
+        }
+    }
+
+    let mut ifd = IFD::new();
+    let short = |value| IFDField::Short(Box::new([value]));
+    let long = |value| IFDField::Long(Box::new([value]));
+    ifd.entries.insert(tags::IMAGE_WIDTH, long(image.width));
+    ifd.entries.insert(tags::IMAGE_LENGTH, long(image.height));
+    ifd.entries.insert(
+        tags::BITS_PER_SAMPLE,
+        IFDField::Short(vec![16; spp as usize].into()),
+    );
+    ifd.entries
+        .insert(tags::SAMPLES_PER_PIXEL, short(image.samples_per_pixel));
+    ifd.entries
+        .insert(tags::PHOTOMETRIC_INTERPRETATION, short(image.photometric));
+    ifd.entries.insert(tags::COMPRESSION, short(compression));
+    ifd.entries.insert(tags::TILE_WIDTH, long(tile_width));
+    ifd.entries.insert(tags::TILE_LENGTH, long(tile_length));
+    ifd.entries
+        .insert(tags::TILE_OFFSETS, IFDField::Long(offsets.into()));
+    ifd.entries
+        .insert(tags::TILE_BYTE_COUNTS, IFDField::Long(byte_counts.into()));
+    (Cursor::new(file), ifd)
+}
+
+#[test]
+fn decodes_tiles_and_crops_their_padding() {
+    let image = test_image(40, 35, 3);
+    for &compression in &[compression::UNCOMPRESSED, compression::PACKBITS] {
+        let (mut file, ifd) = tile_by_hand(&image, 16, 32, compression);
+        let decoder = ImageDecoder::new(&ifd, true).unwrap();
+        assert!(decoder.is_tiled());
+        assert_eq!(decoder.block_size(), (16, 32));
+        assert_eq!((decoder.blocks_across(), decoder.blocks_down()), (3, 2));
+        assert_eq!(decoder.block_count(), 6);
+        assert_eq!(decoder.decode(&mut file).unwrap(), image);
+
+        // Tiles on the right and bottom edges are cropped to the image
+        assert_eq!(
+            decoder.read_tile(&mut file, 1, 0).unwrap(),
+            crop(&image, 16, 0, 16, 32)
+        );
+        assert_eq!(
+            decoder.read_tile(&mut file, 2, 1).unwrap(),
+            crop(&image, 32, 32, 8, 3)
+        );
+        assert!(decoder.read_tile(&mut file, 3, 0).is_err());
+        assert!(decoder.read_tile(&mut file, 0, 2).is_err());
+        assert!(decoder.read_strip(&mut file, 0).is_err());
+    }
+}
+
+#[test]
+fn round_trips_strips_and_reads_single_strips() {
+    let image = test_image(37, 23, 3);
+    // 37 * 3 * 2 = 222 bytes per row, so strips hold 4 rows, the last only 3
+    let encoder = ImageEncoder::new()
+        .with_compression(compression::LZW)
+        .unwrap()
+        .with_strip_bytes(1000);
+    assert_eq!(encoder.rows_per_strip(&image), 4);
+    let (mut file, ifd) = encode::<BigEndian>(&encoder, &image, |_| {});
+    let decoder = decoder::<BigEndian>(&ifd);
+    assert!(!decoder.is_tiled());
+    assert_eq!(decoder.block_size(), (37, 4));
+    assert_eq!(decoder.block_count(), 6);
+    assert_eq!(decoder.decode(&mut file).unwrap(), image);
+
+    assert_eq!(
+        decoder.read_strip(&mut file, 0).unwrap(),
+        crop(&image, 0, 0, 37, 4)
+    );
+    assert_eq!(
+        decoder.read_strip(&mut file, 5).unwrap(),
+        crop(&image, 0, 20, 37, 3)
+    );
+    assert!(decoder.read_strip(&mut file, 6).is_err());
+    assert!(decoder.read_tile(&mut file, 0, 0).is_err());
+}
+
+#[test]
+fn round_trips_tiles_and_reads_single_tiles() {
+    let image = test_image(40, 35, 3);
+    let encoder = ImageEncoder::new()
+        .with_compression(compression::PACKBITS)
+        .unwrap()
+        .with_tiles(16, 32)
+        .unwrap();
+    for &little_endian in &[true, false] {
+        let (mut file, ifd, decoder) = if little_endian {
+            let (file, ifd) = encode::<LittleEndian>(&encoder, &image, |_| {});
+            let decoder = decoder::<LittleEndian>(&ifd);
+            (file, ifd, decoder)
+        } else {
+            let (file, ifd) = encode::<BigEndian>(&encoder, &image, |_| {});
+            let decoder = decoder::<BigEndian>(&ifd);
+            (file, ifd, decoder)
+        };
+        assert_eq!(ifd.get::<u32>(tags::TILE_WIDTH).unwrap(), 16);
+        assert!(decoder.is_tiled());
+        assert_eq!((decoder.blocks_across(), decoder.blocks_down()), (3, 2));
+        assert_eq!(decoder.decode(&mut file).unwrap(), image);
+
+        // Tiles on the right and bottom edges are cropped to the image
+        assert_eq!(
+            decoder.read_tile(&mut file, 1, 0).unwrap(),
+            crop(&image, 16, 0, 16, 32)
+        );
+        assert_eq!(
+            decoder.read_tile(&mut file, 2, 1).unwrap(),
+            crop(&image, 32, 32, 8, 3)
+        );
+        assert!(decoder.read_tile(&mut file, 3, 0).is_err());
+        assert!(decoder.read_tile(&mut file, 0, 2).is_err());
+        assert!(decoder.read_strip(&mut file, 0).is_err());
+    }
+}
+
+#[test]
+fn round_trips_planar_images() {
+    let image = test_image(21, 19, 3);
+    let planar = image.to_planar();
+    let encoders = [
+        ImageEncoder::new().with_strip_bytes(100),
+        ImageEncoder::new()
+            .with_compression(compression::ADOBE_DEFLATE)
+            .unwrap()
+            .with_tiles(16, 16)
+            .unwrap(),
+    ];
+    for encoder in &encoders {
+        let (mut file, ifd) = encode::<LittleEndian>(encoder, &planar, |_| {});
+        assert_eq!(
+            ifd.get::<u16>(tags::PLANAR_CONFIGURATION).unwrap(),
+            planar_configuration::PLANAR
+        );
+        let decoder = decoder::<LittleEndian>(&ifd);
+        assert!(decoder.is_planar());
+        assert_eq!(decoder.planes(), 3);
+        assert_eq!(decoder.block_count(), 3 * decoder.blocks_per_plane());
+
+        // Samples are interleaved unless asked otherwise
+        assert_eq!(decoder.decode(&mut file).unwrap(), image);
+        let decoder = decoder.with_planar_configuration(planar_configuration::PLANAR);
+        assert_eq!(decoder.decode(&mut file).unwrap(), planar);
+    }
+
+    // Chunky images may also be decoded into planes
+    let (mut file, ifd) = encode::<LittleEndian>(&encoders[0], &image, |_| {});
+    let decoder =
+        decoder::<LittleEndian>(&ifd).with_planar_configuration(planar_configuration::PLANAR);
+    assert_eq!(decoder.decode(&mut file).unwrap(), planar);
+}
+
+#[test]
+fn round_trips_packed_samples_in_both_fill_orders() {
+    // An odd width, so that rows end partway through a byte
+    let (width, height) = (13, 5);
+    let count = (width * height) as usize;
+    for &bits in &[1u16, 2, 4, 12, 24] {
+        let mask = (1u32 << bits) - 1;
+        let samples = (0..count as u32).map(|i| i.wrapping_mul(2_654_435_761) & mask);
+        let data = match bits {
+            1..=8 => PixelBuffer::U8(samples.map(|sample| sample as u8).collect()),
+            9..=16 => PixelBuffer::U16(samples.map(|sample| sample as u16).collect()),
+            _ => PixelBuffer::U32(samples.collect()),
+        };
+        let image = Image {
+            width,
+            height,
+            samples_per_pixel: 1,
+            photometric: photometic_interpretation::BLACKISZERO,
+            planar_configuration: planar_configuration::CHUNKY,
+            data,
+        };
+
+        let mut encoded = Vec::new();
+        for &order in &[fill_order::MSB_TO_LSB, fill_order::LSB_TO_MSB] {
+            let encoder = ImageEncoder::new()
+                .with_bits_per_sample(bits)
+                .with_fill_order(order)
+                .with_strip_bytes(4);
+            let (mut file, ifd) = encode::<BigEndian>(&encoder, &image, |_| {});
+            assert_eq!(ifd.get::<u16>(tags::BITS_PER_SAMPLE).unwrap(), bits);
+            let decoder = decoder::<BigEndian>(&ifd);
+            assert_eq!(decoder.bits_per_sample(), bits);
+            assert_eq!(decoder.decode(&mut file).unwrap(), image, "{} bits", bits);
+
+            // Both fill orders hold the same bits, reversed within each byte
+            let offset = ifd.get::<Vec<u64>>(tags::STRIP_OFFSETS).unwrap()[0] as usize;
+            let length = ifd.get::<Vec<u64>>(tags::STRIP_BYTE_COUNTS).unwrap()[0] as usize;
+            encoded.push(file.into_inner()[offset..offset + length].to_vec());
+        }
+        let reversed: Vec<u8> = encoded[1].iter().map(|byte| byte.reverse_bits()).collect();
+        assert_eq!(encoded[0], reversed);
+    }
+}
+
+/// Encode the 8-bit `samples` as a `width` by 1 image of `photometric`, letting `edit` add tags
+/// to its IFD, then decode it and convert it to 8-bit RGB.
+fn to_rgb8(
+    photometric: u16,
+    samples_per_pixel: u16,
+    samples: Vec<u8>,
+    edit: impl FnOnce(&mut IFD),
+) -> Vec<u8> {
+    let width = (samples.len() / usize::from(samples_per_pixel)) as u32;
+    let image = Image {
+        width,
+        height: 1,
+        samples_per_pixel,
+        photometric,
+        planar_configuration: planar_configuration::CHUNKY,
+        data: PixelBuffer::U8(samples),
+    };
+    let (mut file, ifd) = encode::<LittleEndian>(&ImageEncoder::new(), &image, edit);
+    let decoded = decoder::<LittleEndian>(&ifd).decode(&mut file).unwrap();
+    match ColorConverter::new(&ifd)
+        .unwrap()
+        .to_rgb8(&decoded)
+        .unwrap()
+        .data
+    {
+        PixelBuffer::U8(rgb) => rgb,
+        _ => unreachable!(),
+    }
+}
+
+/// Assert that each of `actual` is within `tolerance` of `expected`.
+fn assert_close(actual: &[u8], expected: &[u8], tolerance: u8) {
+    assert_eq!(actual.len(), expected.len());
+    for (&a, &e) in actual.iter().zip(expected) {
+        assert!(
+            a.max(e) - a.min(e) <= tolerance,
+            "{:?} != {:?}",
+            actual,
+            expected
+        );
+    }
+}
+
+#[test]
+fn converts_each_photometric_to_rgb() {
+    use photometic_interpretation::*;
+
+    let rgb = to_rgb8(WHITEISZERO, 1, vec![0, 255, 64], |_| {});
+    assert_eq!(rgb, [255, 255, 255, 0, 0, 0, 191, 191, 191]);
+
+    let rgb = to_rgb8(BLACKISZERO, 1, vec![0, 255, 64], |_| {});
+    assert_eq!(rgb, [0, 0, 0, 255, 255, 255, 64, 64, 64]);
+
+    let rgb = to_rgb8(RGB, 3, vec![10, 20, 30, 255, 0, 128], |_| {});
+    assert_eq!(rgb, [10, 20, 30, 255, 0, 128]);
+
+    // Palette entries are 16-bit: red rises with the index, green falls, and blue is zero
+    let rgb = to_rgb8(RGB_PALETTE, 1, vec![0, 255, 3], |ifd| {
+        let mut color_map: Vec<u16> = (0..256).map(|i| i * 257).collect();
+        color_map.extend((0..256).map(|i| (255 - i) * 257));
+        color_map.extend(vec![0; 256]);
+        ifd.entries
+            .insert(tags::COLOR_MAP, IFDField::Short(color_map.into()));
+    });
+    assert_eq!(rgb, [0, 255, 0, 255, 0, 0, 3, 252, 0]);
+
+    let rgb = to_rgb8(
+        CMYK,
+        4,
+        vec![0, 0, 0, 0, 255, 0, 0, 0, 0, 0, 0, 255, 0, 255, 255, 0],
+        |ifd| {
+            ifd.entries
+                .insert(tags::INK_SET, IFDField::Short(Box::new([ink_set::CMYK])));
+        },
+    );
+    assert_eq!(rgb, [255, 255, 255, 0, 255, 255, 0, 0, 0, 255, 0, 0]);
+
+    // Black, white and neutral grey have no chroma
+    let rgb = to_rgb8(
+        YCBCR,
+        3,
+        vec![0, 128, 128, 255, 128, 128, 100, 128, 128],
+        |ifd| {
+            YCbCr {
+                subsampling: (1, 1),
+                ..YCbCr::new(8)
+            }
+            .write_to_ifd(ifd)
+        },
+    );
+    assert_close(&rgb, &[0, 0, 0, 255, 255, 255, 100, 100, 100], 1);
+
+    // L* of 0 and 100 with neutral a* and b* are black and white, and a positive a* is reddish
+    let rgb = to_rgb8(CIELAB, 3, vec![0, 0, 0, 255, 0, 0, 136, 60, 0], |_| {});
+    assert_close(&rgb[..6], &[0, 0, 0, 255, 255, 255], 1);
+    assert!(rgb[6] > rgb[7] && rgb[6] > rgb[8]);
+}
+
+#[test]
+fn converts_subsampled_ycbcr_to_rgb() {
+    // Chroma is interpolated between blocks, so only a uniform colour survives exactly. The
+    // image ends partway through the blocks on its right and bottom edges
+    let (width, height) = (5, 3);
+    let rgb = Image {
+        width,
+        height,
+        samples_per_pixel: 3,
+        photometric: photometic_interpretation::RGB,
+        planar_configuration: planar_configuration::CHUNKY,
+        data: PixelBuffer::U8([200, 30, 40].repeat((width * height) as usize)),
+    };
+    let ycbcr = YCbCr::new(8);
+    let image = ycbcr.from_rgb_image(&rgb).unwrap();
+    let encoder = ImageEncoder::new().with_ycbcr(ycbcr);
+    let (mut file, ifd) = encode::<LittleEndian>(&encoder, &image, |_| {});
+    let decoded = decoder::<LittleEndian>(&ifd).decode(&mut file).unwrap();
+    let converted = ColorConverter::new(&ifd)
+        .unwrap()
+        .to_rgb8(&decoded)
+        .unwrap();
+    match (&converted.data, &rgb.data) {
+        (PixelBuffer::U8(actual), PixelBuffer::U8(expected)) => assert_close(actual, expected, 2),
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn rejects_dimensions_beyond_32_bits() {
+    let image = test_image(4, 4, 1);
+    let (_, mut ifd) = encode::<LittleEndian>(&ImageEncoder::new(), &image, |_| {});
+    ifd.entries
+        .insert(tags::IMAGE_WIDTH, IFDField::Long8(Box::new([1 << 32])));
+    assert_eq!(
+        ImageDecoder::new(&ifd, true).err().unwrap().to_string(),
+        "Image dimensions are too large"
+    );
+
+    let (_, mut ifd) = encode::<LittleEndian>(
+        &ImageEncoder::new().with_tiles(16, 16).unwrap(),
+        &image,
+        |_| {},
+    );
+    ifd.entries.insert(
+        tags::TILE_LENGTH,
+        IFDField::Long8(Box::new([(1 << 32) + 16])),
+    );
+    assert_eq!(
+        ImageDecoder::new(&ifd, true).err().unwrap().to_string(),
+        "Image dimensions are too large"
+    );
+}
+
+#[test]
+fn rejects_malformed_sample_layouts() {
+    let image = test_image(4, 4, 1);
+    let (_, valid) = encode::<LittleEndian>(&ImageEncoder::new(), &image, |_| {});
+    let malformed = [
+        (tags::SAMPLES_PER_PIXEL, 0),
+        // Values which would otherwise be truncated to 0 or a supported value
+        (tags::SAMPLES_PER_PIXEL, 1 << 16),
+        (tags::PLANAR_CONFIGURATION, (1 << 16) + 1),
+        (tags::COMPRESSION, (1 << 16) + 1),
+        (tags::PREDICTOR, (1 << 16) + 1),
+    ];
+    for &(tag, value) in &malformed {
+        let mut ifd = valid.clone();
+        ifd.entries.insert(tag, IFDField::Long(Box::new([value])));
+        assert!(ImageDecoder::new(&ifd, true).is_err(), "{:#X}", tag);
+    }
+
+    let mut ifd = valid;
+    ifd.entries
+        .insert(tags::SAMPLES_PER_PIXEL, IFDField::Short(Box::new([0])));
+    assert_eq!(
+        ImageDecoder::new(&ifd, true).err().unwrap().to_string(),
+        "Pixels must have at least one sample"
+    );
+}
+
+#[test]
+fn rejects_blocks_too_large_for_memory() {
+    let block = BlockDimensions {
+        width: u32::MAX,
+        height: u32::MAX,
+        row_bytes: usize::MAX / 2,
+    };
+    assert!(block.byte_count().is_err());
+
+    // Tiles of the largest size describable, of which the image only needs a corner
+    let image = test_image(4, 4, 3);
+    let encoder = ImageEncoder::new().with_tiles(16, 16).unwrap();
+    let (mut file, mut ifd) = encode::<LittleEndian>(&encoder, &image, |_| {});
+    for &tag in &[tags::TILE_WIDTH, tags::TILE_LENGTH] {
+        ifd.entries
+            .insert(tag, IFDField::Long(Box::new([u32::MAX])));
+    }
+    let decoder = decoder::<LittleEndian>(&ifd);
+    assert_eq!(
+        decoder.decode(&mut file).err().unwrap().to_string(),
+        "Image dimensions are too large"
+    );
+}
@@ -0,0 +1,325 @@
+mod common;
+
+use byteorder::{BigEndian, WriteBytesExt};
+use common::write_and_read;
+use std::io::{Seek, Write};
+use tiffy::baseline::{
+    constants::{compression, photometic_interpretation},
+    tags,
+};
+use tiffy::compression::{codec_for, BlockDimensions, Codec, Jpeg};
+use tiffy::image::{ColorConverter, ImageDecoder, PixelBuffer};
+use tiffy::lowlevel::{FieldTypeSizes, IFDField, TiffFormat, IFD};
+
+/// A marker segment holding `payload`.
+fn segment(marker: u8, payload: &[u8]) -> Vec<u8> {
+    let mut segment = vec![0xFF, marker];
+    segment
+        .write_u16::<BigEndian>(payload.len() as u16 + 2)
+        .unwrap();
+    segment.extend_from_slice(payload);
+    segment
+}
+
+/// An abbreviated stream holding only tables, as stored in `JPEG_TABLES`: a quantization table
+/// scaling DC coefficients by 8, a DC table coding category 0 as "0" and category 4 as "10",
+/// and an AC table coding only the end of block as "0".
+fn tables() -> Vec<u8> {
+    let mut quantization = vec![0, 8];
+    quantization.extend_from_slice(&[1; 63]);
+    let mut dc = vec![0x00, 1, 1];
+    dc.extend_from_slice(&[0; 14]);
+    dc.extend_from_slice(&[0, 4]);
+    let mut ac = vec![0x10, 1];
+    ac.extend_from_slice(&[0; 15]);
+    ac.push(0);
+
+    let mut stream = vec![0xFF, 0xD8];
+    stream.extend(segment(0xDB, &quantization));
+    stream.extend(segment(0xC4, &dc));
+    stream.extend(segment(0xC4, &ac));
+    stream.extend_from_slice(&[0xFF, 0xD9]);
+    stream
+}
+
+/// An abbreviated baseline stream of `width` by `height` pixels whose components have the given
+/// sampling factors, with entropy coded data given as a string of '0' and '1' characters.
+fn stream(width: u16, height: u16, sampling: &[u8], bits: &str) -> Vec<u8> {
+    let mut frame = vec![8];
+    frame.write_u16::<BigEndian>(height).unwrap();
+    frame.write_u16::<BigEndian>(width).unwrap();
+    frame.push(sampling.len() as u8);
+    let mut scan = vec![sampling.len() as u8];
+    for (id, &factors) in (1..).zip(sampling) {
+        frame.extend_from_slice(&[id, factors, 0]);
+        scan.extend_from_slice(&[id, 0x00]);
+    }
+    scan.extend_from_slice(&[0, 63, 0]);
+
+    let mut stream = vec![0xFF, 0xD8];
+    stream.extend(segment(0xC0, &frame));
+    stream.extend(segment(0xDA, &scan));
+    stream.extend(entropy_coded(bits));
+    stream.extend_from_slice(&[0xFF, 0xD9]);
+    stream
+}
+
+/// Entropy coded data given as a string of '0' and '1' characters, padded with ones.
+fn entropy_coded(bits: &str) -> Vec<u8> {
+    let bits: Vec<u8> = bits
+        .bytes()
+        .filter(|&b| b != b' ')
+        .map(|b| b - b'0')
+        .collect();
+    bits.chunks(8)
+        .map(|byte| {
+            let byte = (0..8).fold(0u8, |acc, i| acc << 1 | byte.get(i).copied().unwrap_or(1));
+            assert_ne!(byte, 0xFF);
+            byte
+        })
+        .collect()
+}
+
+/// An IFD of a `width` by `height` grayscale image with old-style JPEG compression.
+fn old_jpeg_ifd(width: u16, height: u16) -> IFD {
+    let mut ifd = IFD::new();
+    let entries = &mut ifd.entries;
+    entries.insert(tags::IMAGE_WIDTH, IFDField::Short(Box::new([width])));
+    entries.insert(tags::IMAGE_LENGTH, IFDField::Short(Box::new([height])));
+    entries.insert(tags::BITS_PER_SAMPLE, IFDField::Short(Box::new([8])));
+    entries.insert(
+        tags::COMPRESSION,
+        IFDField::Short(Box::new([compression::JPEG])),
+    );
+    entries.insert(
+        tags::PHOTOMETRIC_INTERPRETATION,
+        IFDField::Short(Box::new([photometic_interpretation::BLACKISZERO])),
+    );
+    entries.insert(tags::ROWS_PER_STRIP, IFDField::Short(Box::new([8])));
+    ifd
+}
+
+/// Write `ifd` to a new file after `data`, then decode the image it describes.
+fn decode_after(data: &[u8], ifd: &IFD) -> tiffy::Result<PixelBuffer> {
+    let (mut file, reader) = write_and_read::<BigEndian>(
+        TiffFormat::Classic,
+        FieldTypeSizes::new(),
+        |metadata_writer, file| {
+            file.write_all(data).unwrap();
+            metadata_writer.write_ifd(ifd, file).unwrap();
+        },
+    );
+    let ifd = reader.ifds().next().unwrap();
+    Ok(ImageDecoder::new(ifd, false)?.decode(&mut file)?.data)
+}
+
+/// Entropy coded blocks with a DC difference of +8 (136 after the level shift), 0 and -8.
+const PLUS_8: &str = "10 1000 0";
+const ZERO: &str = "0 0";
+const MINUS_8: &str = "10 0111 0";
+
+#[test]
+fn merges_tables_with_abbreviated_streams() {
+    let data = stream(16, 8, &[0x11], &[PLUS_8, MINUS_8].concat());
+    let block = BlockDimensions {
+        width: 16,
+        height: 8,
+        row_bytes: 16,
+    };
+    let pixels = Jpeg::with_tables(tables())
+        .decompress(&data, &block)
+        .unwrap();
+    let expected: Vec<u8> = (0..8).flat_map(|_| [[136; 8], [128; 8]].concat()).collect();
+    assert_eq!(pixels, expected);
+
+    // Without the tables there is nothing to decode the stream with
+    let codec = codec_for(compression::NEW_JPEG).unwrap();
+    assert!(codec.decompress(&data, &block).is_err());
+    assert!(codec.compress(&expected, &block).is_err());
+}
+
+#[test]
+fn returns_samples_without_color_transform() {
+    let data = stream(8, 8, &[0x11; 3], &[PLUS_8, ZERO, MINUS_8].concat());
+    let block = BlockDimensions {
+        width: 8,
+        height: 8,
+        row_bytes: 24,
+    };
+    let pixels = Jpeg::with_tables(tables())
+        .decompress(&data, &block)
+        .unwrap();
+    assert_eq!(pixels, [136, 128, 120].repeat(64));
+
+    let wrong_block = BlockDimensions {
+        row_bytes: 8,
+        ..block
+    };
+    assert!(Jpeg::with_tables(tables())
+        .decompress(&data, &wrong_block)
+        .is_err());
+}
+
+#[test]
+fn decodes_subsampled_ycbcr_strips() {
+    // Two strips of one 16x16 MCU each, of four luma blocks followed by Cb and Cr
+    let strips = [
+        stream(
+            16,
+            16,
+            &[0x22, 0x11, 0x11],
+            &[PLUS_8, ZERO, ZERO, ZERO, ZERO, MINUS_8].concat(),
+        ),
+        stream(
+            16,
+            16,
+            &[0x22, 0x11, 0x11],
+            &[ZERO, ZERO, ZERO, ZERO, ZERO, ZERO].concat(),
+        ),
+    ];
+
+    let mut ifd = IFD::new();
+    let entries = &mut ifd.entries;
+    entries.insert(tags::IMAGE_WIDTH, IFDField::Short(Box::new([16])));
+    entries.insert(tags::IMAGE_LENGTH, IFDField::Short(Box::new([32])));
+    entries.insert(tags::BITS_PER_SAMPLE, IFDField::Short(Box::new([8; 3])));
+    entries.insert(
+        tags::COMPRESSION,
+        IFDField::Short(Box::new([compression::NEW_JPEG])),
+    );
+    entries.insert(
+        tags::PHOTOMETRIC_INTERPRETATION,
+        IFDField::Short(Box::new([photometic_interpretation::YCBCR])),
+    );
+    entries.insert(tags::SAMPLES_PER_PIXEL, IFDField::Short(Box::new([3])));
+    entries.insert(tags::ROWS_PER_STRIP, IFDField::Short(Box::new([16])));
+    entries.insert(tags::JPEG_TABLES, IFDField::Undefined(tables().into()));
+    let (mut file, reader) = write_and_read::<BigEndian>(
+        TiffFormat::Classic,
+        FieldTypeSizes::new(),
+        |metadata_writer, file| {
+            let mut offsets = Vec::new();
+            let mut byte_counts = Vec::new();
+            for strip in &strips {
+                offsets.push(file.stream_position().unwrap() as u32);
+                byte_counts.push(strip.len() as u32);
+                file.write_all(strip).unwrap();
+            }
+            let entries = &mut ifd.entries;
+            entries.insert(tags::STRIP_OFFSETS, IFDField::Long(offsets.into()));
+            entries.insert(tags::STRIP_BYTE_COUNTS, IFDField::Long(byte_counts.into()));
+            metadata_writer.write_ifd(&ifd, file).unwrap();
+        },
+    );
+    let ifd = reader.ifds().next().unwrap();
+    let image = ImageDecoder::new(ifd, false)
+        .unwrap()
+        .decode(&mut file)
+        .unwrap();
+    // DC predictors start afresh in each strip
+    let expected = [[136, 128, 120].repeat(256), [128; 3].repeat(256)].concat();
+    assert_eq!(image.data, PixelBuffer::U8(expected));
+
+    let rgb = ColorConverter::new(ifd).unwrap().to_rgb8(&image).unwrap();
+    match rgb.data {
+        PixelBuffer::U8(samples) => {
+            assert_eq!(samples[..3], [125, 142, 136]);
+            assert_eq!(samples[samples.len() - 3..], [128, 128, 128]);
+        }
+        _ => panic!("expected 8-bit RGB"),
+    }
+}
+
+#[test]
+fn decodes_old_style_interchange_format() {
+    // A complete stream, whose entropy coded data the strip offsets point into
+    let tables = tables();
+    let mut data = tables[..tables.len() - 2].to_vec();
+    let stream = stream(16, 8, &[0x11], &[PLUS_8, MINUS_8].concat());
+    data.extend_from_slice(&stream[2..]);
+    // Files start with the 8 byte header
+    let start = 8;
+    let scan_end = start + data.len() as u32 - 2;
+
+    let mut ifd = old_jpeg_ifd(16, 8);
+    let entries = &mut ifd.entries;
+    entries.insert(
+        tags::JPEG_INTERCHANGE_FORMAT,
+        IFDField::Long(Box::new([start])),
+    );
+    entries.insert(
+        tags::JPEG_INTERCHANGE_FORMAT_LNGTH,
+        IFDField::Long(Box::new([data.len() as u32])),
+    );
+    entries.insert(
+        tags::STRIP_OFFSETS,
+        IFDField::Long(Box::new([scan_end - 2])),
+    );
+    entries.insert(tags::STRIP_BYTE_COUNTS, IFDField::Long(Box::new([2])));
+
+    let expected = PixelBuffer::U8((0..8).flat_map(|_| [[136; 8], [128; 8]].concat()).collect());
+    assert_eq!(decode_after(&data, &ifd).unwrap(), expected);
+
+    // Without a length, the stream ends with the strips rather than running on into the IFD,
+    // even if it lacks an end of image marker
+    ifd.entries.remove(&tags::JPEG_INTERCHANGE_FORMAT_LNGTH);
+    assert_eq!(decode_after(&data, &ifd).unwrap(), expected);
+    data.truncate(data.len() - 2);
+    assert_eq!(decode_after(&data, &ifd).unwrap(), expected);
+}
+
+#[test]
+fn decodes_old_style_tables() {
+    // Raw tables without marker segments, followed by two strips of entropy coded data
+    let mut data = vec![8];
+    data.extend_from_slice(&[1; 63]);
+    data.extend_from_slice(&[1, 1]);
+    data.extend_from_slice(&[0; 14]);
+    data.extend_from_slice(&[0, 4]);
+    data.push(1);
+    data.extend_from_slice(&[0; 15]);
+    data.push(0);
+    let strips = [
+        entropy_coded(&[PLUS_8, MINUS_8].concat()),
+        entropy_coded(&[ZERO, ZERO].concat()),
+    ];
+    let start = 8;
+    let mut offsets = Vec::new();
+    let mut byte_counts = Vec::new();
+    for strip in &strips {
+        offsets.push(start + data.len() as u32);
+        byte_counts.push(strip.len() as u32);
+        data.extend_from_slice(strip);
+    }
+
+    let mut ifd = old_jpeg_ifd(16, 16);
+    let entries = &mut ifd.entries;
+    entries.insert(tags::JPEG_PROC, IFDField::Short(Box::new([1])));
+    entries.insert(tags::JPEGQ_TABLES, IFDField::Long(Box::new([start])));
+    entries.insert(tags::JPEGDC_TABLES, IFDField::Long(Box::new([start + 64])));
+    entries.insert(tags::JPEGAC_TABLES, IFDField::Long(Box::new([start + 82])));
+    entries.insert(tags::STRIP_OFFSETS, IFDField::Long(offsets.into()));
+    entries.insert(tags::STRIP_BYTE_COUNTS, IFDField::Long(byte_counts.into()));
+
+    let expected: Vec<u8> = (0..8)
+        .flat_map(|_| [[136; 8], [128; 8]].concat())
+        .chain(vec![128; 128])
+        .collect();
+    assert_eq!(
+        decode_after(&data, &ifd).unwrap(),
+        PixelBuffer::U8(expected)
+    );
+
+    // Frame headers cannot hold widths beyond 16 bits, which are not truncated
+    ifd.entries
+        .insert(tags::IMAGE_WIDTH, IFDField::Long(Box::new([1 << 16])));
+    assert_eq!(
+        decode_after(&data, &ifd).err().unwrap().to_string(),
+        "JPEG data does not match the dimensions of its strip or tile"
+    );
+
+    // Only baseline sequential coding is supported
+    ifd.entries
+        .insert(tags::JPEG_PROC, IFDField::Short(Box::new([14])));
+    assert!(ImageDecoder::new(&ifd, false).is_err());
+}
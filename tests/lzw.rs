@@ -0,0 +1,136 @@
+use tiffy::baseline::constants::compression;
+use tiffy::compression::{codec_for, lzw_decode, lzw_encode, BlockDimensions};
+
+/// Pack `(code, width)` pairs MSB-first, as standard TIFF LZW does.
+fn pack_msb(codes: &[(u16, u8)]) -> Vec<u8> {
+    let mut bits = Vec::new();
+    for &(code, width) in codes {
+        for bit in (0..width).rev() {
+            bits.push((code >> bit) & 1 == 1);
+        }
+    }
+    bits.chunks(8)
+        .map(|byte| {
+            byte.iter()
+                .enumerate()
+                .fold(0u8, |acc, (i, &bit)| acc | ((bit as u8) << (7 - i)))
+        })
+        .collect()
+}
+
+/// Pack `(code, width)` pairs LSB-first, as old-style LZW does.
+fn pack_lsb(codes: &[(u16, u8)]) -> Vec<u8> {
+    let mut bits = Vec::new();
+    for &(code, width) in codes {
+        for bit in 0..width {
+            bits.push((code >> bit) & 1 == 1);
+        }
+    }
+    bits.chunks(8)
+        .map(|byte| {
+            byte.iter()
+                .enumerate()
+                .fold(0u8, |acc, (i, &bit)| acc | ((bit as u8) << i))
+        })
+        .collect()
+}
+
+/// "ABABABA" is <clear> A B AB ABA <eoi>, where the final ABA code is defined by its own use.
+const ABABABA_CODES: [(u16, u8); 6] = [(256, 9), (65, 9), (66, 9), (258, 9), (260, 9), (257, 9)];
+
+/// Deterministic, moderately compressible test data.
+fn test_data(len: usize) -> Vec<u8> {
+    let mut seed = 0x1234_5678u32;
+    (0..len)
+        .map(|_| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            ((seed >> 16) % 24) as u8
+        })
+        .collect()
+}
+
+#[test]
+fn decodes_msb_fixture() {
+    let stream = pack_msb(&ABABABA_CODES);
+    assert_eq!(lzw_decode(&stream, 7).unwrap(), b"ABABABA");
+}
+
+#[test]
+fn decodes_old_style_lsb_fixture() {
+    let stream = pack_lsb(&ABABABA_CODES);
+    assert_eq!(lzw_decode(&stream, 7).unwrap(), b"ABABABA");
+}
+
+#[test]
+fn encodes_msb_fixture() {
+    assert_eq!(lzw_encode(b"ABABABA"), pack_msb(&ABABABA_CODES));
+}
+
+#[test]
+fn msb_code_width_changes_early() {
+    // 254 literals define codes up to 510, at which point standard LZW switches to 10-bit codes
+    // one code before the 9-bit table is actually full.
+    let mut codes = vec![(256, 9)];
+    let mut expected = Vec::new();
+    for i in 0..254u16 {
+        codes.push((i, 9));
+        expected.push(i as u8);
+    }
+    codes.push((258, 10));
+    expected.extend_from_slice(&[0, 1]);
+    codes.push((257, 10));
+
+    assert_eq!(
+        lzw_decode(&pack_msb(&codes), expected.len()).unwrap(),
+        expected
+    );
+    assert_eq!(lzw_encode(&expected), pack_msb(&codes));
+}
+
+#[test]
+fn lsb_code_width_changes_late() {
+    // Old-style LZW only switches to 10-bit codes once code 511 has been assigned.
+    let mut codes = vec![(256, 9)];
+    let mut expected = Vec::new();
+    for i in 0..254u16 {
+        codes.push((i, 9));
+        expected.push(i as u8);
+    }
+    codes.push((258, 9));
+    expected.extend_from_slice(&[0, 1]);
+    codes.push((259, 10));
+    expected.extend_from_slice(&[1, 2]);
+    codes.push((257, 10));
+
+    assert_eq!(
+        lzw_decode(&pack_lsb(&codes), expected.len()).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn stops_at_expected_length_without_eoi() {
+    let stream = pack_msb(&ABABABA_CODES[..5]);
+    assert_eq!(lzw_decode(&stream, 4).unwrap(), b"ABAB");
+}
+
+#[test]
+fn rejects_undefined_codes() {
+    let stream = pack_msb(&[(256, 9), (65, 9), (300, 9), (257, 9)]);
+    assert!(lzw_decode(&stream, 16).is_err());
+}
+
+#[test]
+fn round_trips_through_table_resets() {
+    let codec = codec_for(compression::LZW).unwrap();
+    for &len in &[0, 1, 2, 511, 4096, 100_000] {
+        let data = test_data(len);
+        let block = BlockDimensions {
+            width: len as u32,
+            height: 1,
+            row_bytes: len,
+        };
+        let compressed = codec.compress(&data, &block).unwrap();
+        assert_eq!(codec.decompress(&compressed, &block).unwrap(), data);
+    }
+}
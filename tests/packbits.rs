@@ -0,0 +1,72 @@
+use tiffy::baseline::constants::compression;
+use tiffy::compression::{codec_for, packbits_decode, packbits_encode, BlockDimensions};
+
+/// The example from Apple Technical Note TN1023.
+const TN1023_PACKED: [u8; 15] = [
+    0xFE, 0xAA, 0x02, 0x80, 0x00, 0x2A, 0xFD, 0xAA, 0x03, 0x80, 0x00, 0x2A, 0x22, 0xF7, 0xAA,
+];
+const TN1023_UNPACKED: [u8; 24] = [
+    0xAA, 0xAA, 0xAA, 0x80, 0x00, 0x2A, 0xAA, 0xAA, 0xAA, 0xAA, 0x80, 0x00, 0x2A, 0x22, 0xAA, 0xAA,
+    0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA, 0xAA,
+];
+
+/// Deterministic test data mixing literal stretches with long runs.
+fn test_data(len: usize) -> Vec<u8> {
+    let mut seed = 0x1234_5678u32;
+    (0..len)
+        .map(|i| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            if (i / 200) % 2 == 0 {
+                (seed >> 16) as u8
+            } else {
+                (i / 50) as u8
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn decodes_tn1023_example() {
+    assert_eq!(
+        packbits_decode(&TN1023_PACKED, TN1023_UNPACKED.len()).unwrap(),
+        TN1023_UNPACKED
+    );
+}
+
+#[test]
+fn round_trips() {
+    for &len in &[0, 1, 2, 3, 127, 128, 129, 1000] {
+        let data = test_data(len);
+        assert_eq!(packbits_decode(&packbits_encode(&data), len).unwrap(), data);
+    }
+    assert_eq!(
+        packbits_decode(&packbits_encode(&TN1023_UNPACKED), 24).unwrap(),
+        TN1023_UNPACKED
+    );
+
+    // Through the codec, rows are packed separately
+    let codec = codec_for(compression::PACKBITS).unwrap();
+    let data = test_data(1000);
+    let block = BlockDimensions {
+        width: 100,
+        height: 10,
+        row_bytes: 100,
+    };
+    let compressed = codec.compress(&data, &block).unwrap();
+    assert_eq!(codec.decompress(&compressed, &block).unwrap(), data);
+}
+
+#[test]
+fn cuts_off_runs_past_the_expected_length() {
+    // A replicate run of four bytes, of which only two are wanted
+    assert_eq!(packbits_decode(&[0xFD, 7], 2).unwrap(), [7, 7]);
+    // Likewise for literal runs
+    assert_eq!(packbits_decode(&[0x03, 1, 2, 3, 4], 3).unwrap(), [1, 2, 3]);
+}
+
+#[test]
+fn rejects_truncated_input() {
+    assert!(packbits_decode(&TN1023_PACKED, 25).is_err());
+    assert!(packbits_decode(&TN1023_PACKED[..14], 24).is_err());
+    assert!(packbits_decode(&[0x03, 1, 2], 4).is_err());
+}
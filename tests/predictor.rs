@@ -0,0 +1,213 @@
+mod common;
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use common::write_and_read;
+use tiffy::baseline::constants::{compression, photometic_interpretation, predictor};
+use tiffy::compression::{BlockDimensions, Predictor};
+use tiffy::image::{Image, ImageDecoder, ImageEncoder, PixelBuffer};
+use tiffy::lowlevel::{FieldTypeSizes, TiffFormat};
+
+/// A block of `height` rows of `row_bytes` bytes each.
+fn block(row_bytes: usize, height: u32) -> BlockDimensions {
+    BlockDimensions {
+        width: 0,
+        height,
+        row_bytes,
+    }
+}
+
+/// Apply `predictor` to `data`, check the result against `expected`, then undo it again.
+fn check(predictor: &Predictor, data: &[u8], row_bytes: usize, expected: &[u8]) {
+    let block = block(row_bytes, (data.len() / row_bytes) as u32);
+    let mut predicted = data.to_vec();
+    predictor.encode(&mut predicted, &block).unwrap();
+    assert_eq!(predicted, expected);
+    predictor.decode(&mut predicted, &block).unwrap();
+    assert_eq!(predicted, data);
+}
+
+fn horizontal(samples_per_pixel: u16, bits_per_sample: u16, little_endian: bool) -> Predictor {
+    Predictor {
+        predictor: predictor::HORIZONTAL_DIFFERENCING,
+        samples_per_pixel,
+        bits_per_sample,
+        little_endian,
+    }
+}
+
+fn floating_point(samples_per_pixel: u16, bits_per_sample: u16, little_endian: bool) -> Predictor {
+    Predictor {
+        predictor: predictor::FLOATING_POINT,
+        samples_per_pixel,
+        bits_per_sample,
+        little_endian,
+    }
+}
+
+#[test]
+fn differences_8_bit_samples() {
+    // Each row is differenced separately, and wraps around
+    check(
+        &horizontal(1, 8, false),
+        &[10, 12, 15, 0, 200, 100, 100, 255],
+        4,
+        &[10, 2, 3, 241, 200, 156, 0, 155],
+    );
+    // Samples are differenced against the same sample of the previous pixel
+    check(
+        &horizontal(3, 8, true),
+        &[1, 2, 3, 4, 6, 8, 4, 6, 8],
+        9,
+        &[1, 2, 3, 3, 4, 5, 0, 0, 0],
+    );
+}
+
+/// Write `samples` as `bits`-bit integers in byte order `E`.
+fn integers<E: ByteOrder>(samples: &[u64], bits: u16) -> Vec<u8> {
+    let size = usize::from(bits / 8);
+    let mut bytes = vec![0; samples.len() * size];
+    for (chunk, &sample) in bytes.chunks_exact_mut(size).zip(samples) {
+        E::write_uint(chunk, sample, size);
+    }
+    bytes
+}
+
+#[test]
+fn differences_16_and_32_bit_samples_in_both_byte_orders() {
+    let samples = [0x0100, 0x0102, 0x00FF, 0x8000];
+    let differences = [0x0100, 2, 0xFFFD, 0x7F01];
+    check(
+        &horizontal(1, 16, true),
+        &integers::<LittleEndian>(&samples, 16),
+        8,
+        &integers::<LittleEndian>(&differences, 16),
+    );
+    check(
+        &horizontal(1, 16, false),
+        &integers::<BigEndian>(&samples, 16),
+        8,
+        &integers::<BigEndian>(&differences, 16),
+    );
+
+    // Two samples per pixel, across two rows
+    let samples = [1, 0x1_0000, 0, 0x1_0001, 5, 7, 4, 9];
+    let differences = [1, 0x1_0000, 0xFFFF_FFFF, 1, 5, 7, 0xFFFF_FFFF, 2];
+    check(
+        &horizontal(2, 32, true),
+        &integers::<LittleEndian>(&samples, 32),
+        16,
+        &integers::<LittleEndian>(&differences, 32),
+    );
+    check(
+        &horizontal(2, 32, false),
+        &integers::<BigEndian>(&samples, 32),
+        16,
+        &integers::<BigEndian>(&differences, 32),
+    );
+}
+
+#[test]
+fn differences_shuffled_f32_bytes() {
+    // 1.0 and 2.0 are 3F800000 and 40000000. Their bytes are shuffled into planes of equal
+    // significance and then differenced, whatever the byte order of the file
+    let expected = [0x3F, 0x01, 0x40, 0x80, 0x00, 0x00, 0x00, 0x00];
+    let mut little = vec![0; 8];
+    LittleEndian::write_f32_into(&[1.0, 2.0], &mut little);
+    check(&floating_point(1, 32, true), &little, 8, &expected);
+    let mut big = vec![0; 8];
+    BigEndian::write_f32_into(&[1.0, 2.0], &mut big);
+    check(&floating_point(1, 32, false), &big, 8, &expected);
+}
+
+#[test]
+fn round_trips_f64_samples_in_both_byte_orders() {
+    let samples = [0.0, -1.5, 1.0e300, f64::MIN_POSITIVE, 3.25, -0.0];
+    for &little_endian in &[true, false] {
+        let mut data = vec![0; 48];
+        if little_endian {
+            LittleEndian::write_f64_into(&samples, &mut data);
+        } else {
+            BigEndian::write_f64_into(&samples, &mut data);
+        }
+        // Three pixels of two samples in a single row, and two rows of three pixels
+        for &(samples_per_pixel, row_bytes) in &[(2, 48), (1, 24)] {
+            let predictor = floating_point(samples_per_pixel, 64, little_endian);
+            let block = block(row_bytes, (48 / row_bytes) as u32);
+            let mut predicted = data.clone();
+            predictor.encode(&mut predicted, &block).unwrap();
+            assert_ne!(predicted, data);
+            predictor.decode(&mut predicted, &block).unwrap();
+            assert_eq!(predicted, data);
+        }
+    }
+}
+
+#[test]
+fn rejects_unsupported_sample_sizes() {
+    let mut data = vec![0; 8];
+    assert!(horizontal(1, 12, false)
+        .encode(&mut data, &block(8, 1))
+        .is_err());
+    assert!(floating_point(1, 8, false)
+        .decode(&mut data, &block(8, 1))
+        .is_err());
+}
+
+/// Encode `image` with `encoder` in byte order `E` and decode it again in the same layout.
+fn round_trip<E: ByteOrder>(encoder: &ImageEncoder, image: &Image) -> Image {
+    let (mut file, reader) = write_and_read::<E>(
+        TiffFormat::Classic,
+        FieldTypeSizes::new(),
+        |metadata_writer, file| {
+            let ifd = encoder.encode::<E, _>(image, file).unwrap();
+            metadata_writer.write_ifd(&ifd, file).unwrap();
+        },
+    );
+    let ifd = reader.ifds().next().unwrap();
+    ImageDecoder::new(ifd, reader.is_little_endian())
+        .unwrap()
+        .with_planar_configuration(image.planar_configuration)
+        .decode(&mut file)
+        .unwrap()
+}
+
+/// An RGB image of `width` by `height` pixels holding `data`.
+fn rgb(width: u32, height: u32, planar_configuration: u16, data: PixelBuffer) -> Image {
+    Image {
+        width,
+        height,
+        samples_per_pixel: 3,
+        photometric: photometic_interpretation::RGB,
+        planar_configuration,
+        data,
+    }
+}
+
+#[test]
+fn round_trips_chunky_and_planar_images() {
+    let (width, height) = (13, 7);
+    let count = (width * height * 3) as usize;
+    let integers: Vec<u16> = (0..count).map(|i| (i * 997 % 65536) as u16).collect();
+    let floats: Vec<f32> = (0..count).map(|i| (i as f32).sin() * 100.0).collect();
+    let doubles: Vec<f64> = (0..count).map(|i| (i as f64).cos() / 3.0).collect();
+    let images = [
+        (
+            predictor::HORIZONTAL_DIFFERENCING,
+            PixelBuffer::U16(integers),
+        ),
+        (predictor::FLOATING_POINT, PixelBuffer::F32(floats)),
+        (predictor::FLOATING_POINT, PixelBuffer::F64(doubles)),
+    ];
+    for (predictor, data) in &images {
+        for &planar_configuration in &[1, 2] {
+            let image = rgb(width, height, planar_configuration, data.clone());
+            let encoder = ImageEncoder::new()
+                .with_compression(compression::ADOBE_DEFLATE)
+                .unwrap()
+                .with_predictor(*predictor)
+                .with_strip_bytes(200);
+            assert_eq!(round_trip::<LittleEndian>(&encoder, &image), image);
+            assert_eq!(round_trip::<BigEndian>(&encoder, &image), image);
+        }
+    }
+}
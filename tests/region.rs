@@ -0,0 +1,136 @@
+mod common;
+
+use byteorder::LittleEndian;
+use common::write_and_read;
+use std::io::Cursor;
+use tiffy::baseline::constants::{compression, photometic_interpretation, planar_configuration};
+use tiffy::image::{Image, ImageDecoder, ImageEncoder, PixelBuffer};
+use tiffy::lowlevel::{FieldTypeSizes, TiffFormat, IFD};
+
+/// An interleaved RGB image of `width` by `height` pixels with distinct 16-bit samples.
+fn test_image(width: u32, height: u32) -> Image {
+    let count = (width * height * 3) as usize;
+    Image {
+        width,
+        height,
+        samples_per_pixel: 3,
+        photometric: photometic_interpretation::RGB,
+        planar_configuration: planar_configuration::CHUNKY,
+        data: PixelBuffer::U16((1..=count).map(|i| i as u16).collect()),
+    }
+}
+
+/// Encode `image` with `encoder` into a new file, returning it along with the IFD read back.
+fn encode(encoder: &ImageEncoder, image: &Image) -> (Cursor<Vec<u8>>, IFD) {
+    let (file, reader) = write_and_read::<LittleEndian>(
+        TiffFormat::Classic,
+        FieldTypeSizes::new(),
+        |metadata_writer, file| {
+            let ifd = encoder.encode::<LittleEndian, _>(image, file).unwrap();
+            metadata_writer.write_ifd(&ifd, file).unwrap();
+        },
+    );
+    let ifd = reader.ifds().next().unwrap().clone();
+    (file, ifd)
+}
+
+/// The `width` by `height` pixels of the interleaved 16-bit `image` at (`x`, `y`), with samples
+/// of `fill` for pixels outside of the image.
+fn crop(image: &Image, (x, y, width, height): (u32, u32, u32, u32), fill: u16) -> Image {
+    let samples = match &image.data {
+        PixelBuffer::U16(samples) => samples,
+        _ => unreachable!(),
+    };
+    let mut data = Vec::new();
+    for row in y..y + height {
+        for column in x..x + width {
+            for sample in 0..3 {
+                data.push(if row < image.height && column < image.width {
+                    samples[((row * image.width + column) * 3 + sample) as usize]
+                } else {
+                    fill
+                });
+            }
+        }
+    }
+    Image {
+        width,
+        height,
+        data: PixelBuffer::U16(data),
+        ..image.clone()
+    }
+}
+
+/// Regions of a 45 by 37 image: within a single block, crossing strip and tile boundaries,
+/// touching the right and bottom edges, and overhanging them.
+const REGIONS: [(u32, u32, u32, u32); 9] = [
+    (0, 0, 45, 37),
+    (2, 1, 3, 2),
+    (10, 3, 12, 9),
+    (15, 15, 2, 2),
+    (14, 5, 20, 30),
+    (40, 0, 5, 37),
+    (0, 33, 45, 4),
+    (44, 36, 1, 1),
+    (30, 30, 30, 30),
+];
+
+#[test]
+fn reads_regions_like_the_full_image() {
+    let image = test_image(45, 37);
+    let encoders = [
+        // Strips of 4 rows
+        ImageEncoder::new().with_strip_bytes(45 * 6 * 4),
+        ImageEncoder::new()
+            .with_compression(compression::LZW)
+            .unwrap()
+            .with_tiles(16, 16)
+            .unwrap(),
+    ];
+    for encoder in &encoders {
+        for source in &[image.clone(), image.to_planar()] {
+            let (mut file, ifd) = encode(encoder, source);
+            let decoder = ImageDecoder::new(&ifd, true).unwrap();
+            let full = decoder.decode(&mut file).unwrap();
+            assert_eq!(full, image);
+            for &(x, y, width, height) in &REGIONS {
+                let expected = crop(&full, (x, y, width, height), 0);
+                let region = decoder.read_region(&mut file, x, y, width, height).unwrap();
+                assert_eq!(region, expected, "region {:?}", (x, y, width, height));
+
+                // Copying into a buffer leaves the pixels outside of the image untouched
+                let mut buffer = PixelBuffer::U16(vec![7; (width * height * 3) as usize]);
+                decoder
+                    .read_region_into(&mut file, x, y, width, height, &mut buffer)
+                    .unwrap();
+                assert_eq!(buffer, crop(&full, (x, y, width, height), 7).data);
+
+                // Planar output holds the same pixels
+                let planar = ImageDecoder::new(&ifd, true)
+                    .unwrap()
+                    .with_planar_configuration(planar_configuration::PLANAR);
+                let region = planar.read_region(&mut file, x, y, width, height).unwrap();
+                assert_eq!(region, expected.to_planar());
+            }
+        }
+    }
+}
+
+#[test]
+fn reads_empty_regions() {
+    let image = test_image(45, 37);
+    let encoder = ImageEncoder::new().with_tiles(16, 16).unwrap();
+    let (mut file, ifd) = encode(&encoder, &image);
+    let decoder = ImageDecoder::new(&ifd, true).unwrap();
+    for &(x, y, width, height) in &[(0, 0, 0, 0), (10, 10, 0, 5), (10, 10, 5, 0), (50, 50, 0, 0)] {
+        let region = decoder.read_region(&mut file, x, y, width, height).unwrap();
+        assert_eq!((region.width, region.height), (width, height));
+        assert!(region.data.is_empty());
+
+        let mut buffer = PixelBuffer::U16(Vec::new());
+        decoder
+            .read_region_into(&mut file, x, y, width, height, &mut buffer)
+            .unwrap();
+        assert!(buffer.is_empty());
+    }
+}
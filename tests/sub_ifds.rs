@@ -0,0 +1,261 @@
+mod common;
+
+use byteorder::{BigEndian, LittleEndian, WriteBytesExt};
+use common::{round_trip, write_and_read};
+use std::io::{Cursor, Seek, SeekFrom};
+use tiffy::baseline::{constants::photometic_interpretation, tags};
+use tiffy::image::{Image, ImageDecoder, ImageEncoder, PixelBuffer};
+use tiffy::lowlevel::{
+    FieldTypeSizes, IFDField, MetadataReader, MetadataWriter, TiffFormat, IFD, MAX_IFD_DEPTH,
+};
+
+/// An IFD holding only an `IMAGE_WIDTH` of `width`, used to tell IFDs apart.
+fn ifd(width: u32) -> IFD {
+    let mut ifd = IFD::new();
+    ifd.entries
+        .insert(tags::IMAGE_WIDTH, IFDField::Long(Box::new([width])));
+    ifd
+}
+
+/// Write `ifd` at the end of `file` without linking it into any chain, followed by a pointer to
+/// `next`. Returns the offset of the IFD.
+fn write_detached(file: &mut Cursor<Vec<u8>>, ifd: &IFD, next: u32) -> u32 {
+    file.seek(SeekFrom::End(0)).unwrap();
+    let raw_ifd = ifd
+        .write_to::<LittleEndian, _>(file, TiffFormat::Classic)
+        .unwrap();
+    let offset = file.stream_position().unwrap() as u32;
+    raw_ifd
+        .write_to::<LittleEndian, _>(file, TiffFormat::Classic)
+        .unwrap();
+    file.write_u32::<LittleEndian>(next).unwrap();
+    offset
+}
+
+/// A little-endian classic TIFF header whose first IFD is filled in by `link_first`.
+fn header() -> Cursor<Vec<u8>> {
+    Cursor::new(b"II\x2a\x00\x00\x00\x00\x00".to_vec())
+}
+
+/// Point the header of `file` to the IFD at `offset`.
+fn link_first(file: &mut Cursor<Vec<u8>>, offset: u32) {
+    file.get_mut()[4..8].copy_from_slice(&offset.to_le_bytes());
+    file.set_position(0);
+}
+
+/// Widths of the IFDs of `chain`.
+fn widths(chain: &[IFD]) -> Vec<u64> {
+    chain
+        .iter()
+        .map(|ifd| ifd.get::<Vec<u64>>(tags::IMAGE_WIDTH).unwrap()[0])
+        .collect()
+}
+
+#[test]
+fn reads_sub_ifd_trees() {
+    let mut file = header();
+    // A chain of two reduced-resolution images, and one with a sub-IFD of its own
+    let second = write_detached(&mut file, &ifd(2), 0);
+    let first = write_detached(&mut file, &ifd(1), second);
+    let grandchild = write_detached(&mut file, &ifd(4), 0);
+    let mut child = ifd(3);
+    child
+        .entries
+        .insert(tags::SUB_IFDS, IFDField::Long(Box::new([grandchild])));
+    let child = write_detached(&mut file, &child, 0);
+
+    // SubIFDs may also be stored with the IFD field type, which is read as unrecognized
+    let mut main = ifd(0);
+    main.entries.insert(
+        tags::SUB_IFDS,
+        IFDField::Opaque {
+            tag_type: 13,
+            count: 2,
+            data: [first.to_le_bytes(), child.to_le_bytes()].concat().into(),
+        },
+    );
+    let main = write_detached(&mut file, &main, 0);
+    link_first(&mut file, main);
+
+    let reader = MetadataReader::read_header(&mut file).unwrap();
+    let ifds: Vec<&IFD> = reader.ifds().collect();
+    assert_eq!(ifds.len(), 1);
+    let sub_ifds = ifds[0].sub_ifds();
+    assert_eq!(sub_ifds.len(), 2);
+    assert_eq!(widths(&sub_ifds[0]), [1, 2]);
+    assert_eq!(widths(&sub_ifds[1]), [3]);
+    assert_eq!(widths(&sub_ifds[1][0].sub_ifds()[0]), [4]);
+    assert!(sub_ifds[0][0].sub_ifds().is_empty());
+}
+
+#[test]
+fn stops_at_cycles_through_sub_ifds() {
+    let mut file = header();
+    // The main IFD is its own sub-IFD, and the child points back to the main IFD. The main IFD
+    // follows the header, the child IFD and its own SubIFDs data
+    let main_offset = 8 + (2 + 2 * 12 + 4) + 8;
+    let mut child = ifd(1);
+    child
+        .entries
+        .insert(tags::SUB_IFDS, IFDField::Long(Box::new([main_offset])));
+    let child = write_detached(&mut file, &child, main_offset);
+    let mut main = ifd(0);
+    main.entries.insert(
+        tags::SUB_IFDS,
+        IFDField::Long(Box::new([main_offset, child])),
+    );
+    assert_eq!(write_detached(&mut file, &main, 0), main_offset);
+    link_first(&mut file, main_offset);
+
+    let reader = MetadataReader::read_header(&mut file).unwrap();
+    let ifds: Vec<&IFD> = reader.ifds().collect();
+    assert_eq!(ifds.len(), 1);
+    let sub_ifds = ifds[0].sub_ifds();
+    assert_eq!(sub_ifds.len(), 1);
+    assert_eq!(widths(&sub_ifds[0]), [1]);
+    assert!(sub_ifds[0][0].sub_ifds().is_empty());
+}
+
+/// A grayscale gradient of `width` by `height` pixels.
+fn gradient(width: u32, height: u32) -> Image {
+    Image {
+        width,
+        height,
+        samples_per_pixel: 1,
+        photometric: photometic_interpretation::BLACKISZERO,
+        planar_configuration: 1,
+        data: PixelBuffer::U8(
+            (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x * 3 + y * 5) as u8))
+                .collect(),
+        ),
+    }
+}
+
+#[test]
+fn writes_sub_ifd_trees() {
+    for &format in &[TiffFormat::Classic, TiffFormat::BigTiff] {
+        let images = [gradient(64, 48), gradient(32, 24), gradient(16, 12)];
+        let encoder = ImageEncoder::new().with_strip_bytes(256);
+        let (mut file, reader) =
+            write_and_read::<BigEndian>(format, FieldTypeSizes::new(), |metadata_writer, file| {
+                let mut ifds: Vec<IFD> = images
+                    .iter()
+                    .map(|image| encoder.encode::<BigEndian, _>(image, file).unwrap())
+                    .collect();
+                // A pyramid of two reduced-resolution images, and a second chain holding a tree
+                // of its own
+                let mut thumbnail = ifd(7);
+                thumbnail.add_sub_ifds(vec![ifd(8)]);
+                let reduced = ifds.split_off(1);
+                ifds[0].add_sub_ifds(reduced);
+                ifds[0].add_sub_ifds(vec![thumbnail]);
+                metadata_writer.write_ifd(&ifds[0], file).unwrap();
+                metadata_writer.write_ifd(&ifd(9), file).unwrap();
+            });
+        let top: Vec<&IFD> = reader.ifds().collect();
+        assert_eq!(top.len(), 2);
+        assert_eq!(widths(&[top[1].clone()]), [9]);
+        let sub_ifds = top[0].sub_ifds();
+        assert_eq!(sub_ifds.len(), 2);
+        assert_eq!(widths(&sub_ifds[0]), [32, 16]);
+        assert_eq!(widths(&sub_ifds[1]), [7]);
+        assert_eq!(widths(&sub_ifds[1][0].sub_ifds()[0]), [8]);
+
+        let decode = |ifd: &IFD, file: &mut Cursor<Vec<u8>>| {
+            ImageDecoder::new(ifd, false).unwrap().decode(file).unwrap()
+        };
+        assert_eq!(decode(top[0], &mut file), images[0]);
+        assert_eq!(decode(&sub_ifds[0][0], &mut file), images[1]);
+        assert_eq!(decode(&sub_ifds[0][1], &mut file), images[2]);
+    }
+}
+
+#[test]
+fn rewrites_sub_ifds_read_from_a_file() {
+    let mut file = header();
+    let grandchild = write_detached(&mut file, &ifd(2), 0);
+    let mut child = ifd(1);
+    child
+        .entries
+        .insert(tags::SUB_IFDS, IFDField::Long(Box::new([grandchild])));
+    let child = write_detached(&mut file, &child, 0);
+    let mut main = ifd(0);
+    main.entries
+        .insert(tags::SUB_IFDS, IFDField::Long(Box::new([child])));
+    let main = write_detached(&mut file, &main, 0);
+    link_first(&mut file, main);
+    let reader = MetadataReader::read_header(&mut file).unwrap();
+
+    // Offsets from the source file are replaced by those of the rewritten children
+    let mut copy = Cursor::new(vec![0xAA; 100]);
+    let mut metadata_writer = MetadataWriter::<LittleEndian>::write_header(&mut copy).unwrap();
+    copy.seek(SeekFrom::End(0)).unwrap();
+    for ifd in reader.ifds() {
+        metadata_writer.write_ifd(ifd, &mut copy).unwrap();
+    }
+    let mut copy = Cursor::new(copy.into_inner());
+    let rewritten = MetadataReader::read_header(&mut copy).unwrap();
+    let main = rewritten.ifds().next().unwrap();
+    assert_ne!(main.get::<u32>(tags::SUB_IFDS).unwrap(), child);
+    assert_eq!(widths(&main.sub_ifds()[0]), [1]);
+    assert_eq!(widths(&main.sub_ifds()[0][0].sub_ifds()[0]), [2]);
+}
+
+#[test]
+fn keeps_pointers_to_ifds_read_elsewhere() {
+    // The SubIFD of the main IFD is also the next IFD of the main chain
+    let mut file = header();
+    let next = write_detached(&mut file, &ifd(1), 0);
+    let mut main = ifd(0);
+    main.entries
+        .insert(tags::SUB_IFDS, IFDField::Long(Box::new([next])));
+    let main = write_detached(&mut file, &main, next);
+    link_first(&mut file, main);
+
+    let reader = MetadataReader::read_header(&mut file).unwrap();
+    let ifds: Vec<IFD> = reader.ifds().cloned().collect();
+    assert_eq!(widths(&ifds), [0, 1]);
+    assert!(ifds[0].children.is_empty());
+
+    // The pointer tag is written as it was, rather than dropped
+    let rewritten = round_trip::<LittleEndian>(&ifds, TiffFormat::Classic, FieldTypeSizes::new());
+    let main = rewritten.ifds().next().unwrap();
+    assert_eq!(main.get::<u32>(tags::SUB_IFDS).unwrap(), next);
+}
+
+/// A file whose main IFD is the root of a tree nesting `depth` levels of SubIFDs beneath it.
+fn nested(depth: u32) -> Cursor<Vec<u8>> {
+    let mut file = header();
+    let mut offset = write_detached(&mut file, &ifd(depth), 0);
+    for level in (0..depth).rev() {
+        let mut parent = ifd(level);
+        parent
+            .entries
+            .insert(tags::SUB_IFDS, IFDField::Long(Box::new([offset])));
+        offset = write_detached(&mut file, &parent, 0);
+    }
+    link_first(&mut file, offset);
+    file
+}
+
+#[test]
+fn limits_the_depth_of_sub_ifd_trees() {
+    let mut file = nested(MAX_IFD_DEPTH as u32);
+    let reader = MetadataReader::read_header(&mut file).unwrap();
+    let mut ifd = reader.ifds().next().unwrap();
+    for _ in 0..MAX_IFD_DEPTH {
+        ifd = &ifd.sub_ifds()[0][0];
+    }
+    assert_eq!(widths(&[ifd.clone()]), [MAX_IFD_DEPTH as u64]);
+
+    // The pointer tags of IFDs nested any deeper are left unfollowed
+    let mut file = nested(MAX_IFD_DEPTH as u32 + 1);
+    let reader = MetadataReader::read_header(&mut file).unwrap();
+    let mut ifd = reader.ifds().next().unwrap();
+    for _ in 0..MAX_IFD_DEPTH {
+        ifd = &ifd.sub_ifds()[0][0];
+    }
+    assert!(ifd.sub_ifds().is_empty());
+    assert!(ifd.entries.contains_key(&tags::SUB_IFDS));
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use byteorder::BigEndian;
+use common::write_and_read;
+use tiffy::baseline::{
+    constants::{compression, photometic_interpretation, planar_configuration},
+    tags,
+};
+use tiffy::image::{Image, ImageDecoder, ImageEncoder, PixelBuffer, YCbCr};
+use tiffy::lowlevel::{FieldTypeSizes, TiffFormat, IFD};
+
+/// Subsampling factors allowed by the TIFF specification, other than 1x1.
+const SUBSAMPLINGS: [(u16, u16); 4] = [(2, 1), (2, 2), (4, 2), (4, 4)];
+
+/// A YCbCr image of `width` by `height` pixels with varying luma. Chroma is uniform unless
+/// `varying_chroma`, as subsampling can only preserve uniform chroma.
+fn test_image(width: u32, height: u32, varying_chroma: bool) -> Image {
+    let data = (0..width * height)
+        .flat_map(|i| {
+            let luma = (i * 37 % 256) as u8;
+            if varying_chroma {
+                [luma, (i * 11 % 256) as u8, (i * 71 % 256) as u8]
+            } else {
+                [luma, 90, 170]
+            }
+        })
+        .collect();
+    Image {
+        width,
+        height,
+        samples_per_pixel: 3,
+        photometric: photometic_interpretation::YCBCR,
+        planar_configuration: planar_configuration::CHUNKY,
+        data: PixelBuffer::U8(data),
+    }
+}
+
+/// Encode `image` with `encoder` into a new file and decode it again, returning the decoded
+/// image and the IFD describing it.
+fn round_trip(encoder: &ImageEncoder, image: &Image) -> (Image, IFD) {
+    let (mut file, reader) = write_and_read::<BigEndian>(
+        TiffFormat::Classic,
+        FieldTypeSizes::new(),
+        |metadata_writer, file| {
+            let ifd = encoder.encode::<BigEndian, _>(image, file).unwrap();
+            metadata_writer.write_ifd(&ifd, file).unwrap();
+        },
+    );
+    let ifd = reader.ifds().next().unwrap().clone();
+    let decoded = ImageDecoder::new(&ifd, false)
+        .unwrap()
+        .decode(&mut file)
+        .unwrap();
+    (decoded, ifd)
+}
+
+fn ycbcr(subsampling: (u16, u16)) -> YCbCr {
+    YCbCr {
+        subsampling,
+        ..YCbCr::new(8)
+    }
+}
+
+#[test]
+fn round_trips_without_subsampling() {
+    let image = test_image(13, 11, true);
+    let encoder = ImageEncoder::new()
+        .with_ycbcr(ycbcr((1, 1)))
+        .with_strip_bytes(100);
+    let (decoded, _) = round_trip(&encoder, &image);
+    assert_eq!(decoded, image);
+}
+
+#[test]
+fn round_trips_subsampled_strips() {
+    // Sizes which are not multiples of any block size
+    for &(width, height) in &[(13, 11), (7, 5), (1, 1), (6, 9)] {
+        let image = test_image(width, height, false);
+        for &subsampling in &SUBSAMPLINGS {
+            // Strips of a single row, unless rounded up to whole rows of data units
+            let encoder = ImageEncoder::new()
+                .with_compression(compression::LZW)
+                .unwrap()
+                .with_ycbcr(ycbcr(subsampling))
+                .with_strip_bytes(1);
+            let rows_per_strip = encoder.rows_per_strip(&image);
+            assert_eq!(
+                rows_per_strip,
+                u32::from(subsampling.1).min(height),
+                "{:?} subsampling of {}x{}",
+                subsampling,
+                width,
+                height
+            );
+
+            let (decoded, ifd) = round_trip(&encoder, &image);
+            assert_eq!(
+                ifd.get::<u32>(tags::ROWS_PER_STRIP).unwrap(),
+                rows_per_strip
+            );
+            assert_eq!(
+                ifd.get::<Vec<u64>>(tags::Y_CBCR_SUB_SAMPLING).unwrap(),
+                [u64::from(subsampling.0), u64::from(subsampling.1)]
+            );
+            assert_eq!(
+                decoded, image,
+                "{:?} subsampling of {}x{}",
+                subsampling, width, height
+            );
+        }
+    }
+}
+
+#[test]
+fn rounds_strips_up_to_whole_data_units() {
+    let image = test_image(10, 30, false);
+    // 10 * 3 = 30 bytes per row, so 5 rows fit in 150 bytes
+    for &(subsampling, rows) in &[((1, 1), 5), ((2, 1), 5), ((2, 2), 6), ((4, 4), 8)] {
+        let encoder = ImageEncoder::new()
+            .with_ycbcr(ycbcr(subsampling))
+            .with_strip_bytes(150);
+        assert_eq!(encoder.rows_per_strip(&image), rows);
+        assert_eq!(round_trip(&encoder, &image).0, image);
+    }
+
+    // Images other than YCbCr are unaffected
+    let rgb = Image {
+        photometric: photometic_interpretation::RGB,
+        ..image
+    };
+    let encoder = ImageEncoder::new()
+        .with_ycbcr(ycbcr((4, 4)))
+        .with_strip_bytes(150);
+    assert_eq!(encoder.rows_per_strip(&rgb), 5);
+}
+
+#[test]
+fn round_trips_subsampled_tiles() {
+    let image = test_image(35, 21, false);
+    for &subsampling in &SUBSAMPLINGS {
+        let encoder = ImageEncoder::new()
+            .with_ycbcr(ycbcr(subsampling))
+            .with_tiles(16, 16)
+            .unwrap();
+        assert_eq!(round_trip(&encoder, &image).0, image);
+    }
+}